@@ -2,35 +2,20 @@
 #![no_main]
 #![feature(type_alias_impl_trait)]
 
-use byte::BytesExt;
 use defmt;
 use embassy_executor::Spawner;
 use esp_backtrace as _;
 use esp_ieee802154;
 use hal::{clock::ClockControl, embassy, peripherals::Peripherals, prelude::*, timer::TimerGroup};
-use ieee802154::mac::{self, FooterMode};
 
-use psila_data::common::key::Key;
-
-use esp32c6_psila::Parser;
+use esp32c6_psila::pcap::{LinkType, PcapWriter, SerialSink};
+use esp32c6_psila::radio::{EspRadio, Radio};
+use esp32c6_psila::survey::{Survey, SurveyConfig};
+use esp32c6_psila::{parse_key, parse_link_key, print_frame, print_survey, Parser};
 
 const NETWORK_KEY: &str = env!("NETWORK_KEY");
-
-fn key_from_str(s: &str) -> Result<Key, ()> {
-    if s.len() != 32 {
-        return Err(());
-    }
-    let mut offset = 0;
-    let mut key = [0u8; 16];
-    for byte in key.iter_mut().take(16) {
-        *byte = match u8::from_str_radix(&s[offset..offset + 2], 16) {
-            Ok(v) => v,
-            Err(_) => return Err(()),
-        };
-        offset += 2;
-    }
-    Ok(Key::from(key))
-}
+const LINK_KEY: Option<&str> = option_env!("LINK_KEY");
+const CHANNEL: u8 = 25;
 
 #[main]
 async fn main(_spawner: Spawner) -> ! {
@@ -41,46 +26,72 @@ async fn main(_spawner: Spawner) -> ! {
     let timer_group0 = TimerGroup::new(peripherals.TIMG0, &clocks);
 
     let radio = peripherals.IEEE802154;
-    let mut ieee802154 = esp_ieee802154::Ieee802154::new(radio, &mut system.radio_clock_control);
-
-    ieee802154.set_config(esp_ieee802154::Config {
-        channel: 25,
-        promiscuous: true,
-        rx_when_idle: true,
-        auto_ack_rx: false,
-        auto_ack_tx: false,
-        ..esp_ieee802154::Config::default()
-    });
+    let ieee802154 = esp_ieee802154::Ieee802154::new(radio, &mut system.radio_clock_control);
+    let mut radio = match EspRadio::new(ieee802154, CHANNEL) {
+        Ok(radio) => radio,
+        Err(_) => panic!("Invalid channel {}", CHANNEL),
+    };
 
     embassy::init(&clocks, timer_group0);
 
+    if option_env!("SURVEY").is_some() {
+        defmt::info!("start survey");
+        let mut survey = Survey::new(SurveyConfig::default());
+        match survey.run(&mut radio) {
+            Ok(summary) => print_survey(summary),
+            Err(_) => defmt::error!("Survey failed"),
+        }
+        if radio.set_channel(CHANNEL).is_err() {
+            defmt::error!("Failed to set channel");
+        }
+    }
+
+    if option_env!("PCAP").is_some() {
+        defmt::info!("start streaming capture");
+        let mut writer = match PcapWriter::new(SerialSink, LinkType::Ieee802154Tap) {
+            Ok(writer) => writer,
+            Err(never) => match never {},
+        };
+        loop {
+            if let Ok(Some(received)) = radio.receive() {
+                // Writing to the serial port can not fail
+                let _ = writer.write_frame(&received.metadata, &received.data);
+            }
+        }
+    }
+
     let mut parser = Parser::new();
 
-    match key_from_str(NETWORK_KEY) {
+    match parse_key(NETWORK_KEY) {
         Ok(key) => {
             defmt::info!("Added network key");
-            parser.security.add_key(key);
+            parser.security.add_network_key(key, None);
+        }
+        Err(_) => defmt::warn!("Invalid network key"),
+    }
+    if let Some(link_key) = LINK_KEY {
+        match parse_link_key(link_key) {
+            Ok((key, address)) => {
+                defmt::info!("Added link key");
+                parser.security.add_link_key(key, address);
+            }
+            Err(_) => defmt::warn!("Invalid link key"),
         }
-        Err(_) => (),
     }
 
     defmt::info!("start receiving");
-    ieee802154.start_receive();
 
     loop {
-        if let Some(received) = ieee802154.get_raw_received() {
-            let size = usize::from(received.data[0]);
-            let _rssi = usize::from(received.data[size]);
-            let part = &received.data[1..(size - 1)];
-            defmt::info!("Received {=[u8]:02x}\n", part);
-            match part.read_with::<mac::Frame>(&mut 0, FooterMode::None) {
-                Ok(frame) => {
-                    parser.parse_802154_mac(&frame);
-                }
-                Err(_) => {
-                    defmt::error!("Failed to receive frame\n");
+        match radio.receive() {
+            Ok(Some(received)) => {
+                defmt::info!("Received {=[u8]:02x}\n", &received.data[..]);
+                match parser.parse_frame(&received.data, &received.metadata) {
+                    Some(decoded) => print_frame(&decoded),
+                    None => defmt::error!("Failed to parse frame\n"),
                 }
             }
+            Ok(None) => (),
+            Err(_) => defmt::error!("Failed to receive frame\n"),
         }
     }
 }
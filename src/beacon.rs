@@ -0,0 +1,62 @@
+//! Zigbee beacon payload, carried in IEEE 802.15.4 beacons sent by coordinators and routers.
+
+use psila_data::Error;
+
+use crate::reader::ByteReader;
+
+/// Protocol identifier of Zigbee beacons
+pub const PROTOCOL_IDENTIFIER: u8 = 0x00;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BeaconPayload {
+    pub protocol_identifier: u8,
+    /// Stack profile, 1 for Zigbee and 2 for Zigbee PRO
+    pub stack_profile: u8,
+    pub protocol_version: u8,
+    /// Accepts routers joining
+    pub router_capacity: bool,
+    /// Depth of the sender in the network, 0 for the coordinator
+    pub device_depth: u8,
+    /// Accepts end devices joining
+    pub end_device_capacity: bool,
+    pub extended_pan_identifier: u64,
+    /// Time difference between the sender and its parent in symbols, Zigbee 2006 and later
+    pub tx_offset: Option<u32>,
+    /// nwkUpdateId, incremented on network configuration changes such as channel changes
+    pub update_identifier: Option<u8>,
+}
+
+impl BeaconPayload {
+    pub fn unpack(data: &[u8]) -> Result<Self, Error> {
+        let mut reader = ByteReader::new(data);
+        let protocol_identifier = reader.u8()?;
+        if protocol_identifier != PROTOCOL_IDENTIFIER {
+            return Err(Error::InvalidValue);
+        }
+        let profile = reader.u8()?;
+        let capacity = reader.u8()?;
+        let extended_pan_identifier = reader.u64()?;
+        let (tx_offset, update_identifier) = if reader.is_empty() {
+            (None, None)
+        } else {
+            let tx_offset = reader.uint(3)? as u32;
+            (Some(tx_offset), Some(reader.u8()?))
+        };
+        Ok(BeaconPayload {
+            protocol_identifier,
+            stack_profile: profile & 0x0f,
+            protocol_version: profile >> 4,
+            router_capacity: capacity & 0x04 == 0x04,
+            device_depth: (capacity >> 3) & 0x0f,
+            end_device_capacity: capacity & 0x80 == 0x80,
+            extended_pan_identifier,
+            tx_offset,
+            update_identifier,
+        })
+    }
+
+    /// Accepts any device joining
+    pub fn permits_joining(&self) -> bool {
+        self.router_capacity || self.end_device_capacity
+    }
+}
@@ -0,0 +1,272 @@
+//! Replay a pcap or pcapng capture of IEEE 802.15.4 frames through the parser.
+
+use std::process::ExitCode;
+
+use psila_data::ExtendedAddress;
+
+use esp32c6_psila::devices::DeviceTable;
+use esp32c6_psila::fcs::BadFcsPolicy;
+use esp32c6_psila::pcap::PcapReader;
+use esp32c6_psila::radio::ReplayRadio;
+use esp32c6_psila::snapshot::{Snapshot, MAX_SNAPSHOT_SIZE};
+use esp32c6_psila::survey::{Survey, SurveyConfig};
+use esp32c6_psila::topology::Topology;
+use esp32c6_psila::{
+    parse_key, parse_link_key, print_frame_with_devices, print_statistics, print_survey, Parser,
+};
+
+/// Parse `<IEEE>:<CODE>` where both parts are hexadecimal
+fn install_code_from_str(s: &str) -> Result<(ExtendedAddress, Vec<u8>), ()> {
+    let (address, code) = s.split_once(':').ok_or(())?;
+    let address = u64::from_str_radix(address, 16).map_err(|_| ())?;
+    // Slicing below is by byte
+    if !code.is_ascii() || code.len() % 2 != 0 {
+        return Err(());
+    }
+    let code = (0..code.len())
+        .step_by(2)
+        .map(|offset| u8::from_str_radix(&code[offset..offset + 2], 16))
+        .collect::<Result<Vec<u8>, _>>()
+        .map_err(|_| ())?;
+    Ok((ExtendedAddress::from(address), code))
+}
+
+fn usage() {
+    eprintln!(
+        "Usage: replay [--key <KEY>]... [--link-key <KEY>[@<IEEE>]]... [--install-code <IEEE>:<CODE>]... <CAPTURE>"
+    );
+    eprintln!();
+    eprintln!(
+        "  -k, --key <KEY>  Key used to decrypt secure payload, hexadecimal optionally separated"
+    );
+    eprintln!("                   by colons or spaces, or a Wireshark zigbee_pc_keys entry");
+    eprintln!("  -l, --link-key <KEY>[@<IEEE>]");
+    eprintln!(
+        "                   Trust center or application link key used to decrypt APS payload,"
+    );
+    eprintln!("                   optionally for the device with the IEEE address");
+    eprintln!("  -m, --mac-key <KEY>");
+    eprintln!("                   Key used to decrypt IEEE 802.15.4 MAC layer security");
+    eprintln!("  -i, --install-code <IEEE>:<CODE>");
+    eprintln!("                   Install code including CRC for the device with the IEEE address");
+    eprintln!("  -s, --state <FILE>");
+    eprintln!(
+        "                   Load learned keys from the file if it exists, store them when done"
+    );
+    eprintln!("  -t, --topology <FILE>");
+    eprintln!("                   Write the network topology as JSON or Graphviz DOT");
+    eprintln!("  -b, --bad-fcs <drop|flag|decode>");
+    eprintln!("                   Handling of frames with a bad FCS, flag by default");
+    eprintln!("      --survey     Summarise the traffic per channel instead of decoding frames");
+}
+
+fn main() -> ExitCode {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
+
+    let mut parser = Parser::new();
+    let mut path = None;
+    let mut state = None;
+    let mut topology_path = None;
+    let mut survey = false;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-k" | "--key" => match args.next().map(|k| parse_key(&k)) {
+                Some(Ok(key)) => parser.security.add_network_key(key, None),
+                Some(Err(e)) => {
+                    eprintln!("Invalid key, {:?}", e);
+                    return ExitCode::FAILURE;
+                }
+                None => {
+                    eprintln!("Missing key");
+                    return ExitCode::FAILURE;
+                }
+            },
+            "-l" | "--link-key" => match args.next().map(|k| parse_link_key(&k)) {
+                Some(Ok((key, address))) => parser.security.add_link_key(key, address),
+                Some(Err(e)) => {
+                    eprintln!("Invalid link key, {:?}", e);
+                    return ExitCode::FAILURE;
+                }
+                None => {
+                    eprintln!("Missing link key");
+                    return ExitCode::FAILURE;
+                }
+            },
+            "-m" | "--mac-key" => match args.next().map(|k| parse_key(&k)) {
+                Some(Ok(key)) => parser.security.add_mac_key(key, None),
+                _ => {
+                    eprintln!("Invalid MAC key");
+                    return ExitCode::FAILURE;
+                }
+            },
+            "-i" | "--install-code" => match args.next().map(|c| install_code_from_str(&c)) {
+                Some(Ok((address, code))) => {
+                    if let Err(e) = parser.security.add_install_code(address, &code) {
+                        eprintln!("Invalid install code, {:?}", e);
+                        return ExitCode::FAILURE;
+                    }
+                }
+                _ => {
+                    eprintln!("Invalid install code");
+                    return ExitCode::FAILURE;
+                }
+            },
+            "-s" | "--state" => match args.next() {
+                Some(file) => state = Some(file),
+                None => {
+                    eprintln!("Missing state file");
+                    return ExitCode::FAILURE;
+                }
+            },
+            "-t" | "--topology" => match args.next() {
+                Some(file) => topology_path = Some(file),
+                None => {
+                    eprintln!("Missing topology file");
+                    return ExitCode::FAILURE;
+                }
+            },
+            "-b" | "--bad-fcs" => {
+                parser.bad_fcs_policy = match args.next().as_deref() {
+                    Some("drop") => BadFcsPolicy::Drop,
+                    Some("flag") => BadFcsPolicy::Flag,
+                    Some("decode") => BadFcsPolicy::Decode,
+                    _ => {
+                        eprintln!("Invalid bad FCS policy");
+                        return ExitCode::FAILURE;
+                    }
+                }
+            }
+            "--survey" => survey = true,
+            "-h" | "--help" => {
+                usage();
+                return ExitCode::SUCCESS;
+            }
+            _ if arg.starts_with('-') => {
+                eprintln!("Unknown option {}", arg);
+                usage();
+                return ExitCode::FAILURE;
+            }
+            _ if path.is_some() => {
+                eprintln!("Unexpected argument {}", arg);
+                usage();
+                return ExitCode::FAILURE;
+            }
+            _ => path = Some(arg),
+        }
+    }
+    let Some(path) = path else {
+        usage();
+        return ExitCode::FAILURE;
+    };
+    if let Some(ref state) = state {
+        if let Ok(data) = std::fs::read(state) {
+            match Snapshot::decode(&data) {
+                Ok(snapshot) => parser.security.restore(&snapshot),
+                Err(e) => {
+                    eprintln!("Failed to load state {}, {:?}", state, e);
+                    return ExitCode::FAILURE;
+                }
+            }
+        }
+    }
+    let data = match std::fs::read(&path) {
+        Ok(data) => data,
+        Err(e) => {
+            eprintln!("Failed to read {}, {}", path, e);
+            return ExitCode::FAILURE;
+        }
+    };
+    if survey {
+        let mut radio = match ReplayRadio::new(&data) {
+            Ok(radio) => radio,
+            Err(e) => {
+                eprintln!("Failed to read {}, {:?}", path, e);
+                return ExitCode::FAILURE;
+            }
+        };
+        let mut survey = Survey::new(SurveyConfig::default());
+        return match survey.run(&mut radio) {
+            Ok(summary) => {
+                print_survey(summary);
+                ExitCode::SUCCESS
+            }
+            Err(e) => {
+                eprintln!("Failed to survey {}, {:?}", path, e);
+                ExitCode::FAILURE
+            }
+        };
+    }
+    let reader = match PcapReader::new(&data) {
+        Ok(reader) => reader,
+        Err(e) => {
+            eprintln!("Failed to read {}, {:?}", path, e);
+            return ExitCode::FAILURE;
+        }
+    };
+    let mut topology = Topology::new();
+    let mut devices = DeviceTable::new();
+    for (index, record) in reader.enumerate() {
+        let record = match record {
+            Ok(record) => record,
+            Err(e) => {
+                log::error!("Failed to read record {}, {:?}", index, e);
+                return ExitCode::FAILURE;
+            }
+        };
+        let Some(captured) = record.ieee802154_frame() else {
+            log::warn!(
+                "Record {} has unsupported link type {}",
+                index,
+                record.link_type
+            );
+            continue;
+        };
+        log::info!(
+            "Record {} {}.{:06} {:02x?}",
+            index,
+            record.timestamp / 1_000_000,
+            record.timestamp % 1_000_000,
+            captured.frame
+        );
+        match parser.parse_frame_with_devices(captured.frame, &captured.metadata, &devices) {
+            Some(decoded) => {
+                devices.update(&decoded);
+                print_frame_with_devices(&decoded, &devices);
+                topology.update(&decoded);
+            }
+            None if captured.metadata.fcs.is_bad() => {
+                log::warn!("Dropped frame {} with bad FCS", index);
+            }
+            None => {
+                log::error!("Failed to parse frame {}", index);
+            }
+        }
+    }
+    print_statistics(&parser.statistics());
+    if let Some(ref path) = topology_path {
+        let mut output = String::new();
+        let _ = if path.ends_with(".json") {
+            topology.write_json(&mut output)
+        } else {
+            topology.write_dot(&mut output)
+        };
+        if let Err(e) = std::fs::write(path, output) {
+            eprintln!("Failed to write topology {}, {}", path, e);
+            return ExitCode::FAILURE;
+        }
+    }
+    if let Some(ref state) = state {
+        let mut buffer = [0u8; MAX_SNAPSHOT_SIZE];
+        let stored = parser
+            .security
+            .snapshot()
+            .encode(&mut buffer)
+            .map(|data| std::fs::write(state, data));
+        if !matches!(stored, Ok(Ok(()))) {
+            eprintln!("Failed to store state {}", state);
+            return ExitCode::FAILURE;
+        }
+    }
+    ExitCode::SUCCESS
+}
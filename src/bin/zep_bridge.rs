@@ -0,0 +1,137 @@
+//! Forward frames from a pcap stream, e.g. the sniffer serial port, as ZEP datagrams.
+
+use std::io::Read;
+use std::net::UdpSocket;
+use std::process::ExitCode;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use esp32c6_psila::pcap::StreamReader;
+use esp32c6_psila::zep::{self, ZepEncoder};
+
+fn usage() {
+    eprintln!("Usage: zep-bridge [--host <HOST>] [--port <PORT>] [--channel <CHANNEL>] [INPUT]");
+    eprintln!();
+    eprintln!("  INPUT                Pcap stream to read, standard input if not given");
+    eprintln!("  --host <HOST>        Destination host, default 127.0.0.1");
+    eprintln!(
+        "  --port <PORT>        Destination UDP port, default {}",
+        zep::ZEP_PORT
+    );
+    eprintln!("  --channel <CHANNEL>  Channel used when the stream carries none");
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_micros() as u64)
+        .unwrap_or(0)
+}
+
+fn main() -> ExitCode {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
+
+    let mut host = String::from("127.0.0.1");
+    let mut port = zep::ZEP_PORT;
+    let mut default_channel = 0u8;
+    let mut path = None;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--host" => match args.next() {
+                Some(value) => host = value,
+                None => {
+                    usage();
+                    return ExitCode::FAILURE;
+                }
+            },
+            "--port" => match args.next().and_then(|v| v.parse().ok()) {
+                Some(value) => port = value,
+                None => {
+                    eprintln!("Invalid port");
+                    return ExitCode::FAILURE;
+                }
+            },
+            "--channel" => match args.next().and_then(|v| v.parse().ok()) {
+                Some(value) => default_channel = value,
+                None => {
+                    eprintln!("Invalid channel");
+                    return ExitCode::FAILURE;
+                }
+            },
+            "-h" | "--help" => {
+                usage();
+                return ExitCode::SUCCESS;
+            }
+            _ if arg.starts_with('-') || path.is_some() => {
+                eprintln!("Unexpected argument {}", arg);
+                usage();
+                return ExitCode::FAILURE;
+            }
+            _ => path = Some(arg),
+        }
+    }
+
+    let input: Box<dyn Read> = match path {
+        Some(ref path) => match std::fs::File::open(path) {
+            Ok(file) => Box::new(file),
+            Err(e) => {
+                eprintln!("Failed to open {}, {}", path, e);
+                return ExitCode::FAILURE;
+            }
+        },
+        None => Box::new(std::io::stdin()),
+    };
+    let mut reader = match StreamReader::new(input) {
+        Ok(reader) => reader,
+        Err(e) => {
+            eprintln!("Failed to read stream header, {}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+    let socket = match UdpSocket::bind("0.0.0.0:0") {
+        Ok(socket) => socket,
+        Err(e) => {
+            eprintln!("Failed to create socket, {}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+    let destination = format!("{}:{}", host, port);
+    log::info!(
+        "Forwarding link type {} to {}",
+        reader.link_type(),
+        destination
+    );
+
+    let mut encoder = ZepEncoder::new(0);
+    let mut datagram = [0u8; zep::HEADER_SIZE + 256];
+    loop {
+        let record = match reader.next_record() {
+            Ok(Some(record)) => record,
+            Ok(None) => break,
+            Err(e) => {
+                log::error!("Failed to read record, {}", e);
+                return ExitCode::FAILURE;
+            }
+        };
+        let Some(mut captured) = record.ieee802154_frame() else {
+            log::warn!("Unsupported link type {}", record.link_type);
+            continue;
+        };
+        // Streams from the device are timestamped from boot, use the host clock for those
+        if record.timestamp < 1_000_000_000_000_000 {
+            captured.metadata.timestamp = now();
+        }
+        captured.metadata.channel.get_or_insert(default_channel);
+        match encoder.encode(&captured.metadata, captured.frame, &mut datagram) {
+            Ok(size) => {
+                if let Err(e) = socket.send_to(&datagram[..size], &destination) {
+                    log::error!("Failed to send datagram, {}", e);
+                }
+            }
+            Err(e) => {
+                log::warn!("Failed to encode frame, {:?}", e);
+            }
+        }
+    }
+    ExitCode::SUCCESS
+}
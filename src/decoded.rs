@@ -0,0 +1,155 @@
+use ieee802154::mac;
+use psila_data::{
+    application_service::{self, ApplicationServiceHeader},
+    network::{self, NetworkHeader},
+    security::SecurityHeader,
+};
+
+use crate::beacon::BeaconPayload;
+use crate::fcs::FcsStatus;
+use crate::frame_counter::FrameCounterStatus;
+use crate::information_element::InformationElements;
+use crate::mac_security::AuxiliarySecurityHeader;
+use crate::{zcl, zdo};
+
+pub const MAX_PAYLOAD_SIZE: usize = 128;
+
+pub type PayloadBuffer = heapless::Vec<u8, MAX_PAYLOAD_SIZE>;
+
+/// Radio information about a received frame
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct RxMetadata {
+    /// Received signal strength in dBm
+    pub rssi: Option<i8>,
+    /// Link quality indicator, 0 to 255
+    pub lqi: Option<u8>,
+    pub channel: Option<u8>,
+    /// Reception time in microseconds, the epoch depends on the source of the frame
+    pub timestamp: u64,
+    pub fcs: FcsStatus,
+}
+
+/// The result of decoding a single IEEE 802.15.4 frame and the Zigbee layers
+/// carried in it.
+#[derive(Clone, Debug)]
+pub struct DecodedFrame {
+    pub metadata: RxMetadata,
+    pub mac: mac::Header,
+    /// Enhanced beacons have no superframe specification and MAC commands unknown to the
+    /// ieee802154 crate are not decoded, the content of both is `Multipurpose`
+    pub content: mac::FrameContent,
+    pub mac_security: Option<AuxiliarySecurityHeader>,
+    /// The MAC payload was decrypted without MIC, security level 4, so the key is not verified
+    pub mac_unverified: bool,
+    /// Header and payload IEs, removed from the frame before the content was parsed
+    pub information_elements: InformationElements,
+    pub network: Option<NetworkHeader>,
+    pub network_security: Option<SecurityHeader>,
+    pub network_frame_counter: Option<FrameCounterStatus>,
+    pub application_service: Option<ApplicationServiceHeader>,
+    pub application_service_security: Option<SecurityHeader>,
+    pub application_service_frame_counter: Option<FrameCounterStatus>,
+    pub payload: Payload,
+    pub error: Option<DecodeError>,
+}
+
+impl DecodedFrame {
+    pub fn new(frame: &mac::Frame, metadata: &RxMetadata) -> Self {
+        DecodedFrame {
+            metadata: *metadata,
+            mac: frame.header,
+            content: frame.content.clone(),
+            mac_security: None,
+            mac_unverified: false,
+            information_elements: InformationElements::new(),
+            network: None,
+            network_security: None,
+            network_frame_counter: None,
+            application_service: None,
+            application_service_security: None,
+            application_service_frame_counter: None,
+            payload: Payload::None,
+            error: None,
+        }
+    }
+}
+
+/// The innermost payload that was decoded. Data payloads are the processed
+/// bytes, i.e. after decryption if the frame was secured.
+#[derive(Clone, Debug)]
+pub enum Payload {
+    None,
+    Beacon(BeaconPayload),
+    /// Payload of MAC frames that carry no Zigbee layers, e.g. multipurpose frames
+    MacData(PayloadBuffer),
+    /// MAC command the ieee802154 crate does not decode, e.g. the IEEE 802.15.4-2015 commands
+    MacCommand {
+        identifier: u8,
+        name: &'static str,
+        /// The command content following the identifier, not decoded
+        body: PayloadBuffer,
+    },
+    NetworkCommand(network::commands::Command),
+    ApplicationServiceCommand(application_service::Command),
+    ApplicationServiceData(PayloadBuffer),
+    ClusterLibrary(zcl::Frame),
+    DeviceObject(zdo::Frame),
+    ApplicationServiceAcknowledgement(PayloadBuffer),
+    ApplicationServiceInterPan(PayloadBuffer),
+}
+
+#[derive(Clone, Debug)]
+pub enum DecodeError {
+    NetworkHeader(psila_data::Error),
+    NetworkCommand(psila_data::Error),
+    ApplicationServiceHeader(psila_data::Error),
+    ApplicationServiceCommand(psila_data::Error),
+    SecurityHeader(psila_data::Error),
+    ClusterLibrary(psila_data::Error),
+    DeviceObject(psila_data::Error),
+    Beacon(psila_data::Error),
+    InformationElement(psila_data::Error),
+    NoValidKey,
+    PayloadTooLarge,
+    BadFcs,
+    /// Switch Key to a network key sequence that is not known
+    UnknownKeySequence(u8),
+}
+
+impl DecodeError {
+    pub fn message(&self) -> &'static str {
+        match self {
+            DecodeError::NetworkHeader(_) => "Failed to decode network frame",
+            DecodeError::NetworkCommand(_) => "Failed to decode network command",
+            DecodeError::ApplicationServiceHeader(_) => "Failed to parse APS header",
+            DecodeError::ApplicationServiceCommand(_) => "Failed to parse APS command",
+            DecodeError::SecurityHeader(_) => "Failed to parse security header",
+            DecodeError::ClusterLibrary(_) => "Failed to parse ZCL frame",
+            DecodeError::DeviceObject(_) => "Failed to parse ZDO frame",
+            DecodeError::Beacon(_) => "Failed to parse beacon payload",
+            DecodeError::InformationElement(_) => "Failed to parse information element",
+            DecodeError::NoValidKey => "No valid key found",
+            DecodeError::PayloadTooLarge => "Payload too large",
+            DecodeError::BadFcs => "Frame check sequence mismatch",
+            DecodeError::UnknownKeySequence(_) => "No network key with sequence",
+        }
+    }
+
+    pub fn error(&self) -> Option<&psila_data::Error> {
+        match self {
+            DecodeError::NetworkHeader(e)
+            | DecodeError::NetworkCommand(e)
+            | DecodeError::ApplicationServiceHeader(e)
+            | DecodeError::ApplicationServiceCommand(e)
+            | DecodeError::SecurityHeader(e)
+            | DecodeError::ClusterLibrary(e)
+            | DecodeError::DeviceObject(e)
+            | DecodeError::Beacon(e)
+            | DecodeError::InformationElement(e) => Some(e),
+            DecodeError::NoValidKey
+            | DecodeError::PayloadTooLarge
+            | DecodeError::BadFcs
+            | DecodeError::UnknownKeySequence(_) => None,
+        }
+    }
+}
@@ -0,0 +1,325 @@
+//! Device inventory, merges the addresses seen in the different layers into one record per
+//! device.
+
+use ieee802154::mac;
+use psila_data::{application_service, network};
+
+use crate::decoded::{DecodedFrame, Payload};
+use crate::zdo;
+
+pub const MAX_DEVICES: usize = 64;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Role {
+    Unknown,
+    Coordinator,
+    Router,
+    EndDevice,
+}
+
+impl Role {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Role::Unknown => "Unknown",
+            Role::Coordinator => "Coordinator",
+            Role::Router => "Router",
+            Role::EndDevice => "End device",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Device {
+    pub short_address: Option<u16>,
+    pub extended_address: Option<u64>,
+    pub role: Role,
+    /// Timestamp of the last frame transmitted by the device
+    pub last_seen: u64,
+    /// Frames transmitted by the device, including relayed frames
+    pub frames: u32,
+    /// Network frames originating from the device
+    pub network_frames: u32,
+    pub last_rssi: Option<i8>,
+}
+
+impl Device {
+    fn new() -> Self {
+        Device {
+            short_address: None,
+            extended_address: None,
+            role: Role::Unknown,
+            last_seen: 0,
+            frames: 0,
+            network_frames: 0,
+            last_rssi: None,
+        }
+    }
+}
+
+pub struct DeviceTable {
+    pub devices: heapless::Vec<Device, MAX_DEVICES>,
+}
+
+impl DeviceTable {
+    pub fn new() -> Self {
+        DeviceTable {
+            devices: heapless::Vec::new(),
+        }
+    }
+
+    pub fn by_short_address(&self, address: u16) -> Option<&Device> {
+        self.devices
+            .iter()
+            .find(|d| d.short_address == Some(address))
+    }
+
+    pub fn by_extended_address(&self, address: u64) -> Option<&Device> {
+        self.devices
+            .iter()
+            .find(|d| d.extended_address == Some(address))
+    }
+
+    fn insert(&mut self, device: Device) -> Option<usize> {
+        if self.devices.is_full() {
+            // Forget the device that has been silent the longest
+            let oldest = self
+                .devices
+                .iter()
+                .enumerate()
+                .min_by_key(|(_, d)| d.last_seen)
+                .map(|(index, _)| index)?;
+            self.devices.swap_remove(oldest);
+        }
+        self.devices.push(device).ok()?;
+        Some(self.devices.len() - 1)
+    }
+
+    /// Find or create the record for the addresses, merging records when a short address is
+    /// associated with an IEEE address
+    fn record(
+        &mut self,
+        short_address: Option<u16>,
+        extended_address: Option<u64>,
+    ) -> Option<usize> {
+        let short_address = short_address.filter(|a| *a < 0xfff8);
+        let by_extended = extended_address.and_then(|address| {
+            self.devices
+                .iter()
+                .position(|d| d.extended_address == Some(address))
+        });
+        let by_short = short_address.and_then(|address| {
+            self.devices
+                .iter()
+                .position(|d| d.short_address == Some(address))
+        });
+        match (by_extended, by_short) {
+            (Some(index), None) => {
+                if short_address.is_some() {
+                    self.devices[index].short_address = short_address;
+                }
+                Some(index)
+            }
+            (None, Some(index)) => {
+                if self.devices[index].extended_address.is_none() {
+                    self.devices[index].extended_address = extended_address;
+                    Some(index)
+                } else if extended_address.is_some() {
+                    // The short address has been given to another device
+                    self.devices[index].short_address = None;
+                    self.insert(Device {
+                        short_address,
+                        extended_address,
+                        ..Device::new()
+                    })
+                } else {
+                    Some(index)
+                }
+            }
+            (Some(extended), Some(short)) if extended != short => {
+                let other = self.devices[short];
+                if other.extended_address.is_none() {
+                    let device = &mut self.devices[extended];
+                    device.short_address = short_address;
+                    device.frames += other.frames;
+                    device.network_frames += other.network_frames;
+                    if other.last_seen > device.last_seen {
+                        device.last_seen = other.last_seen;
+                        device.last_rssi = other.last_rssi;
+                    }
+                    if device.role == Role::Unknown {
+                        device.role = other.role;
+                    }
+                    self.devices.swap_remove(short);
+                    // swap_remove moves the last record into the removed slot
+                    if extended == self.devices.len() {
+                        Some(short)
+                    } else {
+                        Some(extended)
+                    }
+                } else {
+                    self.devices[short].short_address = None;
+                    self.devices[extended].short_address = short_address;
+                    Some(extended)
+                }
+            }
+            (Some(index), Some(_)) => Some(index),
+            (None, None) => {
+                if short_address.is_none() && extended_address.is_none() {
+                    None
+                } else {
+                    self.insert(Device {
+                        short_address,
+                        extended_address,
+                        ..Device::new()
+                    })
+                }
+            }
+        }
+    }
+
+    fn associate(&mut self, short_address: u16, extended_address: Option<u64>, role: Role) {
+        if let Some(index) = self.record(Some(short_address), extended_address) {
+            if role != Role::Unknown {
+                self.devices[index].role = role;
+            }
+        }
+    }
+
+    fn update_network_command(&mut self, command: &network::commands::Command) {
+        if let network::commands::Command::RouteReply(route_reply) = command {
+            if let Some(address) = route_reply.orginator_ieee_address {
+                let short = u16::from(route_reply.orginator_address);
+                self.associate(short, Some(u64::from(address)), Role::Unknown);
+            }
+            if let Some(address) = route_reply.responder_ieee_address {
+                let short = u16::from(route_reply.responder_address);
+                self.associate(short, Some(u64::from(address)), Role::Unknown);
+            }
+        }
+    }
+
+    fn update_device_object(&mut self, frame: &zdo::Frame) {
+        match frame.message() {
+            Ok(zdo::Message::DeviceAnnounce {
+                network_address,
+                extended_address,
+                capability,
+            }) => {
+                let role = if capability.full_function_device {
+                    Role::Router
+                } else {
+                    Role::EndDevice
+                };
+                self.associate(network_address, Some(extended_address), role);
+            }
+            Ok(zdo::Message::AddressResponse {
+                status: 0,
+                extended_address,
+                network_address,
+                ..
+            }) => self.associate(network_address, Some(extended_address), Role::Unknown),
+            Ok(zdo::Message::ManagementLqiResponse(table)) => {
+                for entry in table.entries {
+                    if let Ok(zdo::Entry::Neighbor(neighbor)) = entry {
+                        let role = match neighbor.device_type {
+                            zdo::LogicalType::Coordinator => Role::Coordinator,
+                            zdo::LogicalType::Router => Role::Router,
+                            zdo::LogicalType::EndDevice => Role::EndDevice,
+                            zdo::LogicalType::Reserved(_) => Role::Unknown,
+                        };
+                        self.associate(
+                            neighbor.network_address,
+                            Some(neighbor.extended_address),
+                            role,
+                        );
+                    }
+                }
+            }
+            _ => (),
+        }
+    }
+
+    /// Update the inventory with a decoded frame
+    pub fn update(&mut self, frame: &DecodedFrame) {
+        // The MAC source is the device that transmitted the frame
+        let transmitter = match frame.mac.source {
+            Some(mac::Address::Short(_, address)) => {
+                // Network frames are secured hop by hop, the auxiliary header holds the IEEE
+                // address of the transmitter
+                let extended = frame.network_security.and_then(|s| s.source).map(u64::from);
+                self.record(Some(address.0), extended)
+            }
+            Some(mac::Address::Extended(_, address)) => self.record(None, Some(address.0)),
+            None => None,
+        };
+        if let Some(index) = transmitter {
+            let device = &mut self.devices[index];
+            device.frames = device.frames.wrapping_add(1);
+            device.last_seen = frame.metadata.timestamp;
+            if frame.metadata.rssi.is_some() {
+                device.last_rssi = frame.metadata.rssi;
+            }
+        }
+        if let Payload::Beacon(ref beacon) = frame.payload {
+            // Only the coordinator and routers send beacons
+            if let Some(index) = transmitter {
+                self.devices[index].role = if beacon.device_depth == 0 {
+                    Role::Coordinator
+                } else {
+                    Role::Router
+                };
+            }
+        }
+        let Some(ref header) = frame.network else {
+            return;
+        };
+        let source = u16::from(header.source_address);
+        let role = if source == 0x0000 {
+            Role::Coordinator
+        } else {
+            Role::Unknown
+        };
+        let extended = header.source_ieee_address.map(u64::from).or_else(|| {
+            frame
+                .application_service_security
+                .as_ref()
+                .and_then(|s| s.source)
+                .map(u64::from)
+        });
+        if let Some(index) = self.record(Some(source), extended) {
+            let device = &mut self.devices[index];
+            device.network_frames = device.network_frames.wrapping_add(1);
+            if role != Role::Unknown {
+                device.role = role;
+            }
+        }
+        if let Some(address) = header.destination_ieee_address {
+            let destination = u16::from(header.destination_address);
+            self.associate(destination, Some(u64::from(address)), Role::Unknown);
+        }
+        match frame.payload {
+            Payload::NetworkCommand(ref command) => self.update_network_command(command),
+            Payload::ApplicationServiceCommand(application_service::Command::UpdateDevice(
+                ref update,
+            )) => {
+                self.associate(
+                    u16::from(update.short_address),
+                    Some(u64::from(update.address)),
+                    Role::Unknown,
+                );
+            }
+            Payload::DeviceObject(ref zdo_frame) => self.update_device_object(zdo_frame),
+            _ => (),
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.devices.clear();
+    }
+}
+
+impl Default for DeviceTable {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,77 @@
+//! IEEE 802.15.4 frame check sequence.
+
+/// Size of the FCS in the 2.4 GHz O-QPSK PHY
+pub const FCS_SIZE: usize = 2;
+
+fn crc16_reflected(initial: u16, data: &[u8]) -> u16 {
+    let mut crc = initial;
+    for b in data.iter() {
+        crc ^= u16::from(*b);
+        for _ in 0..8 {
+            if crc & 1 == 1 {
+                crc = (crc >> 1) ^ 0x8408;
+            } else {
+                crc >>= 1;
+            }
+        }
+    }
+    crc
+}
+
+/// CRC-16/ITU-T as used for the IEEE 802.15.4 FCS, also known as CRC-16/KERMIT
+pub fn crc16_itu_t(data: &[u8]) -> u16 {
+    crc16_reflected(0, data)
+}
+
+/// CRC-16/X-25, used to protect install codes
+pub fn crc16_x25(data: &[u8]) -> u16 {
+    !crc16_reflected(0xffff, data)
+}
+
+/// The FCS to append to the MAC frame
+pub fn fcs(frame: &[u8]) -> [u8; FCS_SIZE] {
+    crc16_itu_t(frame).to_le_bytes()
+}
+
+/// Whether the frame was received intact
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FcsStatus {
+    /// The FCS was not available, e.g. removed by the radio
+    #[default]
+    Unknown,
+    Good,
+    Bad,
+}
+
+impl FcsStatus {
+    pub fn is_bad(&self) -> bool {
+        *self == FcsStatus::Bad
+    }
+}
+
+/// Verify the FCS of a MAC frame
+pub fn check(frame: &[u8], fcs: &[u8]) -> FcsStatus {
+    if fcs.len() == FCS_SIZE && crc16_itu_t(frame).to_le_bytes() == fcs {
+        FcsStatus::Good
+    } else {
+        FcsStatus::Bad
+    }
+}
+
+/// Split a MAC frame with trailing FCS into the frame and the result of the verification
+pub fn split(data: &[u8]) -> Option<(&[u8], FcsStatus)> {
+    let (frame, fcs) = data.split_at(data.len().checked_sub(FCS_SIZE)?);
+    Some((frame, check(frame, fcs)))
+}
+
+/// What the parser does with frames where the FCS does not match
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum BadFcsPolicy {
+    /// Skip the frame
+    Drop,
+    /// Decode the MAC header only and report the frame as broken
+    #[default]
+    Flag,
+    /// Decode the frame as any other frame
+    Decode,
+}
@@ -0,0 +1,2030 @@
+use ieee802154::mac::{self, beacon::BeaconOrder};
+use psila_data::{
+    application_service::{self, ApplicationServiceHeader},
+    network::{self, NetworkHeader},
+    security,
+};
+use ufmt::{uWrite, uwrite};
+
+use crate::beacon::BeaconPayload;
+use crate::decoded::{DecodeError, DecodedFrame, Payload, RxMetadata};
+use crate::devices::DeviceTable;
+use crate::fcs::FcsStatus;
+use crate::frame_counter::FrameCounterStatus;
+use crate::information_element::{ElementKind, InformationElement, ZigbeeElement};
+use crate::mac_security::{AuxiliarySecurityHeader, KeyIdentifierMode};
+use crate::security::mmo_hash;
+use crate::statistics::Statistics;
+use crate::survey::SurveySummary;
+use crate::{zcl, zdo};
+
+type Line = heapless::String<256>;
+
+fn print_key<W: uWrite>(writer: &mut W, key: &psila_data::Key) {
+    let k: [u8; 16] = (*key).into();
+    for b in k.iter() {
+        let _ = uwrite!(writer, "{:02x}", *b);
+    }
+}
+
+/// Identify a key without revealing it, the first two bytes of its MMO hash
+fn print_key_fingerprint<W: uWrite>(writer: &mut W, key: &psila_data::Key) {
+    let k: [u8; 16] = (*key).into();
+    let hash = mmo_hash(&k);
+    let _ = uwrite!(writer, "{:02x}{:02x}", hash[0], hash[1]);
+}
+
+fn print_bytes<W: uWrite>(writer: &mut W, bytes: &[u8]) {
+    for b in bytes.iter() {
+        let _ = uwrite!(writer, "{:02x}", *b);
+    }
+}
+
+pub fn write_security_header<W: uWrite>(line: &mut W, header: &security::SecurityHeader) {
+    let level = match header.control.level {
+        security::SecurityLevel::None => "None",
+        security::SecurityLevel::Integrity32 => "32-bitIntegrity",
+        security::SecurityLevel::Integrity64 => "64-bitIntegrity",
+        security::SecurityLevel::Integrity128 => "128-bitIntegrity",
+        security::SecurityLevel::Encrypted => "Encrypted",
+        security::SecurityLevel::EncryptedIntegrity32 => "Encrypted, 32-bit Integrity",
+        security::SecurityLevel::EncryptedIntegrity64 => "Encrypted, 64-bit Integrity",
+        security::SecurityLevel::EncryptedIntegrity128 => "Encrypted, 128-bit Integrity",
+    };
+    let identifier = match header.control.identifier {
+        security::KeyIdentifier::Data => "Data",
+        security::KeyIdentifier::Network => "Network",
+        security::KeyIdentifier::KeyTransport => "Key transport",
+        security::KeyIdentifier::KeyLoad => "Key load",
+    };
+
+    let _ = uwrite!(line, "SEC Level {} Key Identifier {}", level, identifier,);
+    if let Some(src) = header.source {
+        let _ = uwrite!(line, " SRC {:08x}", u64::from(src));
+    }
+    if let Some(seq) = header.sequence {
+        let _ = uwrite!(line, " Sequence {}", seq);
+    }
+    let _ = uwrite!(line, " Counter {}", header.counter);
+}
+
+pub fn write_mac_security_header<W: uWrite>(line: &mut W, header: &AuxiliarySecurityHeader) {
+    let mode = match header.key_identifier_mode {
+        KeyIdentifierMode::Implicit => "Implicit",
+        KeyIdentifierMode::Index => "Index",
+        KeyIdentifierMode::Source4 => "4-byte source",
+        KeyIdentifierMode::Source8 => "8-byte source",
+    };
+    let _ = uwrite!(
+        line,
+        "MAC SEC Level {} Key Identifier {}",
+        header.level_name(),
+        mode
+    );
+    if let Some(source) = header.key_source {
+        let _ = uwrite!(line, " Source {:x}", source);
+    }
+    if let Some(index) = header.key_index {
+        let _ = uwrite!(line, " Index {}", index);
+    }
+    if let Some(counter) = header.frame_counter {
+        let _ = uwrite!(line, " Counter {}", counter);
+    }
+}
+
+pub fn write_beacon_payload<W: uWrite>(line: &mut W, beacon: &BeaconPayload) {
+    let _ = uwrite!(
+        line,
+        "Zigbee Beacon Stack profile {} Version {} Depth {} EPID {:016x}",
+        beacon.stack_profile,
+        beacon.protocol_version,
+        beacon.device_depth,
+        beacon.extended_pan_identifier
+    );
+    if beacon.router_capacity {
+        let _ = uwrite!(line, " Router capacity");
+    }
+    if beacon.end_device_capacity {
+        let _ = uwrite!(line, " End device capacity");
+    }
+    if let Some(offset) = beacon.tx_offset {
+        let _ = uwrite!(line, " TX offset {}", offset);
+    }
+    if let Some(update) = beacon.update_identifier {
+        let _ = uwrite!(line, " Update {}", update);
+    }
+}
+
+pub fn write_frame_counter_status<W: uWrite>(line: &mut W, status: &FrameCounterStatus) {
+    let _ = match *status {
+        FrameCounterStatus::New => uwrite!(line, "New"),
+        FrameCounterStatus::Valid => uwrite!(line, "Valid"),
+        FrameCounterStatus::Replay => uwrite!(line, "Replay"),
+        FrameCounterStatus::Regression(previous) => uwrite!(line, "Regression from {}", previous),
+        FrameCounterStatus::Jump(previous) => uwrite!(line, "Jump from {}", previous),
+    };
+}
+
+pub fn write_application_service_command<W: uWrite>(
+    line: &mut W,
+    cmd: &application_service::Command,
+) {
+    use application_service::Command;
+    let _ = uwrite!(line, "APS Command ");
+    match cmd {
+        Command::SymmetricKeyKeyEstablishment1(cmd) => {
+            let _ = uwrite!(
+                line,
+                "SKKE1 Initator {} Responder {} ",
+                u64::from(cmd.initiator),
+                u64::from(cmd.responder)
+            );
+            print_bytes(line, &cmd.data);
+        }
+        Command::SymmetricKeyKeyEstablishment2(cmd) => {
+            let _ = uwrite!(
+                line,
+                "SKKE2 Initator {} Responder {} ",
+                u64::from(cmd.initiator),
+                u64::from(cmd.responder)
+            );
+            print_bytes(line, &cmd.data);
+        }
+        Command::SymmetricKeyKeyEstablishment3(cmd) => {
+            let _ = uwrite!(
+                line,
+                "SKKE3 Initator {} Responder {} ",
+                u64::from(cmd.initiator),
+                u64::from(cmd.responder)
+            );
+            print_bytes(line, &cmd.data);
+        }
+        Command::SymmetricKeyKeyEstablishment4(cmd) => {
+            let _ = uwrite!(
+                line,
+                "SKKE4 Initator {} Responder {} ",
+                u64::from(cmd.initiator),
+                u64::from(cmd.responder)
+            );
+            print_bytes(line, &cmd.data);
+        }
+        Command::TransportKey(cmd) => {
+            use application_service::commands::TransportKey;
+            let _ = uwrite!(line, "Transport Key ");
+            match cmd {
+                TransportKey::TrustCenterMasterKey(key) => {
+                    let _ = uwrite!(
+                        line,
+                        "Trust Center Master Key, DST {:016x} SRC {:016x} KEY ",
+                        u64::from(key.destination),
+                        u64::from(key.source)
+                    );
+                    print_key(line, &key.key);
+                }
+                TransportKey::StandardNetworkKey(key) => {
+                    let _ = uwrite!(
+                        line,
+                        "Standard Network Key, DST {:016x} SRC {:016x} SEQ {} KEY ",
+                        u64::from(key.destination),
+                        u64::from(key.source),
+                        key.sequence,
+                    );
+                    print_key(line, &key.key);
+                }
+                TransportKey::ApplicationMasterKey(key) => {
+                    let _ = uwrite!(
+                        line,
+                        "Application Master Key, Partner {:016x} {} KEY ",
+                        u64::from(key.partner),
+                        if key.initiator { "Initiator" } else { "" },
+                    );
+                    print_key(line, &key.key);
+                }
+                TransportKey::ApplicationLinkKey(key) => {
+                    let _ = uwrite!(
+                        line,
+                        "Application Link Key, Partner {:016x} {} KEY ",
+                        u64::from(key.partner),
+                        if key.initiator { "Initiator" } else { "" },
+                    );
+                    print_key(line, &key.key);
+                }
+                TransportKey::UniqueTrustCenterLinkKey(key) => {
+                    let _ = uwrite!(
+                        line,
+                        "Unique Trust Center Link Key, DST {:016x} SRC {:016x} KEY ",
+                        u64::from(key.destination),
+                        u64::from(key.source)
+                    );
+                    print_key(line, &key.key);
+                }
+                TransportKey::HighSecurityNetworkKey(key) => {
+                    let _ = uwrite!(
+                        line,
+                        "High Security Network Key, DST {:016x} SRC {:016x} SEQ {:02x} KEY ",
+                        u64::from(key.destination),
+                        u64::from(key.source),
+                        key.sequence
+                    );
+                    print_key(line, &key.key);
+                }
+            }
+        }
+        Command::UpdateDevice(cmd) => {
+            let _ = uwrite!(
+                line,
+                "Update Device, {} {} {:?}",
+                u64::from(cmd.address),
+                u16::from(cmd.short_address),
+                u8::from(cmd.status)
+            );
+        }
+        Command::RemoveDevice(cmd) => {
+            let _ = uwrite!(line, "Remove Device, {}", u64::from(cmd.address));
+        }
+        Command::RequestKey(cmd) => {
+            let _ = uwrite!(line, "Request Key, {}", u8::from(cmd.key_type));
+            if let Some(partner) = cmd.partner_address {
+                let _ = uwrite!(line, " Partner {}", u64::from(partner));
+            }
+        }
+        Command::SwitchKey(cmd) => {
+            let _ = uwrite!(line, "Switch Key, Sequence {}", cmd.sequence);
+        }
+        Command::EntityAuthenticationInitiatorChallenge => {
+            let _ = uwrite!(line, "EAC Initiator");
+        }
+        Command::EntityAuthenticationResponderChallenge => {
+            let _ = uwrite!(line, "EAC Responder");
+        }
+        Command::EntityAuthenticationInitiatorMacAndData => {
+            let _ = uwrite!(line, "EAMD Initiator");
+        }
+        Command::EntityAuthenticationResponderMacAndData => {
+            let _ = uwrite!(line, "EAMD Responder");
+        }
+        Command::Tunnel(cmd) => {
+            let _ = uwrite!(line, "Tunnel {}", u64::from(cmd.destination));
+        }
+        Command::VerifyKey(cmd) => {
+            let _ = uwrite!(
+                line,
+                "Verify Key, Source {} Type {} ",
+                u64::from(cmd.source),
+                u8::from(cmd.key_type)
+            );
+            print_bytes(line, &cmd.value);
+        }
+        Command::ConfirmKey(cmd) => {
+            let _ = uwrite!(
+                line,
+                "Confirm Key, Source {} Type {} Status {}",
+                u64::from(cmd.destination),
+                u8::from(cmd.key_type),
+                u8::from(cmd.status)
+            );
+        }
+    }
+}
+
+fn print_text<W: uWrite>(writer: &mut W, bytes: &[u8]) {
+    match core::str::from_utf8(bytes) {
+        Ok(text) => {
+            let _ = uwrite!(writer, "\"{}\"", text);
+        }
+        Err(_) => print_bytes(writer, bytes),
+    }
+}
+
+fn print_float<W: uWrite>(writer: &mut W, value: f64) {
+    let sign = if value < 0.0 { "-" } else { "" };
+    let value = if value < 0.0 { -value } else { value };
+    let integer = value as u64;
+    let fraction = ((value - integer as f64) * 1000.0) as u32;
+    let _ = uwrite!(
+        writer,
+        "{}{}.{}{}{}",
+        sign,
+        integer,
+        fraction / 100,
+        (fraction / 10) % 10,
+        fraction % 10
+    );
+}
+
+fn write_attribute_value<W: uWrite>(line: &mut W, value: &zcl::AttributeValue) {
+    use zcl::AttributeValue;
+    match *value {
+        AttributeValue::NoData => {
+            let _ = uwrite!(line, "-");
+        }
+        AttributeValue::Data(data) | AttributeValue::SecurityKey(data) => {
+            print_bytes(line, data);
+        }
+        AttributeValue::Boolean(value) => {
+            let _ = uwrite!(
+                line,
+                "{}",
+                match value {
+                    Some(true) => "true",
+                    Some(false) => "false",
+                    None => "invalid",
+                }
+            );
+        }
+        AttributeValue::Bitmap(value) => {
+            let _ = uwrite!(line, "{:x}", value);
+        }
+        AttributeValue::Unsigned(value) => {
+            let _ = uwrite!(line, "{}", value);
+        }
+        AttributeValue::Signed(value) => {
+            let _ = uwrite!(line, "{}", value);
+        }
+        AttributeValue::Enumeration(value) => {
+            let _ = uwrite!(line, "{:02x}", value);
+        }
+        AttributeValue::Float(value) => print_float(line, value),
+        AttributeValue::OctetString(data) => print_bytes(line, data),
+        AttributeValue::CharacterString(data) => print_text(line, data),
+        AttributeValue::Collection(data_type, data) => {
+            let _ = uwrite!(line, "{:02x}:", data_type);
+            print_bytes(line, data);
+        }
+        AttributeValue::TimeOfDay {
+            hours,
+            minutes,
+            seconds,
+            hundredths,
+        } => {
+            let _ = uwrite!(line, "{}:{}:{}.{}", hours, minutes, seconds, hundredths);
+        }
+        AttributeValue::Date {
+            year,
+            month,
+            day,
+            weekday,
+        } => {
+            let _ = uwrite!(
+                line,
+                "{}-{}-{} ({})",
+                1900 + u16::from(year),
+                month,
+                day,
+                weekday
+            );
+        }
+        AttributeValue::UtcTime(value) => {
+            let _ = uwrite!(line, "{}", value);
+        }
+        AttributeValue::ClusterId(value) | AttributeValue::AttributeId(value) => {
+            let _ = uwrite!(line, "{:04x}", value);
+        }
+        AttributeValue::BacnetOid(value) => {
+            let _ = uwrite!(line, "{:08x}", value);
+        }
+        AttributeValue::IeeeAddress(value) => {
+            let _ = uwrite!(line, "{:016x}", value);
+        }
+    }
+}
+
+fn write_attribute<W: uWrite>(line: &mut W, attribute: &zcl::Attribute) {
+    let _ = uwrite!(
+        line,
+        " {:04x} ({:02x}) ",
+        attribute.identifier,
+        attribute.data_type
+    );
+    write_attribute_value(line, &attribute.value);
+}
+
+fn write_reporting_configuration<W: uWrite>(
+    line: &mut W,
+    configuration: &zcl::ReportingConfiguration,
+) {
+    match configuration {
+        zcl::ReportingConfiguration::Send {
+            identifier,
+            data_type,
+            minimum_interval,
+            maximum_interval,
+            reportable_change,
+        } => {
+            let _ = uwrite!(
+                line,
+                " {:04x} ({:02x}) Min {}s Max {}s",
+                *identifier,
+                *data_type,
+                *minimum_interval,
+                *maximum_interval
+            );
+            if let Some(change) = reportable_change {
+                let _ = uwrite!(line, " Change ");
+                write_attribute_value(line, change);
+            }
+        }
+        zcl::ReportingConfiguration::Receive {
+            identifier,
+            timeout,
+        } => {
+            let _ = uwrite!(line, " {:04x} Timeout {}s", *identifier, *timeout);
+        }
+    }
+}
+
+fn write_records<W: uWrite>(line: &mut W, records: zcl::Records) {
+    for record in records {
+        match record {
+            Ok(zcl::Record::Identifier(identifier)) => {
+                let _ = uwrite!(line, " {:04x}", identifier);
+            }
+            Ok(zcl::Record::Attribute(attribute)) => {
+                write_attribute(line, &attribute);
+            }
+            Ok(zcl::Record::Status(status)) => match status.attribute {
+                Some(ref attribute) => write_attribute(line, attribute),
+                None => {
+                    if status.identifier != 0xffff {
+                        let _ = uwrite!(line, " {:04x}", status.identifier);
+                    }
+                    let _ = uwrite!(line, " {}", zcl::status_name(status.status));
+                }
+            },
+            Ok(zcl::Record::Reporting(configuration)) => {
+                write_reporting_configuration(line, &configuration);
+            }
+            Ok(zcl::Record::ReportingStatus(status)) => match status.configuration {
+                Some(ref configuration) => write_reporting_configuration(line, configuration),
+                None => {
+                    if status.identifier != 0xffff {
+                        let _ = uwrite!(line, " {:04x}", status.identifier);
+                    }
+                    let _ = uwrite!(line, " {}", zcl::status_name(status.status));
+                }
+            },
+            Ok(zcl::Record::ReadReporting {
+                direction,
+                identifier,
+            }) => {
+                let _ = uwrite!(line, " {:04x} Direction {}", identifier, direction);
+            }
+            Ok(zcl::Record::Discovered {
+                identifier,
+                data_type,
+            }) => {
+                let _ = uwrite!(line, " {:04x} ({:02x})", identifier, data_type);
+            }
+            Err(_) => {
+                let _ = uwrite!(line, " Broken record");
+            }
+        }
+    }
+}
+
+fn write_cluster_command<W: uWrite>(line: &mut W, command: &zcl::ClusterCommand) {
+    use zcl::ClusterCommand;
+    match *command {
+        ClusterCommand::Identify { time } => {
+            let _ = uwrite!(line, "Identify {}s", time);
+        }
+        ClusterCommand::IdentifyQuery => {
+            let _ = uwrite!(line, "Identify Query");
+        }
+        ClusterCommand::IdentifyQueryResponse { timeout } => {
+            let _ = uwrite!(line, "Identify Query Response {}s", timeout);
+        }
+        ClusterCommand::TriggerEffect { effect, variant } => {
+            let _ = uwrite!(line, "Trigger Effect {:02x} {:02x}", effect, variant);
+        }
+        ClusterCommand::AddGroup { group, name } => {
+            let _ = uwrite!(line, "Add Group {:04x} ", group);
+            print_text(line, name);
+        }
+        ClusterCommand::ViewGroup { group } => {
+            let _ = uwrite!(line, "View Group {:04x}", group);
+        }
+        ClusterCommand::GetGroupMembership { groups } => {
+            let _ = uwrite!(line, "Get Group Membership");
+            for group in groups.chunks(2) {
+                let _ = uwrite!(line, " {:02x}{:02x}", group[1], group[0]);
+            }
+        }
+        ClusterCommand::RemoveGroup { group } => {
+            let _ = uwrite!(line, "Remove Group {:04x}", group);
+        }
+        ClusterCommand::RemoveAllGroups => {
+            let _ = uwrite!(line, "Remove All Groups");
+        }
+        ClusterCommand::AddGroupIfIdentifying { group, name } => {
+            let _ = uwrite!(line, "Add Group If Identifying {:04x} ", group);
+            print_text(line, name);
+        }
+        ClusterCommand::AddGroupResponse { status, group } => {
+            let _ = uwrite!(
+                line,
+                "Add Group Response {:04x} {}",
+                group,
+                zcl::status_name(status)
+            );
+        }
+        ClusterCommand::ViewGroupResponse {
+            status,
+            group,
+            name,
+        } => {
+            let _ = uwrite!(
+                line,
+                "View Group Response {:04x} {} ",
+                group,
+                zcl::status_name(status)
+            );
+            print_text(line, name);
+        }
+        ClusterCommand::GetGroupMembershipResponse { capacity, groups } => {
+            let _ = uwrite!(line, "Get Group Membership Response Capacity {}", capacity);
+            for group in groups.chunks(2) {
+                let _ = uwrite!(line, " {:02x}{:02x}", group[1], group[0]);
+            }
+        }
+        ClusterCommand::RemoveGroupResponse { status, group } => {
+            let _ = uwrite!(
+                line,
+                "Remove Group Response {:04x} {}",
+                group,
+                zcl::status_name(status)
+            );
+        }
+        ClusterCommand::Off => {
+            let _ = uwrite!(line, "Off");
+        }
+        ClusterCommand::On => {
+            let _ = uwrite!(line, "On");
+        }
+        ClusterCommand::Toggle => {
+            let _ = uwrite!(line, "Toggle");
+        }
+        ClusterCommand::OffWithEffect { effect, variant } => {
+            let _ = uwrite!(line, "Off With Effect {:02x} {:02x}", effect, variant);
+        }
+        ClusterCommand::OnWithRecallGlobalScene => {
+            let _ = uwrite!(line, "On With Recall Global Scene");
+        }
+        ClusterCommand::OnWithTimedOff {
+            control,
+            on_time,
+            off_wait_time,
+        } => {
+            let _ = uwrite!(
+                line,
+                "On With Timed Off {:02x} On {} Off wait {}",
+                control,
+                on_time,
+                off_wait_time
+            );
+        }
+        ClusterCommand::MoveToLevel {
+            level,
+            transition_time,
+            with_on_off,
+        } => {
+            let _ = uwrite!(
+                line,
+                "Move To Level{} {} Time {}",
+                if with_on_off { " With On/Off" } else { "" },
+                level,
+                transition_time
+            );
+        }
+        ClusterCommand::Move {
+            mode,
+            rate,
+            with_on_off,
+        } => {
+            let _ = uwrite!(
+                line,
+                "Move{} {} Rate {}",
+                if with_on_off { " With On/Off" } else { "" },
+                if mode == 0 { "Up" } else { "Down" },
+                rate
+            );
+        }
+        ClusterCommand::Step {
+            mode,
+            size,
+            transition_time,
+            with_on_off,
+        } => {
+            let _ = uwrite!(
+                line,
+                "Step{} {} Size {} Time {}",
+                if with_on_off { " With On/Off" } else { "" },
+                if mode == 0 { "Up" } else { "Down" },
+                size,
+                transition_time
+            );
+        }
+        ClusterCommand::Stop { with_on_off } => {
+            let _ = uwrite!(
+                line,
+                "Stop{}",
+                if with_on_off { " With On/Off" } else { "" }
+            );
+        }
+        ClusterCommand::MoveToHue {
+            hue,
+            direction,
+            transition_time,
+        } => {
+            let _ = uwrite!(
+                line,
+                "Move To Hue {} Direction {} Time {}",
+                hue,
+                direction,
+                transition_time
+            );
+        }
+        ClusterCommand::MoveToSaturation {
+            saturation,
+            transition_time,
+        } => {
+            let _ = uwrite!(
+                line,
+                "Move To Saturation {} Time {}",
+                saturation,
+                transition_time
+            );
+        }
+        ClusterCommand::MoveToHueAndSaturation {
+            hue,
+            saturation,
+            transition_time,
+        } => {
+            let _ = uwrite!(
+                line,
+                "Move To Hue And Saturation {} {} Time {}",
+                hue,
+                saturation,
+                transition_time
+            );
+        }
+        ClusterCommand::MoveToColor {
+            x,
+            y,
+            transition_time,
+        } => {
+            let _ = uwrite!(line, "Move To Color {} {} Time {}", x, y, transition_time);
+        }
+        ClusterCommand::MoveToColorTemperature {
+            mireds,
+            transition_time,
+        } => {
+            let _ = uwrite!(
+                line,
+                "Move To Color Temperature {} Time {}",
+                mireds,
+                transition_time
+            );
+        }
+        ClusterCommand::StopMoveStep => {
+            let _ = uwrite!(line, "Stop Move Step");
+        }
+        ClusterCommand::Other { command, payload } => {
+            let _ = uwrite!(line, "Command {:02x} ", command);
+            print_bytes(line, payload);
+        }
+    }
+}
+
+pub fn write_cluster_library_frame<W: uWrite>(line: &mut W, frame: &zcl::Frame) {
+    let _ = uwrite!(
+        line,
+        "ZCL {} {:04x} Profile {:04x} {} {} SEQ {}",
+        zcl::cluster_name(frame.cluster),
+        frame.cluster,
+        frame.profile,
+        match frame.header.frame_type {
+            zcl::FrameType::Global => "Global",
+            zcl::FrameType::ClusterSpecific => "Cluster",
+        },
+        match frame.header.direction {
+            zcl::Direction::ClientToServer => "To server",
+            zcl::Direction::ServerToClient => "To client",
+        },
+        frame.header.transaction_sequence
+    );
+    if let Some(code) = frame.header.manufacturer_code {
+        let _ = uwrite!(line, " Manufacturer {:04x}", code);
+    }
+    if frame.header.disable_default_response {
+        let _ = uwrite!(line, " No default response");
+    }
+    let _ = uwrite!(line, " CMD {:02x} ", frame.header.command);
+    let command = match frame.command() {
+        Ok(command) => command,
+        Err(_) => {
+            let _ = uwrite!(line, "Malformed ");
+            print_bytes(line, &frame.payload);
+            return;
+        }
+    };
+    match command {
+        zcl::Command::ReadAttributes(records) => {
+            let _ = uwrite!(line, "Read Attributes");
+            write_records(line, records);
+        }
+        zcl::Command::ReadAttributesResponse(records) => {
+            let _ = uwrite!(line, "Read Attributes Response");
+            write_records(line, records);
+        }
+        zcl::Command::WriteAttributes(records) => {
+            let _ = uwrite!(line, "Write Attributes");
+            write_records(line, records);
+        }
+        zcl::Command::WriteAttributesUndivided(records) => {
+            let _ = uwrite!(line, "Write Attributes Undivided");
+            write_records(line, records);
+        }
+        zcl::Command::WriteAttributesResponse(records) => {
+            let _ = uwrite!(line, "Write Attributes Response");
+            write_records(line, records);
+        }
+        zcl::Command::WriteAttributesNoResponse(records) => {
+            let _ = uwrite!(line, "Write Attributes No Response");
+            write_records(line, records);
+        }
+        zcl::Command::ConfigureReporting(records) => {
+            let _ = uwrite!(line, "Configure Reporting");
+            write_records(line, records);
+        }
+        zcl::Command::ConfigureReportingResponse(records) => {
+            let _ = uwrite!(line, "Configure Reporting Response");
+            write_records(line, records);
+        }
+        zcl::Command::ReadReportingConfiguration(records) => {
+            let _ = uwrite!(line, "Read Reporting Configuration");
+            write_records(line, records);
+        }
+        zcl::Command::ReadReportingConfigurationResponse(records) => {
+            let _ = uwrite!(line, "Read Reporting Configuration Response");
+            write_records(line, records);
+        }
+        zcl::Command::ReportAttributes(records) => {
+            let _ = uwrite!(line, "Report Attributes");
+            write_records(line, records);
+        }
+        zcl::Command::DefaultResponse { command, status } => {
+            let _ = uwrite!(
+                line,
+                "Default Response {:02x} {}",
+                command,
+                zcl::status_name(status)
+            );
+        }
+        zcl::Command::DiscoverAttributes { start, maximum } => {
+            let _ = uwrite!(line, "Discover Attributes {:04x} Max {}", start, maximum);
+        }
+        zcl::Command::DiscoverAttributesResponse {
+            complete,
+            attributes,
+        } => {
+            let _ = uwrite!(
+                line,
+                "Discover Attributes Response{}",
+                if complete { " Complete" } else { "" }
+            );
+            write_records(line, attributes);
+        }
+        zcl::Command::Cluster(ref command) => write_cluster_command(line, command),
+        zcl::Command::Other(payload) => print_bytes(line, payload),
+    }
+}
+
+fn write_u16_list<W: uWrite>(line: &mut W, list: zdo::U16List) {
+    for value in list.iter() {
+        let _ = uwrite!(line, " {:04x}", value);
+    }
+}
+
+fn write_endpoints<W: uWrite>(line: &mut W, endpoints: &[u8]) {
+    for endpoint in endpoints.iter() {
+        let _ = uwrite!(line, " {}", endpoint);
+    }
+}
+
+fn logical_type_name(logical_type: zdo::LogicalType) -> &'static str {
+    match logical_type {
+        zdo::LogicalType::Coordinator => "Coordinator",
+        zdo::LogicalType::Router => "Router",
+        zdo::LogicalType::EndDevice => "End device",
+        zdo::LogicalType::Reserved(_) => "Reserved",
+    }
+}
+
+fn write_capability<W: uWrite>(line: &mut W, capability: &zdo::Capability) {
+    if capability.alternate_pan_coordinator {
+        let _ = uwrite!(line, " PAN Coordinator");
+    }
+    if capability.full_function_device {
+        let _ = uwrite!(line, " FFD");
+    }
+    if capability.mains_power {
+        let _ = uwrite!(line, " Mains power");
+    }
+    if capability.receiver_on_when_idle {
+        let _ = uwrite!(line, " Idle receive");
+    }
+    if capability.security {
+        let _ = uwrite!(line, " Secure");
+    }
+    if capability.allocate_address {
+        let _ = uwrite!(line, " Allocate address");
+    }
+}
+
+fn write_binding<W: uWrite>(line: &mut W, binding: &zdo::Binding) {
+    let _ = uwrite!(
+        line,
+        " SRC {:016x}:{} Cluster {:04x} DST",
+        binding.source,
+        binding.source_endpoint,
+        binding.cluster
+    );
+    match binding.destination {
+        zdo::BindingDestination::Group(group) => {
+            let _ = uwrite!(line, " Group {:04x}", group);
+        }
+        zdo::BindingDestination::Extended(address, endpoint) => {
+            let _ = uwrite!(line, " {:016x}:{}", address, endpoint);
+        }
+    }
+}
+
+fn write_table<W: uWrite>(line: &mut W, name: &str, table: zdo::Table) {
+    let _ = uwrite!(line, "{} {}", name, zdo::status_name(table.status));
+    if table.status != 0 {
+        return;
+    }
+    let _ = uwrite!(
+        line,
+        " Total {} Start {} Count {}",
+        table.total,
+        table.start_index,
+        table.entries.len()
+    );
+    for entry in table.entries {
+        match entry {
+            Ok(zdo::Entry::Neighbor(neighbor)) => {
+                let _ = uwrite!(
+                    line,
+                    ", {:04x} {:016x} {} {} Depth {} LQI {}",
+                    neighbor.network_address,
+                    neighbor.extended_address,
+                    logical_type_name(neighbor.device_type),
+                    match neighbor.relationship {
+                        zdo::Relationship::Parent => "Parent",
+                        zdo::Relationship::Child => "Child",
+                        zdo::Relationship::Sibling => "Sibling",
+                        zdo::Relationship::None => "None",
+                        zdo::Relationship::PreviousChild => "Previous child",
+                        zdo::Relationship::Reserved(_) => "Reserved",
+                    },
+                    neighbor.depth,
+                    neighbor.link_quality
+                );
+            }
+            Ok(zdo::Entry::Route(route)) => {
+                let _ = uwrite!(
+                    line,
+                    ", {:04x} via {:04x} {}",
+                    route.destination,
+                    route.next_hop,
+                    match route.status {
+                        zdo::RouteStatus::Active => "Active",
+                        zdo::RouteStatus::DiscoveryUnderway => "Discovery underway",
+                        zdo::RouteStatus::DiscoveryFailed => "Discovery failed",
+                        zdo::RouteStatus::Inactive => "Inactive",
+                        zdo::RouteStatus::ValidationUnderway => "Validation underway",
+                        zdo::RouteStatus::Reserved(_) => "Reserved",
+                    }
+                );
+                if route.many_to_one {
+                    let _ = uwrite!(line, " Many-to-one");
+                }
+                if route.route_record_required {
+                    let _ = uwrite!(line, " Route record required");
+                }
+            }
+            Ok(zdo::Entry::Binding(binding)) => {
+                let _ = uwrite!(line, ",");
+                write_binding(line, &binding);
+            }
+            Err(_) => {
+                let _ = uwrite!(line, ", Malformed");
+            }
+        }
+    }
+}
+
+pub fn write_device_object_frame<W: uWrite>(line: &mut W, frame: &zdo::Frame) {
+    let _ = uwrite!(
+        line,
+        "ZDO {:04x} SEQ {} ",
+        frame.cluster,
+        frame.transaction_sequence
+    );
+    let message = match frame.message() {
+        Ok(message) => message,
+        Err(_) => {
+            let _ = uwrite!(line, "Malformed ");
+            print_bytes(line, &frame.payload);
+            return;
+        }
+    };
+    match message {
+        zdo::Message::NetworkAddressRequest {
+            address,
+            extended,
+            start_index,
+        } => {
+            let _ = uwrite!(line, "NWK Address Request {:016x}", address);
+            if extended {
+                let _ = uwrite!(line, " Extended Start {}", start_index);
+            }
+        }
+        zdo::Message::IeeeAddressRequest {
+            address,
+            extended,
+            start_index,
+        } => {
+            let _ = uwrite!(line, "IEEE Address Request {:04x}", address);
+            if extended {
+                let _ = uwrite!(line, " Extended Start {}", start_index);
+            }
+        }
+        zdo::Message::NodeDescriptorRequest { address } => {
+            let _ = uwrite!(line, "Node Descriptor Request {:04x}", address);
+        }
+        zdo::Message::PowerDescriptorRequest { address } => {
+            let _ = uwrite!(line, "Power Descriptor Request {:04x}", address);
+        }
+        zdo::Message::SimpleDescriptorRequest { address, endpoint } => {
+            let _ = uwrite!(
+                line,
+                "Simple Descriptor Request {:04x} Endpoint {}",
+                address,
+                endpoint
+            );
+        }
+        zdo::Message::ActiveEndpointRequest { address } => {
+            let _ = uwrite!(line, "Active Endpoint Request {:04x}", address);
+        }
+        zdo::Message::MatchDescriptorRequest {
+            address,
+            profile,
+            input_clusters,
+            output_clusters,
+        } => {
+            let _ = uwrite!(
+                line,
+                "Match Descriptor Request {:04x} Profile {:04x} In",
+                address,
+                profile
+            );
+            write_u16_list(line, input_clusters);
+            let _ = uwrite!(line, " Out");
+            write_u16_list(line, output_clusters);
+        }
+        zdo::Message::DeviceAnnounce {
+            network_address,
+            extended_address,
+            capability,
+        } => {
+            let _ = uwrite!(
+                line,
+                "Device Announce {:04x} {:016x}",
+                network_address,
+                extended_address
+            );
+            write_capability(line, &capability);
+        }
+        zdo::Message::BindRequest(binding) => {
+            let _ = uwrite!(line, "Bind Request");
+            write_binding(line, &binding);
+        }
+        zdo::Message::UnbindRequest(binding) => {
+            let _ = uwrite!(line, "Unbind Request");
+            write_binding(line, &binding);
+        }
+        zdo::Message::ManagementLqiRequest { start_index } => {
+            let _ = uwrite!(line, "Management LQI Request Start {}", start_index);
+        }
+        zdo::Message::ManagementRoutingRequest { start_index } => {
+            let _ = uwrite!(line, "Management Routing Request Start {}", start_index);
+        }
+        zdo::Message::ManagementBindRequest { start_index } => {
+            let _ = uwrite!(line, "Management Bind Request Start {}", start_index);
+        }
+        zdo::Message::ManagementLeaveRequest {
+            address,
+            remove_children,
+            rejoin,
+        } => {
+            let _ = uwrite!(line, "Management Leave Request {:016x}", address);
+            if remove_children {
+                let _ = uwrite!(line, " Remove children");
+            }
+            if rejoin {
+                let _ = uwrite!(line, " Rejoin");
+            }
+        }
+        zdo::Message::ManagementPermitJoiningRequest {
+            duration,
+            trust_center_significance,
+        } => {
+            let _ = uwrite!(line, "Management Permit Joining Request {}s", duration);
+            if trust_center_significance {
+                let _ = uwrite!(line, " TC significance");
+            }
+        }
+        zdo::Message::ManagementNetworkUpdateRequest {
+            channels,
+            scan_duration,
+            scan_count,
+            update_identifier,
+            manager_address,
+        } => {
+            let _ = uwrite!(
+                line,
+                "Management NWK Update Request Channels {:08x} Duration {:02x}",
+                channels,
+                scan_duration
+            );
+            if let Some(count) = scan_count {
+                let _ = uwrite!(line, " Count {}", count);
+            }
+            if let Some(identifier) = update_identifier {
+                let _ = uwrite!(line, " Update {}", identifier);
+            }
+            if let Some(address) = manager_address {
+                let _ = uwrite!(line, " Manager {:04x}", address);
+            }
+        }
+        zdo::Message::AddressResponse {
+            ieee_address_response,
+            status,
+            extended_address,
+            network_address,
+            start_index,
+            associated_devices,
+        } => {
+            let _ = uwrite!(
+                line,
+                "{} Address Response {} {:04x} {:016x}",
+                if ieee_address_response { "IEEE" } else { "NWK" },
+                zdo::status_name(status),
+                network_address,
+                extended_address
+            );
+            if !associated_devices.is_empty() {
+                let _ = uwrite!(line, " Start {} Associated", start_index);
+                write_u16_list(line, associated_devices);
+            }
+        }
+        zdo::Message::NodeDescriptorResponse {
+            status,
+            address,
+            descriptor,
+        } => {
+            let _ = uwrite!(
+                line,
+                "Node Descriptor Response {} {:04x}",
+                zdo::status_name(status),
+                address
+            );
+            if let Some(descriptor) = descriptor {
+                let _ = uwrite!(
+                    line,
+                    " {} Bands {:02x} Manufacturer {:04x} Buffer {} In {} Out {} Server {:04x} Descriptor {:02x}",
+                    logical_type_name(descriptor.logical_type),
+                    descriptor.frequency_bands,
+                    descriptor.manufacturer_code,
+                    descriptor.maximum_buffer_size,
+                    descriptor.maximum_incoming_transfer_size,
+                    descriptor.maximum_outgoing_transfer_size,
+                    descriptor.server_mask,
+                    descriptor.descriptor_capability
+                );
+                if descriptor.complex_descriptor {
+                    let _ = uwrite!(line, " Complex");
+                }
+                if descriptor.user_descriptor {
+                    let _ = uwrite!(line, " User");
+                }
+                write_capability(line, &descriptor.capability);
+            }
+        }
+        zdo::Message::PowerDescriptorResponse {
+            status,
+            address,
+            descriptor,
+        } => {
+            let _ = uwrite!(
+                line,
+                "Power Descriptor Response {} {:04x}",
+                zdo::status_name(status),
+                address
+            );
+            if let Some(descriptor) = descriptor {
+                let _ = uwrite!(
+                    line,
+                    " Mode {} Available {:x} Current {:x} Level {}",
+                    descriptor.mode,
+                    descriptor.available_sources,
+                    descriptor.current_source,
+                    match descriptor.level {
+                        0x0 => "Critical",
+                        0x4 => "33%",
+                        0x8 => "66%",
+                        0xc => "100%",
+                        _ => "Reserved",
+                    }
+                );
+            }
+        }
+        zdo::Message::SimpleDescriptorResponse {
+            status,
+            address,
+            descriptor,
+        } => {
+            let _ = uwrite!(
+                line,
+                "Simple Descriptor Response {} {:04x}",
+                zdo::status_name(status),
+                address
+            );
+            if let Some(descriptor) = descriptor {
+                let _ = uwrite!(
+                    line,
+                    " Endpoint {} Profile {:04x} Device {:04x} Version {} In",
+                    descriptor.endpoint,
+                    descriptor.profile,
+                    descriptor.device,
+                    descriptor.device_version
+                );
+                write_u16_list(line, descriptor.input_clusters);
+                let _ = uwrite!(line, " Out");
+                write_u16_list(line, descriptor.output_clusters);
+            }
+        }
+        zdo::Message::ActiveEndpointResponse {
+            status,
+            address,
+            endpoints,
+        } => {
+            let _ = uwrite!(
+                line,
+                "Active Endpoint Response {} {:04x} Endpoints",
+                zdo::status_name(status),
+                address
+            );
+            write_endpoints(line, endpoints);
+        }
+        zdo::Message::MatchDescriptorResponse {
+            status,
+            address,
+            endpoints,
+        } => {
+            let _ = uwrite!(
+                line,
+                "Match Descriptor Response {} {:04x} Endpoints",
+                zdo::status_name(status),
+                address
+            );
+            write_endpoints(line, endpoints);
+        }
+        zdo::Message::BindResponse { status } => {
+            let _ = uwrite!(line, "Bind Response {}", zdo::status_name(status));
+        }
+        zdo::Message::UnbindResponse { status } => {
+            let _ = uwrite!(line, "Unbind Response {}", zdo::status_name(status));
+        }
+        zdo::Message::ManagementLqiResponse(table) => {
+            write_table(line, "Management LQI Response", table);
+        }
+        zdo::Message::ManagementRoutingResponse(table) => {
+            write_table(line, "Management Routing Response", table);
+        }
+        zdo::Message::ManagementBindResponse(table) => {
+            write_table(line, "Management Bind Response", table);
+        }
+        zdo::Message::ManagementLeaveResponse { status } => {
+            let _ = uwrite!(
+                line,
+                "Management Leave Response {}",
+                zdo::status_name(status)
+            );
+        }
+        zdo::Message::ManagementPermitJoiningResponse { status } => {
+            let _ = uwrite!(
+                line,
+                "Management Permit Joining Response {}",
+                zdo::status_name(status)
+            );
+        }
+        zdo::Message::ManagementNetworkUpdateNotify {
+            status,
+            channels,
+            transmissions,
+            failures,
+            energies,
+        } => {
+            let _ = uwrite!(
+                line,
+                "Management NWK Update Notify {} Channels {:08x} Transmissions {} Failures {} Energy",
+                zdo::status_name(status),
+                channels,
+                transmissions,
+                failures
+            );
+            write_endpoints(line, energies);
+        }
+        zdo::Message::Other { payload, .. } => print_bytes(line, payload),
+    }
+}
+
+pub fn write_application_service_header<W: uWrite>(
+    line: &mut W,
+    header: &ApplicationServiceHeader,
+) {
+    let _ = uwrite!(line, "APS ");
+    let ack_format = if header.control.acknowledge_format {
+        "AckCmd"
+    } else {
+        "AckData"
+    };
+    let _ = uwrite!(line, "{} ", ack_format,);
+    if header.control.security {
+        let _ = uwrite!(line, "Secure ");
+    }
+    if header.control.acknowledge_request {
+        let _ = uwrite!(line, "AckReq ");
+    }
+    if header.control.extended_header {
+        let _ = uwrite!(line, "ExtHdr ");
+    }
+    if let Some(addr) = header.destination {
+        let _ = uwrite!(line, "Dst {:02x} ", addr);
+    }
+    if let Some(group) = header.group {
+        let _ = uwrite!(line, "Group {:04x} ", group);
+    }
+    if let Some(cluster) = header.cluster {
+        let _ = uwrite!(line, "Cluster {:04x} ", cluster);
+    }
+    if let Some(profile) = header.profile {
+        let _ = uwrite!(line, "Profile {:04x} ", profile);
+    }
+    if let Some(addr) = header.source {
+        let _ = uwrite!(line, "Src {:02x} ", addr);
+    }
+    let _ = uwrite!(line, "Counter {:02x}", header.counter);
+}
+
+pub fn write_network_command<W: uWrite>(line: &mut W, cmd: &network::commands::Command) {
+    use network::commands::Command;
+    let _ = uwrite!(line, "NWK CMD ",);
+    match cmd {
+        Command::RouteRequest(rr) => {
+            let many = match rr.options.many_to_one {
+                network::commands::ManyToOne::No => "One to one",
+                network::commands::ManyToOne::RouteRequestTableSupport => "Many to one, table",
+                network::commands::ManyToOne::NoRouteRequestTableSupport => "Many to one",
+            };
+            let _ = uwrite!(
+                line,
+                "Route Request {:02x} Cost {} {}",
+                rr.identifier,
+                rr.path_cost,
+                many
+            );
+            match rr.destination_address {
+                network::commands::AddressType::Singlecast(a) => {
+                    let _ = uwrite!(line, " Destination {:04x}", u16::from(a));
+                }
+                network::commands::AddressType::Multicast(a) => {
+                    let _ = uwrite!(line, " Group {:04x}", u16::from(a));
+                }
+            }
+            if let Some(address) = rr.destination_ieee_address {
+                let _ = uwrite!(line, " Destination {:08x}", u64::from(address));
+            }
+        }
+        Command::RouteReply(rr) => {
+            let _ = uwrite!(
+                line,
+                "Route Reply Identifier {:02x} Originator {:04x} Responder {:04x} Path cost {}",
+                rr.identifier,
+                u16::from(rr.orginator_address),
+                u16::from(rr.responder_address),
+                rr.path_cost
+            );
+            if let Some(address) = rr.orginator_ieee_address {
+                let _ = uwrite!(line, " Originator {:04x}", u64::from(address));
+            }
+            if let Some(address) = rr.responder_ieee_address {
+                let _ = uwrite!(line, " Responder {:08x}", u64::from(address));
+            }
+        }
+        Command::NetworkStatus(ns) => {
+            let _ = uwrite!(
+                line,
+                "Network Status Destination {:04x} Status {:02x}",
+                u16::from(ns.destination),
+                u8::from(ns.status)
+            );
+        }
+        Command::Leave(leave) => {
+            let _ = uwrite!(
+                line,
+                "Leave {}{}{}",
+                if leave.rejoin { "Rejoin " } else { "" },
+                if leave.request { "Request " } else { "" },
+                if leave.remove_children {
+                    "Remove children "
+                } else {
+                    ""
+                },
+            );
+        }
+        Command::RouteRecord(rr) => {
+            let _ = uwrite!(line, "Route Record ");
+            for address in rr.entries() {
+                let _ = uwrite!(line, "{:04x} ", u16::from(*address));
+            }
+        }
+        Command::RejoinRequest(_rr) => {
+            let _ = uwrite!(line, "Rejoin Request");
+        }
+        Command::RejoinResponse(_rr) => {
+            let _ = uwrite!(line, "Rejoin Response");
+        }
+        Command::LinkStatus(ls) => {
+            let _ = uwrite!(line, "Link Status ");
+            if ls.first_frame && !ls.last_frame {
+                let _ = uwrite!(line, "First ");
+            } else if !ls.first_frame && ls.last_frame {
+                let _ = uwrite!(line, "Last ");
+            }
+            for entry in ls.entries() {
+                let _ = uwrite!(
+                    line,
+                    "{:04x} Incoming {} Outgoing {} ",
+                    u16::from(entry.address),
+                    entry.incoming_cost,
+                    entry.outgoing_cost
+                );
+            }
+        }
+        Command::NetworkReport(nr) => {
+            let _ = uwrite!(
+                line,
+                "Network Conflict {:08x} {:04x}",
+                u64::from(nr.extended_pan_identifier),
+                u16::from(nr.pan_identifier)
+            );
+        }
+        Command::NetworkUpdate(nu) => {
+            let _ = uwrite!(
+                line,
+                "Network Update {:08x} {:04x}",
+                u64::from(nu.extended_pan_identifier),
+                u16::from(nu.pan_identifier)
+            );
+        }
+        Command::EndDeviceTimeoutRequest(edtr) => {
+            let _ = uwrite!(
+                line,
+                "End-device Timeout Request, Timeout {}s",
+                edtr.timeout.in_seconds()
+            );
+        }
+        Command::EndDeviceTimeoutResponse(edtr) => {
+            let _ = uwrite!(
+                line,
+                "End-device Timeout Response, {} {} {}",
+                u8::from(edtr.status),
+                if edtr.mac_keep_alive {
+                    "MAC keep alive"
+                } else {
+                    ""
+                },
+                if edtr.end_device_keep_alive {
+                    "End device keep alive"
+                } else {
+                    ""
+                },
+            );
+        }
+    }
+}
+
+pub fn write_network_header<W: uWrite>(line: &mut W, network_frame: &NetworkHeader) {
+    let frame_type = match network_frame.control.frame_type {
+        network::header::FrameType::Command => "Command",
+        network::header::FrameType::Data => "Data",
+        network::header::FrameType::InterPan => "Inter-PAN",
+    };
+    let discovery = match network_frame.control.discover_route {
+        network::header::DiscoverRoute::EnableDiscovery => " DSC",
+        network::header::DiscoverRoute::SuppressDiscovery => "",
+    };
+    let security = if network_frame.control.security {
+        " SEC"
+    } else {
+        ""
+    };
+    let _ = uwrite!(
+        line,
+        "NWK {} VER {}{}{} DST {:04x} SRC {:04x} RAD {} SEQ {}",
+        frame_type,
+        network_frame.control.protocol_version,
+        discovery,
+        security,
+        u16::from(network_frame.destination_address),
+        u16::from(network_frame.source_address),
+        network_frame.radius,
+        network_frame.sequence_number,
+    );
+    if let Some(dst) = network_frame.destination_ieee_address {
+        let _ = uwrite!(line, " DST {:08x}", u64::from(dst));
+    }
+    if let Some(src) = network_frame.source_ieee_address {
+        let _ = uwrite!(line, " SRC {:08x}", u64::from(src));
+    }
+    if let Some(ref mc) = network_frame.multicast_control {
+        let mode = match mc.mode {
+            network::header::MulticastMode::NonmemberMode => "non-member",
+            network::header::MulticastMode::MemberMode => "member",
+        };
+        let _ = uwrite!(line, " MC {} RAD {} MAX {}", mode, mc.radius, mc.max_radius);
+    }
+    if let Some(ref srf) = network_frame.source_route_frame {
+        let _ = uwrite!(line, " SRF I {}", srf.index);
+        for address in srf.entries() {
+            let _ = uwrite!(line, " {:04x}", u16::from(*address));
+        }
+    }
+}
+
+pub fn write_metadata<W: uWrite>(line: &mut W, metadata: &RxMetadata) {
+    if let Some(channel) = metadata.channel {
+        let _ = uwrite!(line, " CH: {}", channel);
+    }
+    if let Some(rssi) = metadata.rssi {
+        let _ = uwrite!(line, " RSSI: {}", rssi);
+    }
+    if let Some(lqi) = metadata.lqi {
+        let _ = uwrite!(line, " LQI: {}", lqi);
+    }
+    match metadata.fcs {
+        FcsStatus::Good => {
+            let _ = uwrite!(line, " FCS: OK");
+        }
+        FcsStatus::Bad => {
+            let _ = uwrite!(line, " FCS: BAD");
+        }
+        FcsStatus::Unknown => (),
+    }
+}
+
+/// Status of an association response, including the IEEE 802.15.4-2015 status values
+fn association_status_name(status: u8) -> &'static str {
+    match status {
+        0x00 => "Successful",
+        0x01 => "PAN at capacity",
+        0x02 => "PAN access denied",
+        0x03 => "Hopping sequence offset duplication",
+        0x80 => "Fast association successful",
+        _ => "Reserved",
+    }
+}
+
+pub fn write_mac<W: uWrite>(line: &mut W, header: &mac::Header, content: &mac::FrameContent) {
+    let enhanced = matches!(header.version, mac::FrameVersion::Ieee802154);
+    let frame_type = match header.frame_type {
+        mac::FrameType::Acknowledgement if enhanced => "Enhanced acknowledgement",
+        mac::FrameType::Acknowledgement => "Acknowledgement",
+        mac::FrameType::Beacon if enhanced => "Enhanced beacon",
+        mac::FrameType::Beacon => "Beacon",
+        mac::FrameType::Data => "Data",
+        mac::FrameType::MacCommand => "Command",
+        mac::FrameType::Multipurpose => "Multipurpose",
+        mac::FrameType::FragOrFragAck => "Fragment",
+        mac::FrameType::Extended => "Extended",
+    };
+    let frame_version = match header.version {
+        mac::FrameVersion::Ieee802154_2003 => "2003",
+        mac::FrameVersion::Ieee802154_2006 => "2006",
+        mac::FrameVersion::Ieee802154 => "2015",
+    };
+    let _ = uwrite!(line, "802.15.4 VER: {} TYPE: {}", frame_version, frame_type);
+    if header.frame_pending {
+        let _ = uwrite!(line, " PEND");
+    }
+    if header.ack_request {
+        let _ = uwrite!(line, " ACK");
+    }
+    if header.pan_id_compress {
+        let _ = uwrite!(line, " CMPR");
+    }
+    let _ = uwrite!(line, " SEQ: {}", header.seq);
+    match header.destination {
+        Some(mac::Address::Short(i, a)) => {
+            let _ = uwrite!(line, " DST: {:04x}:{:04x}", i.0, a.0);
+        }
+        Some(mac::Address::Extended(i, a)) => {
+            let _ = uwrite!(line, " DST: {:04x}:{:016x}", i.0, a.0);
+        }
+        None => (),
+    }
+    match header.source {
+        Some(mac::Address::Short(i, a)) => {
+            let _ = uwrite!(line, " SRC: {:04x}:{:04x}", i.0, a.0);
+        }
+        Some(mac::Address::Extended(i, a)) => {
+            let _ = uwrite!(line, " SRC: {:04x}:{:016x}", i.0, a.0);
+        }
+        None => (),
+    }
+    match content {
+        mac::FrameContent::Acknowledgement => {
+            // Nothing here
+        }
+        mac::FrameContent::Beacon(beacon) => {
+            let _ = uwrite!(line, " Beacon ");
+            match beacon.superframe_spec.beacon_order {
+                BeaconOrder::OnDemand => {
+                    let _ = uwrite!(line, "on-demand ");
+                }
+                BeaconOrder::BeaconOrder(value) => {
+                    let _ = uwrite!(line, "order {} ", value);
+                }
+            }
+            let coordinator = if beacon.superframe_spec.pan_coordinator {
+                "Coordinator"
+            } else {
+                "Device"
+            };
+            let association_permit = if beacon.superframe_spec.association_permit {
+                "Permit association"
+            } else {
+                "Deny association"
+            };
+            let _ = uwrite!(line, "{} {}", coordinator, association_permit);
+            if beacon.superframe_spec.battery_life_extension {
+                let _ = uwrite!(line, " Battery life extension");
+            }
+            if beacon.guaranteed_time_slot_info.permit {
+                let _ = uwrite!(
+                    line,
+                    " GTS slots {}",
+                    beacon.guaranteed_time_slot_info.slots().len()
+                );
+            }
+            let short_addresses = beacon.pending_address.short_addresses();
+            let extended_addresses = beacon.pending_address.extended_addresses();
+            if !short_addresses.is_empty() || !extended_addresses.is_empty() {
+                let _ = uwrite!(line, " Pending");
+                for address in short_addresses.iter() {
+                    let _ = uwrite!(line, " {:04x}", address.0);
+                }
+                for address in extended_addresses.iter() {
+                    let _ = uwrite!(line, " {:016x}", address.0);
+                }
+            }
+        }
+        mac::FrameContent::Data => (),
+        mac::FrameContent::Command(command) => {
+            let _ = uwrite!(line, " Command ");
+            match command {
+                mac::command::Command::AssociationRequest(cmd) => {
+                    let _ = uwrite!(line, "Association request ");
+                    if cmd.full_function_device {
+                        let _ = uwrite!(line, "FFD ");
+                    } else {
+                        let _ = uwrite!(line, "RFD ");
+                    }
+                    if cmd.mains_power {
+                        let _ = uwrite!(line, "Mains power ");
+                    }
+                    if cmd.idle_receive {
+                        let _ = uwrite!(line, "Idle Rx ");
+                    }
+                    if cmd.frame_protection {
+                        let _ = uwrite!(line, "Secure ");
+                    }
+                    if cmd.allocate_address {
+                        let _ = uwrite!(line, "Allocate address ");
+                    }
+                }
+                mac::command::Command::AssociationResponse(address, status) => {
+                    let _ = uwrite!(
+                        line,
+                        " Association response {:04x} {}",
+                        address.0,
+                        association_status_name(u8::from(*status))
+                    );
+                }
+                mac::command::Command::DisassociationNotification(reason) => {
+                    let reason = match reason {
+                        mac::command::DisassociationReason::CoordinatorLeave => {
+                            "requested to leave"
+                        }
+                        mac::command::DisassociationReason::DeviceLeave => "leave",
+                    };
+                    let _ = uwrite!(line, " Disassociation {}", reason);
+                }
+                mac::command::Command::BeaconRequest => {
+                    let _ = uwrite!(line, " Beacon request");
+                }
+                mac::command::Command::DataRequest => {
+                    let _ = uwrite!(line, " Data request");
+                }
+                mac::command::Command::PanIdConflictNotification => {
+                    let _ = uwrite!(line, " PAN ID conflict");
+                }
+                mac::command::Command::OrphanNotification => {
+                    let _ = uwrite!(line, " Orphan notification");
+                }
+                mac::command::Command::CoordinatorRealignment(data) => {
+                    let _ = uwrite!(
+                        line,
+                        " Coordinator realignment PAN {:04x} Coordinator {:04x} Channel {}",
+                        data.pan_id.0,
+                        data.coordinator_address.0,
+                        data.channel
+                    );
+                    if let Some(page) = data.channel_page {
+                        let _ = uwrite!(line, " Page {}", page);
+                    }
+                    // Broadcast when the realignment is not directed to an orphaned device
+                    if data.device_address.0 != 0xffff {
+                        let _ = uwrite!(line, " Device {:04x}", data.device_address.0);
+                    }
+                }
+                mac::command::Command::GuaranteedTimeSlotRequest(characteristics) => {
+                    let direction = if characteristics.receive_only {
+                        "receive"
+                    } else {
+                        "transmit"
+                    };
+                    let allocation = if characteristics.allocation {
+                        "allocate"
+                    } else {
+                        "deallocate"
+                    };
+                    let _ = uwrite!(
+                        line,
+                        " GTS request {} {} slots {}",
+                        allocation,
+                        characteristics.count,
+                        direction
+                    );
+                }
+            }
+        }
+        // The payload is all there is, shown with the frame
+        mac::FrameContent::Multipurpose => (),
+        mac::FrameContent::FragOrFragAck => (),
+        mac::FrameContent::Extended => (),
+    }
+}
+
+/// Write a header or payload IE, Zigbee IEs are broken down
+pub fn write_information_element<W: uWrite>(line: &mut W, element: &InformationElement) {
+    let kind = match element.kind {
+        ElementKind::Header => "Header",
+        ElementKind::Payload => "Payload",
+    };
+    let _ = uwrite!(
+        line,
+        "IE {} {:02x} {}",
+        kind,
+        element.identifier,
+        element.name()
+    );
+    let mut content = &element.content[..];
+    if let Some(oui) = element.vendor_oui() {
+        let _ = uwrite!(line, " OUI {:06x}", oui);
+        content = &content[3..];
+    }
+    match element.zigbee_elements() {
+        Some(elements) => {
+            let _ = uwrite!(line, " Zigbee");
+            for zigbee in elements {
+                match zigbee {
+                    Ok(ZigbeeElement::Rejoin {
+                        extended_pan_identifier,
+                        short_address,
+                    }) => {
+                        let _ = uwrite!(
+                            line,
+                            " Rejoin {:016x} {:04x}",
+                            extended_pan_identifier,
+                            short_address
+                        );
+                    }
+                    Ok(ZigbeeElement::TxPower(power)) => {
+                        let _ = uwrite!(line, " TX power {} dBm", power);
+                    }
+                    Ok(ZigbeeElement::BeaconPayload(_)) => {
+                        let _ = uwrite!(line, " Beacon payload");
+                    }
+                    Ok(ZigbeeElement::Other(identifier, data)) => {
+                        let _ = uwrite!(line, " {:03x} ", identifier);
+                        print_bytes(line, data);
+                    }
+                    Err(_) => {
+                        let _ = uwrite!(line, " Broken");
+                    }
+                }
+            }
+        }
+        None => {
+            if !content.is_empty() {
+                let _ = uwrite!(line, " ");
+                print_bytes(line, content);
+            }
+        }
+    }
+}
+
+fn write_device<W: uWrite>(line: &mut W, label: &str, address: u16, devices: &DeviceTable) {
+    if let Some(device) = devices.by_short_address(address) {
+        let _ = uwrite!(line, " {} {:04x}", label, address);
+        if let Some(extended) = device.extended_address {
+            let _ = uwrite!(line, " {:016x}", extended);
+        }
+        if device.role != crate::devices::Role::Unknown {
+            let _ = uwrite!(line, " {}", device.role.name());
+        }
+    }
+}
+
+/// Write the devices known for the short addresses in the MAC and NWK headers
+pub fn write_frame_devices<W: uWrite>(line: &mut W, frame: &DecodedFrame, devices: &DeviceTable) {
+    let _ = uwrite!(line, "DEV");
+    if let Some(mac::Address::Short(_, address)) = frame.mac.source {
+        write_device(line, "MAC SRC", address.0, devices);
+    }
+    if let Some(mac::Address::Short(_, address)) = frame.mac.destination {
+        write_device(line, "MAC DST", address.0, devices);
+    }
+    if let Some(ref header) = frame.network {
+        write_device(line, "NWK SRC", u16::from(header.source_address), devices);
+        write_device(
+            line,
+            "NWK DST",
+            u16::from(header.destination_address),
+            devices,
+        );
+    }
+}
+
+/// Print the frame followed by the devices the short addresses belong to
+pub fn print_frame_with_devices(frame: &DecodedFrame, devices: &DeviceTable) {
+    print_frame(frame);
+    let mut line = Line::new();
+    write_frame_devices(&mut line, frame, devices);
+    if line.len() > 3 {
+        info!("{}", line.as_str());
+    }
+}
+
+fn print_frame_counter_status(status: &Option<FrameCounterStatus>) {
+    if let Some(status) = status.filter(|s| s.is_suspicious()) {
+        let mut line = Line::new();
+        let _ = uwrite!(line, "SEC Frame counter ");
+        write_frame_counter_status(&mut line, &status);
+        warn!("{}", line.as_str());
+    }
+}
+
+pub fn print_frame(frame: &DecodedFrame) {
+    let mut line = Line::new();
+
+    write_mac(&mut line, &frame.mac, &frame.content);
+    write_metadata(&mut line, &frame.metadata);
+    info!("{}", line.as_str());
+
+    if let Some(ref header) = frame.mac_security {
+        line.clear();
+        write_mac_security_header(&mut line, header);
+        if frame.mac_unverified {
+            let _ = uwrite!(line, " Unverified");
+        }
+        info!("{}", line.as_str());
+    }
+    for element in frame.information_elements.iter() {
+        line.clear();
+        write_information_element(&mut line, element);
+        info!("{}", line.as_str());
+    }
+    if let Some(ref header) = frame.network {
+        line.clear();
+        write_network_header(&mut line, header);
+        info!("{}", line.as_str());
+    }
+    if let Some(ref header) = frame.network_security {
+        line.clear();
+        write_security_header(&mut line, header);
+        info!("{}", line.as_str());
+        print_frame_counter_status(&frame.network_frame_counter);
+    }
+    if let Some(ref header) = frame.application_service {
+        line.clear();
+        write_application_service_header(&mut line, header);
+        match frame.payload {
+            Payload::ApplicationServiceData(ref payload) => {
+                let _ = uwrite!(line, " Payload: ");
+                print_bytes(&mut line, payload);
+            }
+            Payload::ApplicationServiceAcknowledgement(ref payload) => {
+                if !payload.is_empty() {
+                    let _ = uwrite!(line, " APS Acknowledgement Payload: ");
+                    print_bytes(&mut line, payload);
+                }
+            }
+            Payload::ApplicationServiceInterPan(ref payload) => {
+                let _ = uwrite!(line, " APS Inter-PAN Payload: ");
+                print_bytes(&mut line, payload);
+            }
+            _ => (),
+        }
+        info!("{}", line.as_str());
+    }
+    if let Some(ref header) = frame.application_service_security {
+        line.clear();
+        write_security_header(&mut line, header);
+        info!("{}", line.as_str());
+        print_frame_counter_status(&frame.application_service_frame_counter);
+    }
+    match frame.payload {
+        Payload::Beacon(ref beacon) => {
+            line.clear();
+            write_beacon_payload(&mut line, beacon);
+            info!("{}", line.as_str());
+        }
+        Payload::MacData(ref payload) => {
+            line.clear();
+            let _ = uwrite!(line, "MAC Payload: ");
+            print_bytes(&mut line, payload);
+            info!("{}", line.as_str());
+        }
+        Payload::MacCommand {
+            identifier,
+            name,
+            ref body,
+        } => {
+            line.clear();
+            let _ = uwrite!(line, "MAC Command: {:02x} {}", identifier, name);
+            if !body.is_empty() {
+                let _ = uwrite!(line, " Payload: ");
+                print_bytes(&mut line, body);
+            }
+            info!("{}", line.as_str());
+        }
+        Payload::ClusterLibrary(ref zcl_frame) => {
+            line.clear();
+            write_cluster_library_frame(&mut line, zcl_frame);
+            info!("{}", line.as_str());
+        }
+        Payload::DeviceObject(ref zdo_frame) => {
+            line.clear();
+            write_device_object_frame(&mut line, zdo_frame);
+            info!("{}", line.as_str());
+        }
+        Payload::NetworkCommand(ref cmd) => {
+            line.clear();
+            write_network_command(&mut line, cmd);
+            info!("{}", line.as_str());
+        }
+        Payload::ApplicationServiceCommand(ref cmd) => {
+            line.clear();
+            write_application_service_command(&mut line, cmd);
+            info!("{}", line.as_str());
+        }
+        _ => (),
+    }
+    if let Some(ref error) = frame.error {
+        match (error, error.error()) {
+            (_, Some(e)) => crate::print_error(e, error.message()),
+            (DecodeError::UnknownKeySequence(sequence), None) => {
+                warn!("{} {}", error.message(), sequence)
+            }
+            (_, None) => warn!("{}", error.message()),
+        }
+    }
+}
+
+/// Print the counters, one line per group
+pub fn print_statistics(statistics: &Statistics) {
+    let mut line = Line::new();
+    let _ = uwrite!(
+        line,
+        "STAT Frames {} Malformed {} Dropped {} No valid key {} Too large {}",
+        statistics.frames,
+        statistics.malformed,
+        statistics.dropped,
+        statistics.no_valid_key,
+        statistics.payload_too_large
+    );
+    info!("{}", line.as_str());
+
+    if !statistics.bad_fcs.is_empty() {
+        line.clear();
+        let _ = uwrite!(line, "STAT Bad FCS");
+        for (channel, count) in statistics.bad_fcs.iter() {
+            let _ = uwrite!(line, " CH {} {}", *channel, *count);
+        }
+        info!("{}", line.as_str());
+    }
+
+    line.clear();
+    let _ = uwrite!(line, "STAT MAC");
+    for (name, count) in statistics.mac_frame_types.iter() {
+        let _ = uwrite!(line, " {} {}", *name, *count);
+    }
+    for (command, count) in statistics.mac_commands.iter() {
+        let _ = uwrite!(line, " CMD {:02x} {}", *command, *count);
+    }
+    info!("{}", line.as_str());
+
+    line.clear();
+    let _ = uwrite!(line, "STAT NWK");
+    for (name, count) in statistics.network_frame_types.iter() {
+        let _ = uwrite!(line, " {} {}", *name, *count);
+    }
+    for (command, count) in statistics.network_commands.iter() {
+        let _ = uwrite!(line, " CMD {:02x} {}", *command, *count);
+    }
+    info!("{}", line.as_str());
+
+    line.clear();
+    let _ = uwrite!(line, "STAT APS");
+    for (name, count) in statistics.application_service_frame_types.iter() {
+        let _ = uwrite!(line, " {} {}", *name, *count);
+    }
+    for (command, count) in statistics.application_service_commands.iter() {
+        let _ = uwrite!(line, " CMD {:02x} {}", *command, *count);
+    }
+    info!("{}", line.as_str());
+
+    line.clear();
+    let _ = uwrite!(line, "STAT PAN");
+    for (pan, count) in statistics.pan_identifiers.iter() {
+        let _ = uwrite!(line, " {:04x} {}", *pan, *count);
+    }
+    info!("{}", line.as_str());
+
+    for (name, count) in statistics.decode_errors.iter() {
+        line.clear();
+        let _ = uwrite!(line, "STAT Error {} {}", *name, *count);
+        info!("{}", line.as_str());
+    }
+    for (index, key) in statistics.keys.iter().enumerate() {
+        line.clear();
+        let _ = uwrite!(line, "STAT Key {} ", index);
+        print_key_fingerprint(&mut line, &key.key);
+        let _ = uwrite!(line, " Success {} Failure {}", key.successes, key.failures);
+        info!("{}", line.as_str());
+    }
+}
+
+pub fn print_survey(summary: &SurveySummary) {
+    let mut line = Line::new();
+    for channel in summary.channels.iter() {
+        line.clear();
+        let _ = uwrite!(
+            line,
+            "SURVEY CH {} Frames {} Bytes {} Beacons {} Malformed {} Bad FCS {}",
+            channel.channel,
+            channel.frames,
+            channel.bytes,
+            channel.beacons,
+            channel.malformed,
+            channel.bad_fcs
+        );
+        if let Some(rssi) = channel.strongest_rssi {
+            let _ = uwrite!(line, " RSSI {}", rssi);
+        }
+        for pan in channel.pan_identifiers.iter() {
+            let _ = uwrite!(line, " PAN {:04x}", *pan);
+        }
+        for extended in channel.extended_pan_identifiers.iter() {
+            let _ = uwrite!(line, " EPID {:016x}", *extended);
+        }
+        info!("{}", line.as_str());
+    }
+}
@@ -0,0 +1,109 @@
+//! Frame counter table per source and key sequence, classifies each counter as New, Valid, Replay,
+//! Regression or Jump.
+
+use psila_data::{security::SecurityHeader, ExtendedAddress};
+
+pub const MAX_FRAME_COUNTERS: usize = 64;
+/// Counter increments larger than this are reported as jumps
+pub const DEFAULT_JUMP_THRESHOLD: u32 = 1024;
+
+/// Result of checking the frame counter of a secured frame against earlier frames from the same
+/// source
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FrameCounterStatus {
+    /// First frame seen from the source with this key
+    New,
+    /// The counter increased as expected
+    Valid,
+    /// Same counter as the previous frame, a replay or a MAC retransmission
+    Replay,
+    /// The counter is lower than the previous one, holds the previous counter
+    Regression(u32),
+    /// The counter increased by more than the threshold, holds the previous counter
+    Jump(u32),
+}
+
+impl FrameCounterStatus {
+    /// Is the status something worth reporting
+    pub fn is_suspicious(&self) -> bool {
+        matches!(
+            self,
+            FrameCounterStatus::Replay
+                | FrameCounterStatus::Regression(_)
+                | FrameCounterStatus::Jump(_)
+        )
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FrameCounterEntry {
+    pub source: ExtendedAddress,
+    /// Key sequence number for network keys, none for link keys
+    pub sequence: Option<u8>,
+    pub counter: u32,
+}
+
+/// Last seen frame counter per source IEEE address and key sequence
+pub struct FrameCounterTable {
+    pub entries: heapless::Vec<FrameCounterEntry, MAX_FRAME_COUNTERS>,
+    pub jump_threshold: u32,
+}
+
+impl FrameCounterTable {
+    pub fn new() -> Self {
+        FrameCounterTable {
+            entries: heapless::Vec::new(),
+            jump_threshold: DEFAULT_JUMP_THRESHOLD,
+        }
+    }
+
+    /// Check and record the counter of an authenticated security header. Headers without source
+    /// address can not be tracked.
+    pub fn check(&mut self, header: &SecurityHeader) -> Option<FrameCounterStatus> {
+        let source = header.source?;
+        let counter = header.counter;
+        let entry = self
+            .entries
+            .iter_mut()
+            .find(|e| e.source == source && e.sequence == header.sequence);
+        let status = match entry {
+            Some(entry) => {
+                let previous = entry.counter;
+                let status = if counter == previous {
+                    FrameCounterStatus::Replay
+                } else if counter < previous {
+                    FrameCounterStatus::Regression(previous)
+                } else if counter - previous > self.jump_threshold {
+                    FrameCounterStatus::Jump(previous)
+                } else {
+                    FrameCounterStatus::Valid
+                };
+                // Follow the device after a reset or jump, so only the first frame is reported
+                entry.counter = counter;
+                status
+            }
+            None => {
+                if self.entries.is_full() {
+                    self.entries.remove(0);
+                }
+                let _ = self.entries.push(FrameCounterEntry {
+                    source,
+                    sequence: header.sequence,
+                    counter,
+                });
+                FrameCounterStatus::New
+            }
+        };
+        Some(status)
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+}
+
+impl Default for FrameCounterTable {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,238 @@
+//! IEEE 802.15.4-2015 information elements, carried after the MAC header and at the start of the MAC
+//! payload.
+//!
+//! Elements are decoded here and removed from the frame before the rest of it is parsed.
+
+use psila_data::Error;
+
+use crate::beacon::BeaconPayload;
+use crate::reader::ByteReader;
+
+pub const MAX_INFORMATION_ELEMENTS: usize = 6;
+/// Content of an element, the largest that fits in a frame
+pub const MAX_ELEMENT_SIZE: usize = 125;
+
+const HEADER_VENDOR_SPECIFIC: u8 = 0x00;
+const HEADER_TERMINATION_1: u8 = 0x7e;
+const HEADER_TERMINATION_2: u8 = 0x7f;
+const PAYLOAD_ESDU: u8 = 0x0;
+const PAYLOAD_MLME: u8 = 0x1;
+const PAYLOAD_VENDOR_SPECIFIC: u8 = 0x2;
+const PAYLOAD_TERMINATION: u8 = 0xf;
+
+/// Organizationally unique identifier of the Zigbee Alliance
+pub const ZIGBEE_OUI: u32 = 0x4a_191b;
+
+pub type ElementBuffer = heapless::Vec<u8, MAX_ELEMENT_SIZE>;
+pub type InformationElements = heapless::Vec<InformationElement, MAX_INFORMATION_ELEMENTS>;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ElementKind {
+    /// Header IE, identified by the element identifier
+    Header,
+    /// Payload IE, identified by the group identifier
+    Payload,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct InformationElement {
+    pub kind: ElementKind,
+    /// Element identifier of header IEs, group identifier of payload IEs
+    pub identifier: u8,
+    pub content: ElementBuffer,
+}
+
+impl InformationElement {
+    pub fn name(&self) -> &'static str {
+        match self.kind {
+            ElementKind::Header => match self.identifier {
+                HEADER_VENDOR_SPECIFIC => "Vendor specific",
+                0x1a => "CSL",
+                0x1b => "RIT",
+                0x1c => "DSME PAN descriptor",
+                0x1d => "Rendezvous time",
+                0x1e => "Time correction",
+                0x21 => "Simplified superframe specification",
+                0x22 => "Simplified GTS specification",
+                0x23 => "LECIM capabilities",
+                0x24 => "TRLE descriptor",
+                0x25 => "RCC capabilities",
+                0x26 => "RCCN descriptor",
+                0x27 => "Global time",
+                0x29 => "DA",
+                _ => "Unknown",
+            },
+            ElementKind::Payload => match self.identifier {
+                PAYLOAD_ESDU => "ESDU",
+                PAYLOAD_MLME => "MLME",
+                PAYLOAD_VENDOR_SPECIFIC => "Vendor specific",
+                _ => "Unknown",
+            },
+        }
+    }
+
+    fn is_vendor_specific(&self) -> bool {
+        match self.kind {
+            ElementKind::Header => self.identifier == HEADER_VENDOR_SPECIFIC,
+            ElementKind::Payload => self.identifier == PAYLOAD_VENDOR_SPECIFIC,
+        }
+    }
+
+    /// The OUI of vendor specific elements
+    pub fn vendor_oui(&self) -> Option<u32> {
+        if !self.is_vendor_specific() || self.content.len() < 3 {
+            return None;
+        }
+        Some(u32::from_le_bytes([
+            self.content[0],
+            self.content[1],
+            self.content[2],
+            0,
+        ]))
+    }
+
+    /// The Zigbee sub-elements of a vendor specific element with the Zigbee OUI
+    pub fn zigbee_elements(&self) -> Option<ZigbeeElements<'_>> {
+        if self.vendor_oui() != Some(ZIGBEE_OUI) {
+            return None;
+        }
+        Some(ZigbeeElements {
+            reader: ByteReader::new(&self.content[3..]),
+        })
+    }
+}
+
+/// Zigbee IEs, found in vendor specific payload IEs of enhanced beacons and enhanced beacon
+/// requests
+#[derive(Clone, Debug, PartialEq)]
+pub enum ZigbeeElement<'a> {
+    Rejoin {
+        extended_pan_identifier: u64,
+        short_address: u16,
+    },
+    /// Transmit power in dBm
+    TxPower(i8),
+    BeaconPayload(BeaconPayload),
+    Other(u16, &'a [u8]),
+}
+
+pub struct ZigbeeElements<'a> {
+    reader: ByteReader<'a>,
+}
+
+impl<'a> ZigbeeElements<'a> {
+    fn read_element(&mut self) -> Result<ZigbeeElement<'a>, Error> {
+        let header = self.reader.u16()?;
+        let content = self.reader.bytes(usize::from(header & 0x003f))?;
+        let identifier = header >> 6;
+        let mut reader = ByteReader::new(content);
+        match identifier {
+            0x00 => Ok(ZigbeeElement::Rejoin {
+                extended_pan_identifier: reader.u64()?,
+                short_address: reader.u16()?,
+            }),
+            0x01 => Ok(ZigbeeElement::TxPower(reader.u8()? as i8)),
+            0x02 => Ok(ZigbeeElement::BeaconPayload(BeaconPayload::unpack(
+                content,
+            )?)),
+            _ => Ok(ZigbeeElement::Other(identifier, content)),
+        }
+    }
+}
+
+impl<'a> Iterator for ZigbeeElements<'a> {
+    type Item = Result<ZigbeeElement<'a>, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.reader.is_empty() {
+            return None;
+        }
+        let result = self.read_element();
+        if result.is_err() {
+            // Stop at the first broken element
+            self.reader = ByteReader::new(&[]);
+        }
+        Some(result)
+    }
+}
+
+fn push_element(
+    elements: &mut InformationElements,
+    kind: ElementKind,
+    identifier: u8,
+    content: &[u8],
+) -> Result<(), Error> {
+    let content = ElementBuffer::from_slice(content).map_err(|_| Error::NotEnoughSpace)?;
+    // Elements beyond the capacity are dropped
+    let _ = elements.push(InformationElement {
+        kind,
+        identifier,
+        content,
+    });
+    Ok(())
+}
+
+/// Size of the header IEs at the start of `data`, including the termination element
+pub(crate) fn header_elements_size(data: &[u8]) -> Result<usize, Error> {
+    let mut reader = ByteReader::new(data);
+    while !reader.is_empty() {
+        let header = reader.u16()?;
+        if header & 0x8000 != 0 {
+            return Err(Error::InvalidValue);
+        }
+        reader.bytes(usize::from(header & 0x007f))?;
+        if let HEADER_TERMINATION_1 | HEADER_TERMINATION_2 = ((header >> 7) & 0xff) as u8 {
+            break;
+        }
+    }
+    Ok(reader.offset())
+}
+
+/// Decode the header IEs starting at `offset` and the payload IEs following them. The frame
+/// without the elements, and with the IE present bit cleared, is written to `output`. Returns the
+/// size of the frame written.
+pub(crate) fn strip(
+    data: &[u8],
+    offset: usize,
+    output: &mut [u8],
+    elements: &mut InformationElements,
+) -> Result<usize, Error> {
+    let mut reader = ByteReader::new(data.get(offset..).ok_or(Error::WrongNumberOfBytes)?);
+    let mut payload_elements = false;
+    while !reader.is_empty() {
+        let header = reader.u16()?;
+        if header & 0x8000 != 0 {
+            return Err(Error::InvalidValue);
+        }
+        let content = reader.bytes(usize::from(header & 0x007f))?;
+        match ((header >> 7) & 0xff) as u8 {
+            HEADER_TERMINATION_1 => {
+                payload_elements = true;
+                break;
+            }
+            HEADER_TERMINATION_2 => break,
+            identifier => push_element(elements, ElementKind::Header, identifier, content)?,
+        }
+    }
+    while payload_elements && !reader.is_empty() {
+        let header = reader.u16()?;
+        if header & 0x8000 == 0 {
+            return Err(Error::InvalidValue);
+        }
+        let content = reader.bytes(usize::from(header & 0x07ff))?;
+        match ((header >> 11) & 0x0f) as u8 {
+            PAYLOAD_TERMINATION => break,
+            identifier => push_element(elements, ElementKind::Payload, identifier, content)?,
+        }
+    }
+    let payload = reader.remaining();
+    let size = offset + payload.len();
+    if size > output.len() {
+        return Err(Error::NotEnoughSpace);
+    }
+    output[..offset].copy_from_slice(&data[..offset]);
+    // Clear the IE present bit
+    output[1] &= !0x02;
+    output[offset..size].copy_from_slice(payload);
+    Ok(size)
+}
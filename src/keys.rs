@@ -0,0 +1,123 @@
+//! Parsing of keys written in the notations used by various tools.
+
+use psila_data::{common::key::Key, ExtendedAddress};
+
+const KEY_SIZE: usize = 16;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum KeyError {
+    /// A character that is neither a hexadecimal digit nor a separator
+    InvalidCharacter(char),
+    /// The key does not hold 16 bytes
+    InvalidLength(usize),
+    /// Malformed Wireshark key entry
+    InvalidEntry,
+    /// The IEEE address following a link key is not hexadecimal
+    InvalidAddress,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ByteOrder {
+    /// Bytes in transmission order, as in Transport Key commands
+    Normal,
+    /// Bytes in reversed order, as shown by some tools
+    Reverse,
+}
+
+fn hex_value(c: char) -> Result<u8, KeyError> {
+    c.to_digit(16)
+        .map(|v| v as u8)
+        .ok_or(KeyError::InvalidCharacter(c))
+}
+
+/// Parse a key of hexadecimal digits, bytes may be separated by colons, spaces or dashes. A `0x`
+/// prefix is accepted.
+pub fn parse_key_with_order(s: &str, order: ByteOrder) -> Result<Key, KeyError> {
+    let s = s.trim();
+    let s = s
+        .strip_prefix("0x")
+        .or_else(|| s.strip_prefix("0X"))
+        .unwrap_or(s);
+    let mut key = [0u8; KEY_SIZE];
+    let mut count = 0;
+    let mut high: Option<u8> = None;
+    for c in s.chars() {
+        if matches!(c, ':' | ' ' | '-') {
+            // A single digit group, e.g. "0:1:..."
+            if let Some(value) = high.take() {
+                if count < KEY_SIZE {
+                    key[count] = value;
+                }
+                count += 1;
+            }
+            continue;
+        }
+        let value = hex_value(c)?;
+        match high.take() {
+            Some(h) => {
+                if count < KEY_SIZE {
+                    key[count] = (h << 4) | value;
+                }
+                count += 1;
+            }
+            None => high = Some(value),
+        }
+    }
+    if let Some(value) = high {
+        if count < KEY_SIZE {
+            key[count] = value;
+        }
+        count += 1;
+    }
+    if count != KEY_SIZE {
+        return Err(KeyError::InvalidLength(count));
+    }
+    if order == ByteOrder::Reverse {
+        key.reverse();
+    }
+    Ok(Key::from(key))
+}
+
+/// Parse a key, Wireshark `zigbee_pc_keys` entries are recognised by the leading quote
+pub fn parse_key(s: &str) -> Result<Key, KeyError> {
+    let s = s.trim();
+    if s.starts_with('"') {
+        parse_wireshark_key(s).map(|(key, _)| key)
+    } else {
+        parse_key_with_order(s, ByteOrder::Normal)
+    }
+}
+
+/// Parse an entry of the Wireshark `zigbee_pc_keys` file, `"<key>","<Normal|Reverse>","<label>"`.
+/// Returns the key and the label.
+pub fn parse_wireshark_key(entry: &str) -> Result<(Key, &str), KeyError> {
+    let mut fields = entry.trim().splitn(3, ',').map(|field| {
+        field
+            .trim()
+            .strip_prefix('"')
+            .and_then(|f| f.strip_suffix('"'))
+            .ok_or(KeyError::InvalidEntry)
+    });
+    let key = fields.next().ok_or(KeyError::InvalidEntry)??;
+    let order = match fields.next().transpose()? {
+        Some("Normal") | None => ByteOrder::Normal,
+        Some("Reverse") => ByteOrder::Reverse,
+        Some(_) => return Err(KeyError::InvalidEntry),
+    };
+    let label = fields.next().transpose()?.unwrap_or("");
+    Ok((parse_key_with_order(key, order)?, label))
+}
+
+/// Parse a link key optionally followed by the IEEE address of the device using it,
+/// `<key>[@<IEEE>]` where the address is hexadecimal
+pub fn parse_link_key(s: &str) -> Result<(Key, Option<ExtendedAddress>), KeyError> {
+    match s.trim().rsplit_once('@') {
+        // Wireshark labels may hold an @
+        Some((key, address)) if !address.contains('"') => {
+            let address =
+                u64::from_str_radix(address.trim(), 16).map_err(|_| KeyError::InvalidAddress)?;
+            Ok((parse_key(key)?, Some(ExtendedAddress::from(address))))
+        }
+        _ => Ok((parse_key(s)?, None)),
+    }
+}
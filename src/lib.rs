@@ -1,10 +1,34 @@
-#![no_std]
+#![cfg_attr(not(feature = "std"), no_std)]
 
+#[macro_use]
+mod fmt;
+
+pub mod beacon;
+mod decoded;
+pub mod devices;
+pub mod fcs;
+mod formatter;
+mod frame_counter;
+pub mod information_element;
+mod keys;
+mod mac_header;
+pub mod mac_security;
 mod parser;
+pub mod pcap;
+pub mod radio;
+mod reader;
 mod security;
+pub mod snapshot;
+pub mod statistics;
+pub mod survey;
+pub mod topology;
+pub mod zcl;
+pub mod zdo;
+pub mod zep;
 
-pub fn print_error(error: &psila_data::Error, message: &str) {
-    let error_message = match error {
+/// Description of a decoding error
+pub fn error_name(error: &psila_data::Error) -> &'static str {
+    match error {
         psila_data::Error::NotEnoughSpace => "Not enough space",
         psila_data::Error::WrongNumberOfBytes => "Wrong number of bytes",
         psila_data::Error::InvalidValue => "Invalid value",
@@ -24,9 +48,32 @@ pub fn print_error(error: &psila_data::Error, message: &str) {
         psila_data::Error::UnknownClusterIdentifier => "Unknown cluster identifier",
         psila_data::Error::UnsupportedAttributeValue => "Unsupported attribute value",
         psila_data::Error::CryptoError(_) => "Crypto error",
-    };
-    defmt::error!("{}, {}", message, error_message);
+    }
+}
+
+pub fn print_error(error: &psila_data::Error, message: &str) {
+    error!("{}, {}", message, error_name(error));
 }
 
+pub use decoded::{
+    DecodeError, DecodedFrame, Payload, PayloadBuffer, RxMetadata, MAX_PAYLOAD_SIZE,
+};
+pub use formatter::{
+    print_frame, print_frame_with_devices, print_statistics, print_survey,
+    write_application_service_command, write_application_service_header, write_beacon_payload,
+    write_cluster_library_frame, write_device_object_frame, write_frame_counter_status,
+    write_frame_devices, write_information_element, write_mac, write_mac_security_header,
+    write_metadata, write_network_command, write_network_header, write_security_header,
+};
+pub use frame_counter::{
+    FrameCounterEntry, FrameCounterStatus, FrameCounterTable, DEFAULT_JUMP_THRESHOLD,
+    MAX_FRAME_COUNTERS,
+};
+pub use keys::{
+    parse_key, parse_key_with_order, parse_link_key, parse_wireshark_key, ByteOrder, KeyError,
+};
 pub use parser::Parser;
-pub use security::SecurityService;
+pub use security::{
+    hash_key, install_code_key, mmo_hash, ActiveNetworkKey, InstallCodeError, KeyStatistics,
+    LinkKeyEntry, MacKeyEntry, MmoHash, NetworkKeyEntry, SecurityService,
+};
@@ -0,0 +1,216 @@
+//! Layout of the IEEE 802.15.4 MAC header, used for the parts of frames that are handled before or
+//! beside the ieee802154 crate.
+
+use psila_data::Error;
+
+use crate::reader::ByteReader;
+
+const FRAME_CONTROL_SECURITY: u16 = 0x0008;
+const FRAME_CONTROL_FRAME_PENDING: u16 = 0x0010;
+const FRAME_CONTROL_ACK_REQUEST: u16 = 0x0020;
+const FRAME_CONTROL_PAN_ID_COMPRESSION: u16 = 0x0040;
+const FRAME_CONTROL_SEQUENCE_SUPPRESSION: u16 = 0x0100;
+const FRAME_CONTROL_IE_PRESENT: u16 = 0x0200;
+pub(crate) const FRAME_TYPE_BEACON: u8 = 0;
+pub(crate) const FRAME_TYPE_COMMAND: u8 = 3;
+pub(crate) const FRAME_TYPE_MULTIPURPOSE: u8 = 5;
+pub(crate) const FRAME_VERSION_2015: u8 = 2;
+const ADDRESS_MODE_SHORT: u16 = 2;
+const ADDRESS_MODE_EXTENDED: u16 = 3;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum SourceAddress {
+    Short(u16),
+    Extended(u64),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct HeaderLayout {
+    pub control: u16,
+    /// Size of the frame control, sequence number and addressing fields
+    pub length: usize,
+    pub source: Option<SourceAddress>,
+}
+
+impl HeaderLayout {
+    pub fn frame_type(&self) -> u8 {
+        (self.control & 0x0007) as u8
+    }
+
+    pub fn version(&self) -> u8 {
+        ((self.control >> 12) & 0x03) as u8
+    }
+
+    pub fn security(&self) -> bool {
+        self.control & FRAME_CONTROL_SECURITY != 0
+    }
+
+    pub fn information_elements(&self) -> bool {
+        self.control & FRAME_CONTROL_IE_PRESENT != 0
+    }
+}
+
+fn address_size(mode: u16) -> Result<usize, Error> {
+    match mode {
+        0 => Ok(0),
+        ADDRESS_MODE_SHORT => Ok(2),
+        ADDRESS_MODE_EXTENDED => Ok(8),
+        _ => Err(Error::InvalidValue),
+    }
+}
+
+/// Find the end of the addressing fields of a MAC frame
+pub(crate) fn header_layout(data: &[u8]) -> Result<HeaderLayout, Error> {
+    let mut reader = ByteReader::new(data);
+    let control = reader.u16()?;
+    let version = ((control >> 12) & 0x03) as u8;
+    let destination_mode = (control >> 10) & 0x03;
+    let source_mode = (control >> 14) & 0x03;
+    let compression = control & FRAME_CONTROL_PAN_ID_COMPRESSION != 0;
+    if version < FRAME_VERSION_2015 || control & FRAME_CONTROL_SEQUENCE_SUPPRESSION == 0 {
+        let _sequence = reader.u8()?;
+    }
+    let (destination_pan, source_pan) = if version < FRAME_VERSION_2015 {
+        (destination_mode != 0, source_mode != 0 && !compression)
+    } else {
+        // IEEE 802.15.4-2015 table 7-2
+        match (destination_mode, source_mode) {
+            (0, 0) => (compression, false),
+            (_, 0) => (!compression, false),
+            (0, _) => (false, !compression),
+            (ADDRESS_MODE_EXTENDED, ADDRESS_MODE_EXTENDED) => (!compression, false),
+            _ => (true, !compression),
+        }
+    };
+    if destination_pan {
+        reader.u16()?;
+    }
+    match destination_mode {
+        ADDRESS_MODE_SHORT => {
+            reader.u16()?;
+        }
+        ADDRESS_MODE_EXTENDED => {
+            reader.u64()?;
+        }
+        _ => (),
+    }
+    if source_pan {
+        reader.u16()?;
+    }
+    let source = match source_mode {
+        ADDRESS_MODE_SHORT => Some(SourceAddress::Short(reader.u16()?)),
+        ADDRESS_MODE_EXTENDED => Some(SourceAddress::Extended(reader.u64()?)),
+        _ => None,
+    };
+    Ok(HeaderLayout {
+        control,
+        length: reader.offset(),
+        source,
+    })
+}
+
+/// The command identifier of a MAC command frame without security and information elements
+pub(crate) fn command_identifier(data: &[u8]) -> Option<u8> {
+    let layout = header_layout(data).ok()?;
+    if layout.frame_type() != FRAME_TYPE_COMMAND
+        || layout.security()
+        || layout.information_elements()
+    {
+        return None;
+    }
+    data.get(layout.length).copied()
+}
+
+pub(crate) fn is_multipurpose(data: &[u8]) -> bool {
+    data.first()
+        .is_some_and(|control| control & 0x07 == FRAME_TYPE_MULTIPURPOSE)
+}
+
+/// Is the security enabled bit of the frame control set
+pub(crate) fn is_secured(data: &[u8]) -> bool {
+    data.first()
+        .is_some_and(|control| u16::from(*control) & FRAME_CONTROL_SECURITY != 0)
+}
+
+/// Clear the security enabled bit, found in the first byte of the frame control
+pub(crate) fn clear_security(data: &mut [u8]) {
+    if let Some(control) = data.first_mut() {
+        *control &= !(FRAME_CONTROL_SECURITY as u8);
+    }
+}
+
+fn put(output: &mut [u8], offset: &mut usize, bytes: &[u8]) -> Result<(), Error> {
+    let end = *offset + bytes.len();
+    output
+        .get_mut(*offset..end)
+        .ok_or(Error::NotEnoughSpace)?
+        .copy_from_slice(bytes);
+    *offset = end;
+    Ok(())
+}
+
+/// Rewrite a multipurpose frame, IEEE 802.15.4-2015 7.3.5, with the frame control and addressing
+/// fields used by the other frame types. Multipurpose frames only carry the destination PAN
+/// identifier, it is used for the source as well. Returns the size written to `output`.
+pub(crate) fn normalize_multipurpose(data: &[u8], output: &mut [u8]) -> Result<usize, Error> {
+    let mut reader = ByteReader::new(data);
+    let mut control = u16::from(reader.u8()?);
+    // The long frame control adds the PAN ID present, security, sequence number suppression,
+    // frame pending, version, ack request and IE present fields
+    if control & 0x0008 != 0 {
+        control |= u16::from(reader.u8()?) << 8;
+    }
+    let destination_mode = (control >> 4) & 0x03;
+    let source_mode = (control >> 6) & 0x03;
+    let sequence = if control & 0x0400 == 0 {
+        Some(reader.u8()?)
+    } else {
+        None
+    };
+    let pan = if control & 0x0100 != 0 {
+        reader.u16()?
+    } else {
+        0xffff
+    };
+    let destination = reader.bytes(address_size(destination_mode)?)?;
+    let source = reader.bytes(address_size(source_mode)?)?;
+
+    let mut regular = u16::from(FRAME_TYPE_MULTIPURPOSE)
+        | destination_mode << 10
+        | u16::from(FRAME_VERSION_2015) << 12
+        | source_mode << 14;
+    for (multipurpose, bit) in [
+        (0x0200, FRAME_CONTROL_SECURITY),
+        (0x0400, FRAME_CONTROL_SEQUENCE_SUPPRESSION),
+        (0x0800, FRAME_CONTROL_FRAME_PENDING),
+        (0x4000, FRAME_CONTROL_ACK_REQUEST),
+        (0x8000, FRAME_CONTROL_IE_PRESENT),
+    ] {
+        if control & multipurpose != 0 {
+            regular |= bit;
+        }
+    }
+    // IEEE 802.15.4-2015 table 7-2 without PAN ID compression
+    let (destination_pan, source_pan) = match (destination_mode, source_mode) {
+        (0, 0) => (false, false),
+        (_, 0) => (true, false),
+        (0, _) => (false, true),
+        (ADDRESS_MODE_EXTENDED, ADDRESS_MODE_EXTENDED) => (true, false),
+        _ => (true, true),
+    };
+    let mut offset = 0;
+    put(output, &mut offset, &regular.to_le_bytes())?;
+    if let Some(sequence) = sequence {
+        put(output, &mut offset, &[sequence])?;
+    }
+    if destination_pan {
+        put(output, &mut offset, &pan.to_le_bytes())?;
+    }
+    put(output, &mut offset, destination)?;
+    if source_pan {
+        put(output, &mut offset, &pan.to_le_bytes())?;
+    }
+    put(output, &mut offset, source)?;
+    put(output, &mut offset, reader.remaining())?;
+    Ok(offset)
+}
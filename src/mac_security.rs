@@ -0,0 +1,239 @@
+//! IEEE 802.15.4 MAC layer security, the auxiliary security header and CCM* as specified in
+//! IEEE 802.15.4-2006 7.6 and B.4.
+
+use aes::cipher::{generic_array::GenericArray, BlockEncrypt, KeyInit};
+use aes::Aes128;
+use psila_data::Error;
+
+use crate::information_element::header_elements_size;
+use crate::mac_header::{header_layout, SourceAddress, FRAME_TYPE_COMMAND, FRAME_VERSION_2015};
+use crate::reader::ByteReader;
+
+const BLOCK_SIZE: usize = 16;
+pub const NONCE_SIZE: usize = 13;
+/// Size of the length field in CCM*, 15 - nonce size
+const LENGTH_SIZE: usize = 2;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum KeyIdentifierMode {
+    /// The key is given by the sender and recipient
+    Implicit,
+    /// Key index only
+    Index,
+    /// 4 byte key source and key index
+    Source4,
+    /// 8 byte key source and key index
+    Source8,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AuxiliarySecurityHeader {
+    /// Security level 0 to 7, levels 4 and above are encrypted
+    pub level: u8,
+    pub key_identifier_mode: KeyIdentifierMode,
+    /// Not present when suppressed, IEEE 802.15.4-2015 only
+    pub frame_counter: Option<u32>,
+    pub key_source: Option<u64>,
+    pub key_index: Option<u8>,
+    /// The nonce uses the absolute slot number, IEEE 802.15.4-2015 TSCH only
+    pub asn_in_nonce: bool,
+}
+
+impl AuxiliarySecurityHeader {
+    pub fn unpack(data: &[u8]) -> Result<(Self, usize), Error> {
+        let mut reader = ByteReader::new(data);
+        let control = reader.u8()?;
+        let key_identifier_mode = match (control >> 3) & 0x03 {
+            0 => KeyIdentifierMode::Implicit,
+            1 => KeyIdentifierMode::Index,
+            2 => KeyIdentifierMode::Source4,
+            _ => KeyIdentifierMode::Source8,
+        };
+        let frame_counter = if control & 0x20 == 0x20 {
+            None
+        } else {
+            Some(reader.u32()?)
+        };
+        let key_source = match key_identifier_mode {
+            KeyIdentifierMode::Implicit | KeyIdentifierMode::Index => None,
+            KeyIdentifierMode::Source4 => Some(u64::from(reader.u32()?)),
+            KeyIdentifierMode::Source8 => Some(reader.u64()?),
+        };
+        let key_index = match key_identifier_mode {
+            KeyIdentifierMode::Implicit => None,
+            _ => Some(reader.u8()?),
+        };
+        let header = AuxiliarySecurityHeader {
+            level: control & 0x07,
+            key_identifier_mode,
+            frame_counter,
+            key_source,
+            key_index,
+            asn_in_nonce: control & 0x40 == 0x40,
+        };
+        Ok((header, reader.offset()))
+    }
+
+    pub fn is_encrypted(&self) -> bool {
+        self.level & 0x04 == 0x04
+    }
+
+    /// Does the frame carry a MIC, without one a decrypted payload can not be verified
+    pub fn is_authenticated(&self) -> bool {
+        self.mic_length() > 0
+    }
+
+    /// Size of the message integrity code, 0, 4, 8 or 16 bytes
+    pub fn mic_length(&self) -> usize {
+        match self.level & 0x03 {
+            0 => 0,
+            size => 2 << size,
+        }
+    }
+
+    pub fn level_name(&self) -> &'static str {
+        match self.level {
+            0 => "None",
+            1 => "MIC-32",
+            2 => "MIC-64",
+            3 => "MIC-128",
+            4 => "ENC",
+            5 => "ENC-MIC-32",
+            6 => "ENC-MIC-64",
+            _ => "ENC-MIC-128",
+        }
+    }
+}
+
+/// Where the parts of a secured MAC frame are
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct SecuredFrame {
+    /// Size of the MAC header before the auxiliary security header
+    pub header_length: usize,
+    pub security: AuxiliarySecurityHeader,
+    /// Offset of the first payload byte, after the auxiliary security header
+    pub payload_offset: usize,
+    /// Size of the header IEs following the auxiliary security header, these are not encrypted
+    pub header_elements: usize,
+    /// The payload starts with a command identifier sent in the clear, IEEE 802.15.4-2006 only
+    pub command: bool,
+    pub source: Option<SourceAddress>,
+}
+
+/// Locate the auxiliary security header of a MAC frame with security enabled
+pub(crate) fn secured_frame(data: &[u8]) -> Result<SecuredFrame, Error> {
+    let layout = header_layout(data)?;
+    if !layout.security() {
+        return Err(Error::InvalidValue);
+    }
+    let (security, used) = AuxiliarySecurityHeader::unpack(&data[layout.length..])?;
+    let payload_offset = layout.length + used;
+    let header_elements = if layout.information_elements() {
+        header_elements_size(&data[payload_offset..])?
+    } else {
+        0
+    };
+    Ok(SecuredFrame {
+        header_length: layout.length,
+        security,
+        payload_offset,
+        header_elements,
+        command: layout.frame_type() == FRAME_TYPE_COMMAND && layout.version() < FRAME_VERSION_2015,
+        source: layout.source,
+    })
+}
+
+/// The CCM* nonce, the source address and frame counter are big endian
+pub fn nonce(source: u64, frame_counter: u32, level: u8) -> [u8; NONCE_SIZE] {
+    let mut nonce = [0u8; NONCE_SIZE];
+    nonce[..8].copy_from_slice(&source.to_be_bytes());
+    nonce[8..12].copy_from_slice(&frame_counter.to_be_bytes());
+    nonce[12] = level;
+    nonce
+}
+
+fn encrypt_block(cipher: &Aes128, block: &[u8; BLOCK_SIZE]) -> [u8; BLOCK_SIZE] {
+    let mut output = GenericArray::clone_from_slice(block);
+    cipher.encrypt_block(&mut output);
+    let mut result = [0u8; BLOCK_SIZE];
+    result.copy_from_slice(&output);
+    result
+}
+
+/// CBC-MAC over `data` split in blocks and zero padded
+fn authenticate_blocks(cipher: &Aes128, state: &mut [u8; BLOCK_SIZE], data: &[u8]) {
+    for chunk in data.chunks(BLOCK_SIZE) {
+        for (s, d) in state.iter_mut().zip(chunk.iter()) {
+            *s ^= d;
+        }
+        *state = encrypt_block(cipher, state);
+    }
+}
+
+fn counter_block(cipher: &Aes128, nonce: &[u8; NONCE_SIZE], counter: u16) -> [u8; BLOCK_SIZE] {
+    let mut block = [0u8; BLOCK_SIZE];
+    block[0] = (LENGTH_SIZE - 1) as u8;
+    block[1..1 + NONCE_SIZE].copy_from_slice(nonce);
+    block[1 + NONCE_SIZE..].copy_from_slice(&counter.to_be_bytes());
+    encrypt_block(cipher, &block)
+}
+
+/// CCM* decryption and authentication
+///
+/// `open` is authenticated only, `private` holds the encrypted data followed by the MIC. For
+/// security levels without encryption the unencrypted payload is part of `open` and `private`
+/// is only the MIC. Returns the size of the data written to `output` if the MIC matches. Without
+/// MIC, security level 4, nothing can be verified and decryption always succeeds.
+pub fn ccm_star_decrypt(
+    key: &[u8; BLOCK_SIZE],
+    nonce: &[u8; NONCE_SIZE],
+    mic_length: usize,
+    encrypted: bool,
+    open: &[u8],
+    private: &[u8],
+    output: &mut [u8],
+) -> Option<usize> {
+    let size = private.len().checked_sub(mic_length)?;
+    if size > output.len() || (!encrypted && size > 0) || open.len() >= 0xff00 {
+        return None;
+    }
+    let cipher = Aes128::new(GenericArray::from_slice(key));
+    let (data, mic) = private.split_at(size);
+    for (index, chunk) in data.chunks(BLOCK_SIZE).enumerate() {
+        let stream = counter_block(&cipher, nonce, index as u16 + 1);
+        for (offset, (d, s)) in chunk.iter().zip(stream.iter()).enumerate() {
+            output[index * BLOCK_SIZE + offset] = d ^ s;
+        }
+    }
+    if mic_length == 0 {
+        return Some(size);
+    }
+    let mut state = [0u8; BLOCK_SIZE];
+    let additional_data = if open.is_empty() { 0 } else { 0x40 };
+    state[0] = additional_data | (((mic_length - 2) / 2) as u8) << 3 | (LENGTH_SIZE - 1) as u8;
+    state[1..1 + NONCE_SIZE].copy_from_slice(nonce);
+    state[1 + NONCE_SIZE..].copy_from_slice(&(size as u16).to_be_bytes());
+    state = encrypt_block(&cipher, &state);
+    if !open.is_empty() {
+        // The length of the additional data is prepended to it
+        let length = (open.len() as u16).to_be_bytes();
+        let first = open.len().min(BLOCK_SIZE - 2);
+        let mut block = [0u8; BLOCK_SIZE];
+        block[..2].copy_from_slice(&length);
+        block[2..2 + first].copy_from_slice(&open[..first]);
+        authenticate_blocks(&cipher, &mut state, &block);
+        authenticate_blocks(&cipher, &mut state, &open[first..]);
+    }
+    authenticate_blocks(&cipher, &mut state, &output[..size]);
+    let stream = counter_block(&cipher, nonce, 0);
+    let matches = mic
+        .iter()
+        .zip(state.iter().zip(stream.iter()))
+        .fold(0u8, |diff, (m, (t, s))| diff | (m ^ t ^ s));
+    if matches == 0 {
+        Some(size)
+    } else {
+        output[..size].fill(0);
+        None
+    }
+}
@@ -1,653 +1,592 @@
-use defmt;
-use ieee802154::mac::{self, beacon::BeaconOrder};
+use byte::BytesExt;
+use ieee802154::mac::{self, FooterMode};
 use psila_data::{
     application_service::{self, ApplicationServiceHeader},
     network::{self, NetworkHeader},
     pack::Pack,
+    security::SecurityHeader,
+    ExtendedAddress,
 };
-use ufmt::uwrite;
 
+use crate::beacon::{self, BeaconPayload};
+use crate::decoded::{DecodeError, DecodedFrame, Payload, PayloadBuffer, RxMetadata};
+use crate::devices::DeviceTable;
+use crate::fcs::{self, BadFcsPolicy};
+use crate::information_element::{self, InformationElements, ZigbeeElement};
+use crate::mac_header::{self, SourceAddress};
+use crate::mac_security::{self, AuxiliarySecurityHeader};
+use crate::radio::MAX_FRAME_SIZE;
 use crate::security::SecurityService;
+use crate::statistics::{mac_command_name, Statistics};
+use crate::{zcl, zdo};
+
+/// The Zigbee Device Profile, frames in this profile are not ZCL frames
+const PROFILE_DEVICE: u16 = 0x0000;
+/// MAC commands decoded by the ieee802154 crate, other commands are kept as `Payload::MacCommand`
+const DECODED_MAC_COMMANDS: core::ops::RangeInclusive<u8> = 0x01..=0x09;
 
 pub struct Parser {
     pub security: SecurityService,
+    /// Handling of frames where the FCS does not match
+    pub bad_fcs_policy: BadFcsPolicy,
+    statistics: Statistics,
 }
 
 impl Parser {
     pub fn new() -> Self {
         Parser {
             security: SecurityService::new(),
+            bad_fcs_policy: BadFcsPolicy::default(),
+            statistics: Statistics::new(),
         }
     }
 
-    fn print_key<W: ufmt::uWrite>(writer: &mut W, key: &psila_data::Key) {
-        let k: [u8; 16] = (*key).into();
-        for b in k.iter() {
-            let _ = uwrite!(writer, "{:02x}", *b);
+    /// Snapshot of the counters for the frames parsed so far
+    pub fn statistics(&self) -> Statistics {
+        let mut statistics = self.statistics.clone();
+        statistics.keys = self.security.key_statistics.clone();
+        statistics
+    }
+
+    pub fn clear_statistics(&mut self) {
+        self.statistics.clear();
+        self.security.key_statistics.clear();
+    }
+
+    fn decrypt(
+        &mut self,
+        payload: &[u8],
+        offset: usize,
+        output: &mut [u8],
+    ) -> Result<(SecurityHeader, usize), (Option<SecurityHeader>, DecodeError)> {
+        let header = match SecurityHeader::unpack(&payload[offset..]) {
+            Ok((header, _)) => header,
+            Err(e) => return Err((None, DecodeError::SecurityHeader(e))),
+        };
+        match self.security.decrypt(&header, payload, offset, output) {
+            Some(size) => Ok((header, size)),
+            None => Err((Some(header), DecodeError::NoValidKey)),
         }
     }
 
-    fn handle_application_service_command(&mut self, payload: &[u8]) {
+    fn handle_application_service_command(&mut self, payload: &[u8], decoded: &mut DecodedFrame) {
+        use application_service::commands::TransportKey;
         use application_service::Command;
-        let mut line: heapless::String<256> = heapless::String::new();
-        let _ = uwrite!(line, "APS Command ");
         match Command::unpack(payload) {
             Ok((cmd, _used)) => {
-                match cmd {
-                    Command::SymmetricKeyKeyEstablishment1(cmd) => {
-                        let _ = uwrite!(
-                            line,
-                            "SKKE1 Initator {} Responder {} ",
-                            u64::from(cmd.initiator),
-                            u64::from(cmd.responder)
-                        );
-                        for b in cmd.data.iter() {
-                            let _ = uwrite!(line, "{:02x}", *b);
-                        }
-                    }
-                    Command::SymmetricKeyKeyEstablishment2(cmd) => {
-                        let _ = uwrite!(
-                            line,
-                            "SKKE2 Initator {} Responder {} ",
-                            u64::from(cmd.initiator),
-                            u64::from(cmd.responder)
-                        );
-                        for b in cmd.data.iter() {
-                            let _ = uwrite!(line, "{:02x}", *b);
-                        }
-                    }
-                    Command::SymmetricKeyKeyEstablishment3(cmd) => {
-                        let _ = uwrite!(
-                            line,
-                            "SKKE3 Initator {} Responder {} ",
-                            u64::from(cmd.initiator),
-                            u64::from(cmd.responder)
-                        );
-                        for b in cmd.data.iter() {
-                            let _ = uwrite!(line, "{:02x}", *b);
-                        }
-                    }
-                    Command::SymmetricKeyKeyEstablishment4(cmd) => {
-                        let _ = uwrite!(
-                            line,
-                            "SKKE4 Initator {} Responder {} ",
-                            u64::from(cmd.initiator),
-                            u64::from(cmd.responder)
-                        );
-                        for b in cmd.data.iter() {
-                            let _ = uwrite!(line, "{:02x}", *b);
-                        }
+                let pan_identifier = match decoded.mac.destination {
+                    Some(mac::Address::Short(pan, _)) | Some(mac::Address::Extended(pan, _)) => {
+                        Some(pan.0)
                     }
-                    Command::TransportKey(cmd) => {
-                        use application_service::commands::TransportKey;
-                        let _ = uwrite!(line, "Transport Key ");
-                        match cmd {
-                            TransportKey::TrustCenterMasterKey(key) => {
-                                let _ = uwrite!(
-                                    line,
-                                    "Trust Center Master Key, DST {:016x} SRC {:016x} KEY ",
-                                    u64::from(key.destination),
-                                    u64::from(key.source)
-                                );
-                                Self::print_key(&mut line, &key.key);
-                            }
-                            TransportKey::StandardNetworkKey(key) => {
-                                let _ = uwrite!(
-                                    line,
-                                    "Standard Network Key, DST {:016x} SRC {:016x} SEQ {} KEY ",
-                                    u64::from(key.destination),
-                                    u64::from(key.source),
-                                    key.sequence,
-                                );
-                                Self::print_key(&mut line, &key.key);
-                                self.security.add_transport_key(&key);
-                            }
-                            TransportKey::ApplicationMasterKey(key) => {
-                                let _ = uwrite!(
-                                    line,
-                                    "Application Master Key, Partner {:016x} {} KEY ",
-                                    u64::from(key.partner),
-                                    if key.initiator { "Initiator" } else { "" },
-                                );
-                                Self::print_key(&mut line, &key.key);
-                            }
-                            TransportKey::ApplicationLinkKey(key) => {
-                                let _ = uwrite!(
-                                    line,
-                                    "Application Link Key, Partner {:016x} {} KEY ",
-                                    u64::from(key.partner),
-                                    if key.initiator { "Initiator" } else { "" },
-                                );
-                                Self::print_key(&mut line, &key.key);
-                            }
-                            TransportKey::UniqueTrustCenterLinkKey(key) => {
-                                let _ =
-                                    uwrite!(line,
-                                    "Unique Trust Center Link Key, DST {:016x} SRC {:016x} KEY ",
-                                    u64::from(key.destination), u64::from(key.source)
-                                );
-                                Self::print_key(&mut line, &key.key);
-                            }
-                            TransportKey::HighSecurityNetworkKey(key) => {
-                                let _ = uwrite!(line,
-                                    "High Security Network Key, DST {:016x} SRC {:016x} SEQ {:02x} KEY ",
-                                    u64::from(key.destination), u64::from(key.source), key.sequence
-                                );
-                                Self::print_key(&mut line, &key.key);
+                    None => None,
+                };
+                match cmd {
+                    Command::TransportKey(TransportKey::StandardNetworkKey(ref key)) => {
+                        self.security.add_transport_key(key);
+                        // The first network key of a PAN is in use directly
+                        if let Some(pan) = pan_identifier {
+                            if self.security.active_sequence(pan).is_none() {
+                                self.security.switch_network_key(pan, key.sequence);
                             }
                         }
                     }
-                    Command::UpdateDevice(cmd) => {
-                        let _ = uwrite!(
-                            line,
-                            "Update Device, {} {} {:?}",
-                            u64::from(cmd.address),
-                            u16::from(cmd.short_address),
-                            u8::from(cmd.status)
-                        );
-                    }
-                    Command::RemoveDevice(cmd) => {
-                        let _ = uwrite!(line, "Remove Device, {}", u64::from(cmd.address));
-                    }
-                    Command::RequestKey(cmd) => {
-                        let _ = uwrite!(line, "Request Key, {}", u8::from(cmd.key_type));
-                        if let Some(partner) = cmd.partner_address {
-                            let _ = uwrite!(line, " Partner {}", u64::from(partner));
-                        }
-                    }
-                    Command::SwitchKey(cmd) => {
-                        let _ = uwrite!(line, "Switch Key, Sequence {}", cmd.sequence);
-                    }
-                    Command::EntityAuthenticationInitiatorChallenge => {
-                        let _ = uwrite!(line, "EAC Initiator");
-                    }
-                    Command::EntityAuthenticationResponderChallenge => {
-                        let _ = uwrite!(line, "EAC Responder");
-                    }
-                    Command::EntityAuthenticationInitiatorMacAndData => {
-                        let _ = uwrite!(line, "EAMD Initiator");
-                    }
-                    Command::EntityAuthenticationResponderMacAndData => {
-                        let _ = uwrite!(line, "EAMD Responder");
-                    }
-                    Command::Tunnel(cmd) => {
-                        let _ = uwrite!(line, "Tunnel {}", u64::from(cmd.destination));
-                    }
-                    Command::VerifyKey(cmd) => {
-                        let _ = uwrite!(
-                            line,
-                            "Verify Key, Source {} Type {} ",
-                            u64::from(cmd.source),
-                            u8::from(cmd.key_type)
-                        );
-                        for b in cmd.value.iter() {
-                            let _ = uwrite!(line, "{:02x}", *b);
+                    Command::SwitchKey(ref switch) => {
+                        if let Some(pan) = pan_identifier {
+                            if !self.security.switch_network_key(pan, switch.sequence) {
+                                decoded.error =
+                                    Some(DecodeError::UnknownKeySequence(switch.sequence));
+                            }
                         }
                     }
-                    Command::ConfirmKey(cmd) => {
-                        let _ = uwrite!(
-                            line,
-                            "Confirm Key, Source {} Type {} Status {}",
-                            u64::from(cmd.destination),
-                            u8::from(cmd.key_type),
-                            u8::from(cmd.status)
-                        );
-                    }
+                    _ => (),
                 }
-                defmt::info!("{}", line.as_str());
+                decoded.payload = Payload::ApplicationServiceCommand(cmd);
             }
-            Err(ref e) => {
-                crate::print_error(e, "Failed to parse APS command");
+            Err(e) => {
+                decoded.error = Some(DecodeError::ApplicationServiceCommand(e));
             }
         }
     }
-    fn parse_application_service_frame(&mut self, payload: &[u8]) {
-        let mut line: heapless::String<256> = heapless::String::new();
-        match ApplicationServiceHeader::unpack(payload) {
-            Ok((header, used)) => {
-                let _ = uwrite!(line, "APS ");
-                let ack_format = if header.control.acknowledge_format {
-                    "AckCmd"
-                } else {
-                    "AckData"
-                };
-                let _ = uwrite!(line, "{} ", ack_format,);
-                if header.control.security {
-                    let _ = uwrite!(line, "Secure ");
-                }
-                if header.control.acknowledge_request {
-                    let _ = uwrite!(line, "AckReq ");
-                }
-                if header.control.extended_header {
-                    let _ = uwrite!(line, "ExtHdr ");
-                }
-                if let Some(addr) = header.destination {
-                    let _ = uwrite!(line, "Dst {:02x} ", addr);
-                }
-                if let Some(group) = header.group {
-                    let _ = uwrite!(line, "Group {:04x} ", group);
-                }
-                if let Some(cluster) = header.cluster {
-                    let _ = uwrite!(line, "Cluster {:04x} ", cluster);
-                }
-                if let Some(profile) = header.profile {
-                    let _ = uwrite!(line, "Profile {:04x} ", profile);
+
+    fn parse_application_service_frame(&mut self, payload: &[u8], decoded: &mut DecodedFrame) {
+        let (header, used) = match ApplicationServiceHeader::unpack(payload) {
+            Ok(result) => result,
+            Err(e) => {
+                decoded.error = Some(DecodeError::ApplicationServiceHeader(e));
+                return;
+            }
+        };
+        let mut processed_payload = [0u8; 256];
+        let length = if header.control.security {
+            match self.decrypt(payload, used, &mut processed_payload) {
+                Ok((security, length)) => {
+                    decoded.application_service_frame_counter =
+                        self.security.frame_counters.check(&security);
+                    decoded.application_service_security = Some(security);
+                    length
                 }
-                if let Some(addr) = header.source {
-                    let _ = uwrite!(line, "Src {:02x} ", addr);
+                Err((security, error)) => {
+                    decoded.application_service_security = security;
+                    decoded.application_service = Some(header);
+                    decoded.error = Some(error);
+                    return;
                 }
-                let _ = uwrite!(line, "Counter {:02x}", header.counter);
-                let mut processed_payload = [0u8; 256];
-                let length = if header.control.security {
-                    self.security
-                        .decrypt(&payload, used, &mut processed_payload)
-                } else {
-                    let length = payload.len() - used;
-                    processed_payload[..length].copy_from_slice(&payload[used..]);
-                    length
-                };
-                match header.control.frame_type {
-                    application_service::header::FrameType::Data => {
-                        let _ = uwrite!(line, "Payload: ");
-                        for b in payload[used..].iter() {
-                            let _ = uwrite!(line, "{:02x}", *b);
-                        }
-                    }
-                    application_service::header::FrameType::Command => {
-                        self.handle_application_service_command(&processed_payload[..length]);
+            }
+        } else {
+            let length = payload.len() - used;
+            processed_payload[..length].copy_from_slice(&payload[used..]);
+            length
+        };
+        let processed_payload = &processed_payload[..length];
+        match header.control.frame_type {
+            application_service::header::FrameType::Command => {
+                self.handle_application_service_command(processed_payload, decoded);
+            }
+            application_service::header::FrameType::Data
+            | application_service::header::FrameType::Acknowledgement
+            | application_service::header::FrameType::InterPan => {
+                let buffer = match PayloadBuffer::from_slice(processed_payload) {
+                    Ok(buffer) => buffer,
+                    Err(_) => {
+                        decoded.error = Some(DecodeError::PayloadTooLarge);
+                        PayloadBuffer::new()
                     }
+                };
+                decoded.payload = match header.control.frame_type {
                     application_service::header::FrameType::Acknowledgement => {
-                        if !payload[used..].is_empty() {
-                            let _ = uwrite!(line, "APS Acknowledgement Payload: ");
-                            for b in payload[used..].iter() {
-                                let _ = uwrite!(line, "{:02x}", *b);
-                            }
-                        }
+                        Payload::ApplicationServiceAcknowledgement(buffer)
                     }
                     application_service::header::FrameType::InterPan => {
-                        let _ = uwrite!(line, "APS Inter-PAN Payload: ");
-                        for b in payload[used..].iter() {
-                            let _ = uwrite!(line, "{:02x}", *b);
+                        Payload::ApplicationServiceInterPan(buffer)
+                    }
+                    _ => match (header.profile, header.cluster) {
+                        (Some(profile), Some(cluster)) if profile != PROFILE_DEVICE => {
+                            match zcl::Frame::unpack(profile, cluster, processed_payload) {
+                                Ok(frame) => Payload::ClusterLibrary(frame),
+                                Err(e) => {
+                                    decoded.error = Some(DecodeError::ClusterLibrary(e));
+                                    Payload::ApplicationServiceData(buffer)
+                                }
+                            }
                         }
-                    }
-                }
-                defmt::info!("{}", line.as_str());
-            }
-            Err(ref e) => {
-                crate::print_error(e, "Failed to parse APS header");
+                        (Some(PROFILE_DEVICE), Some(cluster)) => {
+                            match zdo::Frame::unpack(cluster, processed_payload) {
+                                Ok(frame) => Payload::DeviceObject(frame),
+                                Err(e) => {
+                                    decoded.error = Some(DecodeError::DeviceObject(e));
+                                    Payload::ApplicationServiceData(buffer)
+                                }
+                            }
+                        }
+                        _ => Payload::ApplicationServiceData(buffer),
+                    },
+                };
             }
         }
+        decoded.application_service = Some(header);
     }
 
-    fn parse_network_command(&self, payload: &[u8]) {
+    fn parse_network_command(&self, payload: &[u8], decoded: &mut DecodedFrame) {
         use network::commands::Command;
-        let mut line: heapless::String<256> = heapless::String::new();
-        let _ = uwrite!(line, "NWK CMD ",);
         match Command::unpack(payload) {
-            Ok((cmd, _used)) => match cmd {
-                Command::RouteRequest(rr) => {
-                    let many = match rr.options.many_to_one {
-                        network::commands::ManyToOne::No => "One to one",
-                        network::commands::ManyToOne::RouteRequestTableSupport => {
-                            "Many to one, table"
-                        }
-                        network::commands::ManyToOne::NoRouteRequestTableSupport => "Many to one",
-                    };
-                    let _ = uwrite!(
-                        line,
-                        "Route Request {:02x} Cost {} {}",
-                        rr.identifier,
-                        rr.path_cost,
-                        many
-                    );
-                    match rr.destination_address {
-                        network::commands::AddressType::Singlecast(a) => {
-                            let _ = uwrite!(line, " Destination {:04x}", u16::from(a));
-                        }
-                        network::commands::AddressType::Multicast(a) => {
-                            let _ = uwrite!(line, " Group {:04x}", u16::from(a));
-                        }
-                    }
-                    if let Some(address) = rr.destination_ieee_address {
-                        let _ = uwrite!(line, " Destination {:08x}", u64::from(address));
-                    }
-                }
-                Command::RouteReply(rr) => {
-                    let _ = uwrite!(
-                        line,
-                        "Route Reply Identifier {:02x} Originator {:04x} Responder {:04x} Path cost {}",
-                        rr.identifier,
-                        u16::from(rr.orginator_address),
-                        u16::from(rr.responder_address),
-                        rr.path_cost
-                    );
-                    if let Some(address) = rr.orginator_ieee_address {
-                        let _ = uwrite!(line, " Originator {:04x}", u64::from(address));
-                    }
-                    if let Some(address) = rr.responder_ieee_address {
-                        let _ = uwrite!(line, " Responder {:08x}", u64::from(address));
-                    }
-                }
-                Command::NetworkStatus(ns) => {
-                    let _ = uwrite!(
-                        line,
-                        "Network Status Destination {:04x} Status {:02x}",
-                        u16::from(ns.destination),
-                        u8::from(ns.status)
-                    );
-                }
-                Command::Leave(leave) => {
-                    let _ = uwrite!(
-                        line,
-                        "Leave {}{}{}",
-                        if leave.rejoin { "Rejoin " } else { "" },
-                        if leave.request { "Request " } else { "" },
-                        if leave.remove_children {
-                            "Remove children "
-                        } else {
-                            ""
-                        },
-                    );
-                }
-                Command::RouteRecord(rr) => {
-                    let _ = uwrite!(line, "Route Record ");
-                    for address in rr.entries() {
-                        let _ = uwrite!(line, "{:04x} ", u16::from(*address));
-                    }
-                }
-                Command::RejoinRequest(_rr) => {
-                    let _ = uwrite!(line, "Rejoin Request");
-                }
-                Command::RejoinResponse(_rr) => {
-                    let _ = uwrite!(line, "Rejoin Response");
-                }
-                Command::LinkStatus(ls) => {
-                    let _ = uwrite!(line, "Link Status ");
-                    if ls.first_frame && !ls.last_frame {
-                        let _ = uwrite!(line, "First ");
-                    } else if !ls.first_frame && ls.last_frame {
-                        let _ = uwrite!(line, "Last ");
-                    }
-                    for entry in ls.entries() {
-                        let _ = uwrite!(
-                            line,
-                            "{:04x} Incoming {} Outgoing {} ",
-                            u16::from(entry.address),
-                            entry.incoming_cost,
-                            entry.outgoing_cost
-                        );
-                    }
-                }
-                Command::NetworkReport(nr) => {
-                    let _ = uwrite!(
-                        line,
-                        "Network Conflict {:08x} {:04x}",
-                        u64::from(nr.extended_pan_identifier),
-                        u16::from(nr.pan_identifier)
-                    );
-                }
-                Command::NetworkUpdate(nu) => {
-                    let _ = uwrite!(
-                        line,
-                        "Network Update {:08x} {:04x}",
-                        u64::from(nu.extended_pan_identifier),
-                        u16::from(nu.pan_identifier)
-                    );
-                }
-                Command::EndDeviceTimeoutRequest(edtr) => {
-                    let _ = uwrite!(
-                        line,
-                        "End-device Timeout Request, Timeout {}s",
-                        edtr.timeout.in_seconds()
-                    );
-                }
-                Command::EndDeviceTimeoutResponse(edtr) => {
-                    let _ = uwrite!(
-                        line,
-                        "End-device Timeout Response, {} {} {}",
-                        u8::from(edtr.status),
-                        if edtr.mac_keep_alive {
-                            "MAC keep alive"
-                        } else {
-                            ""
-                        },
-                        if edtr.end_device_keep_alive {
-                            "End device keep alive"
-                        } else {
-                            ""
-                        },
-                    );
-                }
-            },
-            Err(ref e) => {
-                crate::print_error(e, "Failed to decode network command");
+            Ok((cmd, _used)) => {
+                decoded.payload = Payload::NetworkCommand(cmd);
+            }
+            Err(e) => {
+                decoded.error = Some(DecodeError::NetworkCommand(e));
             }
         }
-        defmt::info!("{}", line.as_str());
     }
 
-    fn parse_network_frame(&mut self, payload: &[u8]) {
-        let mut line: heapless::String<256> = heapless::String::new();
-
-        match NetworkHeader::unpack(payload) {
-            Ok((network_frame, used)) => {
-                let frame_type = match network_frame.control.frame_type {
-                    network::header::FrameType::Command => "Command",
-                    network::header::FrameType::Data => "Data",
-                    network::header::FrameType::InterPan => "Inter-PAN",
-                };
-                let discovery = match network_frame.control.discover_route {
-                    network::header::DiscoverRoute::EnableDiscovery => " DSC",
-                    network::header::DiscoverRoute::SuppressDiscovery => "",
-                };
-                let security = if network_frame.control.security {
-                    " SEC"
-                } else {
-                    ""
-                };
-                let _ = uwrite!(
-                    line,
-                    "NWK {} VER {}{}{} DST {:04x} SRC {:04x} RAD {} SEQ {}",
-                    frame_type,
-                    network_frame.control.protocol_version,
-                    discovery,
-                    security,
-                    u16::from(network_frame.destination_address),
-                    u16::from(network_frame.source_address),
-                    network_frame.radius,
-                    network_frame.sequence_number,
-                );
-                if let Some(dst) = network_frame.destination_ieee_address {
-                    let _ = uwrite!(line, " DST {:08x}", u64::from(dst));
-                }
-                if let Some(src) = network_frame.source_ieee_address {
-                    let _ = uwrite!(line, " SRC {:08x}", u64::from(src));
-                }
-                if let Some(mc) = network_frame.multicast_control {
-                    let mode = match mc.mode {
-                        network::header::MulticastMode::NonmemberMode => "non-member",
-                        network::header::MulticastMode::MemberMode => "member",
-                    };
-                    let _ = uwrite!(line, " MC {} RAD {} MAX {}", mode, mc.radius, mc.max_radius);
-                }
-                if let Some(srf) = network_frame.source_route_frame {
-                    let _ = uwrite!(line, " SRF I {}", srf.index);
-                    for address in srf.entries() {
-                        let _ = uwrite!(line, " {:04x}", u16::from(*address));
-                    }
-                }
-                defmt::info!("{}", line.as_str());
-
-                let mut processed_payload = [0u8; 256];
-                let length = if network_frame.control.security {
-                    self.security
-                        .decrypt(&payload, used, &mut processed_payload)
-                } else {
-                    let length = payload.len() - used;
-                    processed_payload[..length].copy_from_slice(&payload[used..]);
+    fn parse_network_frame(&mut self, payload: &[u8], decoded: &mut DecodedFrame) {
+        let (network_frame, used) = match NetworkHeader::unpack(payload) {
+            Ok(result) => result,
+            Err(e) => {
+                decoded.error = Some(DecodeError::NetworkHeader(e));
+                return;
+            }
+        };
+        let is_command = matches!(
+            network_frame.control.frame_type,
+            network::header::FrameType::Command
+        );
+        let mut processed_payload = [0u8; 256];
+        let length = if network_frame.control.security {
+            match self.decrypt(payload, used, &mut processed_payload) {
+                Ok((security, length)) => {
+                    decoded.network_frame_counter = self.security.frame_counters.check(&security);
+                    decoded.network_security = Some(security);
                     length
-                };
-                if length > 0 {
-                    match network_frame.control.frame_type {
-                        network::header::FrameType::Data | network::header::FrameType::InterPan => {
-                            self.parse_application_service_frame(&processed_payload[..length])
-                        }
-                        network::header::FrameType::Command => {
-                            self.parse_network_command(&processed_payload[..length]);
-                        }
-                    }
+                }
+                Err((security, error)) => {
+                    decoded.network_security = security;
+                    decoded.network = Some(network_frame);
+                    decoded.error = Some(error);
+                    return;
                 }
             }
-            Err(ref e) => {
-                crate::print_error(e, "Failed to decode network frame");
+        } else {
+            let length = payload.len() - used;
+            processed_payload[..length].copy_from_slice(&payload[used..]);
+            length
+        };
+        decoded.network = Some(network_frame);
+        if length > 0 {
+            if is_command {
+                self.parse_network_command(&processed_payload[..length], decoded);
+            } else {
+                self.parse_application_service_frame(&processed_payload[..length], decoded);
             }
         }
     }
 
-    pub fn parse_802154_mac(&mut self, frame: &mac::Frame) {
-        let mut line: heapless::String<256> = heapless::String::new();
+    fn parse_beacon(payload: &[u8], decoded: &mut DecodedFrame) {
+        // Beacons of other protocols, e.g. Thread, are left as is
+        if payload.first() != Some(&beacon::PROTOCOL_IDENTIFIER) {
+            return;
+        }
+        match BeaconPayload::unpack(payload) {
+            Ok(beacon) => decoded.payload = Payload::Beacon(beacon),
+            Err(e) => decoded.error = Some(DecodeError::Beacon(e)),
+        }
+    }
 
-        let frame_type = match frame.header.frame_type {
-            mac::FrameType::Acknowledgement => "Acknowledgement",
-            mac::FrameType::Beacon => "Beacon",
-            mac::FrameType::Data => "Data",
-            mac::FrameType::MacCommand => "Command",
-            mac::FrameType::Multipurpose => "Multipurpose",
-            mac::FrameType::FragOrFragAck => "Fragment",
-            mac::FrameType::Extended => "Extended",
-        };
-        let frame_version = match frame.header.version {
-            mac::FrameVersion::Ieee802154_2003 => "2003",
-            mac::FrameVersion::Ieee802154_2006 => "2003",
-            mac::FrameVersion::Ieee802154 => "20xx",
+    fn parse_mac_data(payload: &[u8], decoded: &mut DecodedFrame) {
+        if payload.is_empty() {
+            return;
+        }
+        match PayloadBuffer::from_slice(payload) {
+            Ok(buffer) => decoded.payload = Payload::MacData(buffer),
+            Err(_) => decoded.error = Some(DecodeError::PayloadTooLarge),
+        }
+    }
+
+    fn parse_mac_command(payload: &[u8], decoded: &mut DecodedFrame) {
+        let Some((&identifier, body)) = payload.split_first() else {
+            return;
         };
-        let _ = uwrite!(
-            &mut line,
-            "802.15.4 VER: {} TYPE: {}",
-            frame_version,
-            frame_type
-        );
-        if frame.header.frame_pending {
-            let _ = uwrite!(&mut line, " PEND");
+        match PayloadBuffer::from_slice(body) {
+            Ok(body) => {
+                decoded.payload = Payload::MacCommand {
+                    identifier,
+                    name: mac_command_name(identifier),
+                    body,
+                }
+            }
+            Err(_) => decoded.error = Some(DecodeError::PayloadTooLarge),
         }
-        if frame.header.ack_request {
-            let _ = uwrite!(&mut line, " ACK");
+    }
+
+    /// Zigbee IEs carry the beacon payload of enhanced beacons
+    fn parse_information_elements(decoded: &mut DecodedFrame) {
+        let mut beacon = None;
+        for element in decoded.information_elements.iter() {
+            for zigbee in element.zigbee_elements().into_iter().flatten() {
+                match zigbee {
+                    Ok(ZigbeeElement::BeaconPayload(payload)) => beacon = Some(payload),
+                    Ok(_) => (),
+                    Err(e) => decoded.error = Some(DecodeError::InformationElement(e)),
+                }
+            }
         }
-        if frame.header.pan_id_compress {
-            let _ = uwrite!(&mut line, " CMPR");
+        if let Some(beacon) = beacon {
+            decoded.payload = Payload::Beacon(beacon);
         }
-        let _ = uwrite!(&mut line, " SEQ: {}", frame.header.seq);
-        match frame.header.destination {
-            Some(mac::Address::Short(i, a)) => {
-                let _ = uwrite!(&mut line, " DST: {:04x}:{:04x}", i.0, a.0);
+    }
+
+    fn decode_mac(
+        &mut self,
+        frame: &mac::Frame,
+        metadata: &RxMetadata,
+        mac_security: Option<(AuxiliarySecurityHeader, bool)>,
+        information_elements: InformationElements,
+    ) -> DecodedFrame {
+        let mut decoded = DecodedFrame::new(frame, metadata);
+        decoded.information_elements = information_elements;
+        let encrypted = match mac_security {
+            Some((header, decrypted)) => {
+                decoded.mac_security = Some(header);
+                decoded.mac_unverified =
+                    decrypted && header.is_encrypted() && !header.is_authenticated();
+                !decrypted
             }
-            Some(mac::Address::Extended(i, a)) => {
-                let _ = uwrite!(&mut line, " DST: {:04x}:{:016x}", i.0, a.0);
+            None => frame.header.has_security(),
+        };
+        if metadata.fcs.is_bad() && self.bad_fcs_policy != BadFcsPolicy::Decode {
+            decoded.error = Some(DecodeError::BadFcs);
+        } else if encrypted {
+            decoded.error = Some(DecodeError::NoValidKey);
+        } else {
+            match frame.content {
+                mac::FrameContent::Data => self.parse_network_frame(frame.payload, &mut decoded),
+                mac::FrameContent::Beacon(_) => Self::parse_beacon(frame.payload, &mut decoded),
+                // Commands unknown to the ieee802154 crate are parsed as frames without content
+                mac::FrameContent::Multipurpose
+                    if matches!(frame.header.frame_type, mac::FrameType::MacCommand) =>
+                {
+                    Self::parse_mac_command(frame.payload, &mut decoded)
+                }
+                mac::FrameContent::Multipurpose
+                | mac::FrameContent::FragOrFragAck
+                | mac::FrameContent::Extended => Self::parse_mac_data(frame.payload, &mut decoded),
+                mac::FrameContent::Acknowledgement | mac::FrameContent::Command(_) => (),
             }
-            None => (),
+            Self::parse_information_elements(&mut decoded);
         }
-        match frame.header.source {
-            Some(mac::Address::Short(i, a)) => {
-                let _ = uwrite!(&mut line, " SRC: {:04x}:{:04x}", i.0, a.0);
-            }
-            Some(mac::Address::Extended(i, a)) => {
-                let _ = uwrite!(&mut line, " SRC: {:04x}:{:016x}", i.0, a.0);
+        self.statistics.update(&decoded);
+        decoded
+    }
+
+    /// Decode a MAC frame and the Zigbee layers in it, `metadata` is kept with the result
+    ///
+    /// Frames with a bad FCS are flagged unless the policy is to decode them, the frame is already
+    /// parsed so dropping is left to `parse_frame`. Frames with MAC security can not be decrypted
+    /// once parsed and information elements are not separated from the payload, use
+    /// `parse_frame` for these.
+    pub fn parse_802154_mac(&mut self, frame: &mac::Frame, metadata: &RxMetadata) -> DecodedFrame {
+        self.decode_mac(frame, metadata, None, InformationElements::new())
+    }
+
+    /// Remove the MAC security from a frame, the frame is written to `plain` as if sent without
+    /// security. Returns the auxiliary security header, whether the frame was decrypted and the
+    /// size of the frame, `None` for malformed frames.
+    fn remove_security(
+        &mut self,
+        data: &[u8],
+        devices: Option<&DeviceTable>,
+        rewritten: bool,
+        plain: &mut [u8],
+    ) -> Option<(AuxiliarySecurityHeader, bool, usize)> {
+        if data.len() > plain.len() {
+            return None;
+        }
+        let secured = mac_security::secured_frame(data).ok()?;
+        let header = secured.security;
+        let mut length = secured.header_length;
+        plain[..length].copy_from_slice(&data[..length]);
+        mac_header::clear_security(plain);
+        // Header IEs and the command identifier are not encrypted
+        let open_end = secured.payload_offset
+            + secured.header_elements
+            + usize::from(secured.command && secured.payload_offset < data.len());
+        let private_start = if header.is_encrypted() {
+            open_end
+        } else {
+            data.len().checked_sub(header.mic_length())?
+        };
+        if private_start < open_end || open_end > data.len() {
+            return None;
+        }
+        let unencrypted = &data[secured.payload_offset..private_start];
+        plain[length..length + unencrypted.len()].copy_from_slice(unencrypted);
+        length += unencrypted.len();
+        let source = match secured.source {
+            Some(SourceAddress::Extended(address)) => Some(ExtendedAddress::from(address)),
+            Some(SourceAddress::Short(address)) => devices
+                .and_then(|devices| devices.by_short_address(address))
+                .and_then(|device| device.extended_address)
+                .map(ExtendedAddress::from),
+            None => None,
+        };
+        let decrypted = match source {
+            // Security level 0 provides no protection
+            _ if header.level == 0 => Some(0),
+            // The header of rewritten frames differs from the authenticated one
+            Some(source) if !rewritten => self.security.decrypt_mac(
+                &header,
+                source,
+                &data[..private_start],
+                &data[private_start..],
+                &mut plain[length..],
+            ),
+            _ => None,
+        };
+        match decrypted {
+            Some(size) => length += size,
+            None => {
+                // Keep the encrypted payload
+                let size = (data.len() - private_start).saturating_sub(header.mic_length());
+                plain[length..length + size]
+                    .copy_from_slice(&data[private_start..private_start + size]);
+                length += size;
             }
-            None => (),
         }
-        match frame.content {
-            mac::FrameContent::Acknowledgement => {
-                // Nothing here
+        Some((header, decrypted.is_some(), length))
+    }
+
+    /// Rewrite the frame into what the ieee802154 crate parses, IEEE 802.15.4-2015 multipurpose
+    /// frames, MAC security and information elements are handled here
+    fn parse_frame_bytes(
+        &mut self,
+        data: &[u8],
+        metadata: &RxMetadata,
+        devices: Option<&DeviceTable>,
+    ) -> Option<DecodedFrame> {
+        if metadata.fcs.is_bad() && self.bad_fcs_policy == BadFcsPolicy::Drop {
+            self.statistics.update_dropped(metadata);
+            return None;
+        }
+        let multipurpose = mac_header::is_multipurpose(data);
+        let mut regular = [0u8; MAX_FRAME_SIZE];
+        let data = if multipurpose {
+            match mac_header::normalize_multipurpose(data, &mut regular) {
+                Ok(size) => &regular[..size],
+                Err(_) => {
+                    self.statistics.update_malformed(metadata);
+                    return None;
+                }
             }
-            mac::FrameContent::Beacon(beacon) => {
-                let _ = uwrite!(&mut line, " Beacon ");
-                match beacon.superframe_spec.beacon_order {
-                    BeaconOrder::OnDemand => {
-                        let _ = uwrite!(&mut line, "on-demand ");
-                    }
-                    BeaconOrder::BeaconOrder(value) => {
-                        let _ = uwrite!(&mut line, "order {}", value);
-                    }
+        } else {
+            data
+        };
+        let mut plain = [0u8; MAX_FRAME_SIZE];
+        let mut mac_security = None;
+        let data = if mac_header::is_secured(data) {
+            match self.remove_security(data, devices, multipurpose, &mut plain) {
+                Some((header, decrypted, size)) => {
+                    mac_security = Some((header, decrypted));
+                    &plain[..size]
                 }
-                let coordinator = if beacon.superframe_spec.pan_coordinator {
-                    "Coordinator"
-                } else {
-                    "Device"
-                };
-                let association_permit = if beacon.superframe_spec.association_permit {
-                    "Permit association"
-                } else {
-                    "Deny association"
-                };
-                let _ = uwrite!(&mut line, "{} {}", coordinator, association_permit);
-                if beacon.superframe_spec.battery_life_extension {
-                    let _ = uwrite!(&mut line, "Battery life extension");
+                None => {
+                    self.statistics.update_malformed(metadata);
+                    return None;
                 }
-                if beacon.guaranteed_time_slot_info.permit {
-                    let _ = uwrite!(
-                        &mut line,
-                        "GTS slots {}",
-                        beacon.guaranteed_time_slot_info.slots().len()
-                    );
+            }
+        } else {
+            data
+        };
+        let Ok(layout) = mac_header::header_layout(data) else {
+            self.statistics.update_malformed(metadata);
+            return None;
+        };
+        let mut information_elements = InformationElements::new();
+        let mut stripped = [0u8; MAX_FRAME_SIZE];
+        let enhanced_beacon = layout.frame_type() == mac_header::FRAME_TYPE_BEACON
+            && layout.version() == mac_header::FRAME_VERSION_2015;
+        let data = if layout.information_elements() || enhanced_beacon {
+            let size = if layout.information_elements() {
+                information_element::strip(
+                    data,
+                    layout.length,
+                    &mut stripped,
+                    &mut information_elements,
+                )
+            } else if data.len() <= MAX_FRAME_SIZE {
+                stripped[..data.len()].copy_from_slice(data);
+                Ok(data.len())
+            } else {
+                Err(psila_data::Error::NotEnoughSpace)
+            };
+            let Ok(size) = size else {
+                self.statistics.update_malformed(metadata);
+                return None;
+            };
+            if enhanced_beacon {
+                // Enhanced beacons have no superframe specification, parse them as a frame
+                // without content
+                stripped[0] = (stripped[0] & !0x07) | mac_header::FRAME_TYPE_MULTIPURPOSE;
+            }
+            &stripped[..size]
+        } else {
+            data
+        };
+        match data.read_with::<mac::Frame>(&mut 0, FooterMode::None) {
+            Ok(mut frame) => {
+                if enhanced_beacon {
+                    frame.header.frame_type = mac::FrameType::Beacon;
                 }
+                Some(self.decode_mac(&frame, metadata, mac_security, information_elements))
             }
-            mac::FrameContent::Data => (),
-            mac::FrameContent::Command(command) => {
-                let _ = uwrite!(&mut line, " Command ");
-                match command {
-                    mac::command::Command::AssociationRequest(cmd) => {
-                        let _ = uwrite!(&mut line, "Association request ");
-                        if cmd.full_function_device {
-                            let _ = uwrite!(&mut line, "FFD ");
-                        } else {
-                            let _ = uwrite!(&mut line, "RFD ");
-                        }
-                        if cmd.mains_power {
-                            let _ = uwrite!(&mut line, "Mains power ");
-                        }
-                        if cmd.idle_receive {
-                            let _ = uwrite!(&mut line, "Idle Rx ");
-                        }
-                        if cmd.frame_protection {
-                            let _ = uwrite!(&mut line, "Secure ");
-                        }
-                        if cmd.allocate_address {
-                            let _ = uwrite!(&mut line, "Allocate address ");
-                        }
-                    }
-                    mac::command::Command::AssociationResponse(address, _status) => {
-                        let _ = uwrite!(&mut line, " Association response {:04x}", address.0);
-                    }
-                    mac::command::Command::DisassociationNotification(reason) => {
-                        let reason = match reason {
-                            mac::command::DisassociationReason::CoordinatorLeave => {
-                                "requested to leave"
-                            }
-                            mac::command::DisassociationReason::DeviceLeave => "leave",
-                        };
-                        let _ = uwrite!(&mut line, " Disassociation {}", reason);
-                    }
-                    mac::command::Command::BeaconRequest => {
-                        let _ = uwrite!(&mut line, " Beacon request");
-                    }
-                    mac::command::Command::DataRequest => {
-                        let _ = uwrite!(&mut line, " Data request");
+            Err(_) => {
+                // The ieee802154 crate does not know the IEEE 802.15.4-2015 commands, the
+                // identifier of encrypted commands is not known until decrypted
+                let encrypted = mac_security.is_some_and(|(_, decrypted)| !decrypted);
+                match mac_header::command_identifier(data) {
+                    Some(identifier)
+                        if encrypted || !DECODED_MAC_COMMANDS.contains(&identifier) =>
+                    {
+                        self.parse_unknown_command(
+                            data,
+                            metadata,
+                            mac_security,
+                            information_elements,
+                        )
                     }
                     _ => {
-                        let _ = uwrite!(&mut line, " Other command");
+                        self.statistics.update_malformed(metadata);
+                        None
                     }
                 }
             }
-            mac::FrameContent::Multipurpose => (),
-            mac::FrameContent::FragOrFragAck => (),
-            mac::FrameContent::Extended => (),
         }
-        defmt::info!("{}", line.as_str());
-        match frame.content {
-            mac::FrameContent::Data => {
-                self.parse_network_frame(frame.payload);
+    }
+
+    /// Decode a MAC command frame the ieee802154 crate does not know. The header is parsed as a
+    /// multipurpose frame, the frame type is restored afterwards.
+    fn parse_unknown_command(
+        &mut self,
+        data: &[u8],
+        metadata: &RxMetadata,
+        mac_security: Option<(AuxiliarySecurityHeader, bool)>,
+        information_elements: InformationElements,
+    ) -> Option<DecodedFrame> {
+        let mut buffer = [0u8; MAX_FRAME_SIZE];
+        let Some(command) = buffer.get_mut(..data.len()) else {
+            self.statistics.update_malformed(metadata);
+            return None;
+        };
+        command.copy_from_slice(data);
+        command[0] = (command[0] & !0x07) | mac_header::FRAME_TYPE_MULTIPURPOSE;
+        match command.read_with::<mac::Frame>(&mut 0, FooterMode::None) {
+            Ok(mut frame) => {
+                frame.header.frame_type = mac::FrameType::MacCommand;
+                Some(self.decode_mac(&frame, metadata, mac_security, information_elements))
+            }
+            Err(_) => {
+                self.statistics.update_malformed(metadata);
+                None
             }
-            _ => (),
         }
     }
+
+    /// Decode a MAC frame without FCS
+    ///
+    /// Returns `None` for frames dropped due to a bad FCS and for frames that are not valid MAC
+    /// frames, both are counted in the statistics. Frames with MAC security are decrypted with the
+    /// MAC keys when the sender uses its extended address. Information elements are removed from
+    /// the frame and kept in the result.
+    pub fn parse_frame(&mut self, data: &[u8], metadata: &RxMetadata) -> Option<DecodedFrame> {
+        self.parse_frame_bytes(data, metadata, None)
+    }
+
+    /// Decode a MAC frame without FCS, as `parse_frame`. The extended address of senders using
+    /// a short address is looked up in `devices` for decrypting frames with MAC security.
+    pub fn parse_frame_with_devices(
+        &mut self,
+        data: &[u8],
+        metadata: &RxMetadata,
+        devices: &DeviceTable,
+    ) -> Option<DecodedFrame> {
+        self.parse_frame_bytes(data, metadata, Some(devices))
+    }
+
+    /// Decode a MAC frame with trailing FCS, the FCS is verified first
+    pub fn parse_frame_with_fcs(
+        &mut self,
+        data: &[u8],
+        metadata: &RxMetadata,
+    ) -> Option<DecodedFrame> {
+        let mut metadata = *metadata;
+        let frame = match fcs::split(data) {
+            Some((frame, status)) => {
+                metadata.fcs = status;
+                frame
+            }
+            None => {
+                self.statistics.update_malformed(&metadata);
+                return None;
+            }
+        };
+        self.parse_frame(frame, &metadata)
+    }
 }
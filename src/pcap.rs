@@ -0,0 +1,609 @@
+//! Reader and writer for the libpcap capture file format.
+//!
+//! Frames are stored without FCS, either as plain IEEE 802.15.4 frames or with the IEEE 802.15.4 TAP
+//! header which also carries the channel and the received signal strength. The reader also accepts
+//! pcapng files and frames captured with FCS.
+
+use crate::decoded::RxMetadata;
+use crate::fcs;
+
+const MAGIC: u32 = 0xa1b2_c3d4;
+const MAGIC_NANOSECONDS: u32 = 0xa1b2_3c4d;
+const VERSION_MAJOR: u16 = 2;
+const VERSION_MINOR: u16 = 4;
+const SNAPLEN: u32 = 256;
+
+const PCAPNG_SECTION_HEADER: u32 = 0x0a0d_0d0a;
+const PCAPNG_BYTE_ORDER_MAGIC: u32 = 0x1a2b_3c4d;
+const PCAPNG_INTERFACE_DESCRIPTION: u32 = 0x0000_0001;
+const PCAPNG_SIMPLE_PACKET: u32 = 0x0000_0003;
+const PCAPNG_ENHANCED_PACKET: u32 = 0x0000_0006;
+const PCAPNG_OPTION_END: u16 = 0;
+const PCAPNG_OPTION_TIMESTAMP_RESOLUTION: u16 = 9;
+/// Finest decimal timestamp resolution, 10^-25 seconds, one microsecond is 10^19 units
+const PCAPNG_MAX_DECIMAL_RESOLUTION: u8 = 25;
+
+const LINKTYPE_IEEE802_15_4_WITHFCS: u32 = 195;
+const LINKTYPE_IEEE802_15_4_NOFCS: u32 = 230;
+const LINKTYPE_IEEE802_15_4_TAP: u32 = 283;
+
+const TAP_FCS_TYPE: u16 = 0;
+const TAP_RSS: u16 = 1;
+const TAP_CHANNEL_ASSIGNMENT: u16 = 3;
+const TAP_LQI: u16 = 10;
+const TAP_HEADER_SIZE: usize = 4;
+const TAP_TLVS_SIZE: usize = 8 + 8 + 8 + 8;
+
+/// Destination for encoded bytes
+pub trait ByteSink {
+    type Error;
+
+    fn write_all(&mut self, data: &[u8]) -> Result<(), Self::Error>;
+}
+
+impl<const N: usize> ByteSink for heapless::Vec<u8, N> {
+    type Error = ();
+
+    fn write_all(&mut self, data: &[u8]) -> Result<(), Self::Error> {
+        self.extend_from_slice(data)
+    }
+}
+
+/// Writes bytes as hexadecimal text to a `core::fmt::Write`, e.g. a serial console
+pub struct FmtSink<W: core::fmt::Write>(pub W);
+
+impl<W: core::fmt::Write> ByteSink for FmtSink<W> {
+    type Error = core::fmt::Error;
+
+    fn write_all(&mut self, data: &[u8]) -> Result<(), Self::Error> {
+        for b in data.iter() {
+            write!(self.0, "{:02x}", b)?;
+        }
+        Ok(())
+    }
+}
+
+/// Writes bytes unmodified to the serial port used by `esp-println`, for streaming a capture to the
+/// host. Nothing else should be printed while streaming as it would end up in the capture.
+#[cfg(feature = "esp32c6")]
+pub struct SerialSink;
+
+#[cfg(feature = "esp32c6")]
+impl ByteSink for SerialSink {
+    type Error = core::convert::Infallible;
+
+    fn write_all(&mut self, data: &[u8]) -> Result<(), Self::Error> {
+        esp_println::Printer::write_bytes(data);
+        Ok(())
+    }
+}
+
+/// Writes bytes to a `std::io::Write`, e.g. a file
+#[cfg(feature = "std")]
+pub struct IoSink<W: std::io::Write>(pub W);
+
+#[cfg(feature = "std")]
+impl<W: std::io::Write> ByteSink for IoSink<W> {
+    type Error = std::io::Error;
+
+    fn write_all(&mut self, data: &[u8]) -> Result<(), Self::Error> {
+        self.0.write_all(data)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LinkType {
+    /// LINKTYPE_IEEE802_15_4_NOFCS, the frame only
+    Ieee802154NoFcs,
+    /// LINKTYPE_IEEE802_15_4_TAP, the frame with a TAP header holding channel and RSSI
+    Ieee802154Tap,
+}
+
+impl From<LinkType> for u32 {
+    fn from(value: LinkType) -> Self {
+        match value {
+            LinkType::Ieee802154NoFcs => LINKTYPE_IEEE802_15_4_NOFCS,
+            LinkType::Ieee802154Tap => LINKTYPE_IEEE802_15_4_TAP,
+        }
+    }
+}
+
+pub struct PcapWriter<S: ByteSink> {
+    sink: S,
+    link_type: LinkType,
+}
+
+impl<S: ByteSink> PcapWriter<S> {
+    /// Create a writer and write the pcap file header to the sink
+    pub fn new(mut sink: S, link_type: LinkType) -> Result<Self, S::Error> {
+        let mut header = [0u8; 24];
+        header[0..4].copy_from_slice(&MAGIC.to_le_bytes());
+        header[4..6].copy_from_slice(&VERSION_MAJOR.to_le_bytes());
+        header[6..8].copy_from_slice(&VERSION_MINOR.to_le_bytes());
+        // thiszone and sigfigs are zero
+        header[16..20].copy_from_slice(&SNAPLEN.to_le_bytes());
+        header[20..24].copy_from_slice(&u32::from(link_type).to_le_bytes());
+        sink.write_all(&header)?;
+        Ok(PcapWriter { sink, link_type })
+    }
+
+    pub fn link_type(&self) -> LinkType {
+        self.link_type
+    }
+
+    /// Write a frame record, `frame` is the MAC frame without FCS. The TAP header holds the
+    /// signal strength, channel and link quality found in `metadata`.
+    pub fn write_frame(&mut self, metadata: &RxMetadata, frame: &[u8]) -> Result<(), S::Error> {
+        let mut tap = [0u8; TAP_HEADER_SIZE + TAP_TLVS_SIZE];
+        let mut tap_size = 0;
+        if self.link_type == LinkType::Ieee802154Tap {
+            // version and reserved are zero
+            // FCS type, no FCS
+            tap[4..6].copy_from_slice(&TAP_FCS_TYPE.to_le_bytes());
+            tap[6..8].copy_from_slice(&1u16.to_le_bytes());
+            tap_size = TAP_HEADER_SIZE + 8;
+            if let Some(rssi) = metadata.rssi {
+                // Received signal strength in dBm
+                tap[tap_size..tap_size + 2].copy_from_slice(&TAP_RSS.to_le_bytes());
+                tap[tap_size + 2..tap_size + 4].copy_from_slice(&4u16.to_le_bytes());
+                tap[tap_size + 4..tap_size + 8].copy_from_slice(&f32::from(rssi).to_le_bytes());
+                tap_size += 8;
+            }
+            if let Some(channel) = metadata.channel {
+                // Channel on page 0
+                tap[tap_size..tap_size + 2].copy_from_slice(&TAP_CHANNEL_ASSIGNMENT.to_le_bytes());
+                tap[tap_size + 2..tap_size + 4].copy_from_slice(&3u16.to_le_bytes());
+                tap[tap_size + 4..tap_size + 6].copy_from_slice(&u16::from(channel).to_le_bytes());
+                tap_size += 8;
+            }
+            if let Some(lqi) = metadata.lqi {
+                tap[tap_size..tap_size + 2].copy_from_slice(&TAP_LQI.to_le_bytes());
+                tap[tap_size + 2..tap_size + 4].copy_from_slice(&1u16.to_le_bytes());
+                tap[tap_size + 4] = lqi;
+                tap_size += 8;
+            }
+            tap[2..4].copy_from_slice(&(tap_size as u16).to_le_bytes());
+        }
+        let timestamp = metadata.timestamp;
+        let length = (tap_size + frame.len()) as u32;
+        let mut record = [0u8; 16];
+        record[0..4].copy_from_slice(&((timestamp / 1_000_000) as u32).to_le_bytes());
+        record[4..8].copy_from_slice(&((timestamp % 1_000_000) as u32).to_le_bytes());
+        record[8..12].copy_from_slice(&length.to_le_bytes());
+        record[12..16].copy_from_slice(&length.to_le_bytes());
+        self.sink.write_all(&record)?;
+        self.sink.write_all(&tap[..tap_size])?;
+        self.sink.write_all(frame)
+    }
+
+    pub fn into_inner(self) -> S {
+        self.sink
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Error {
+    UnknownFormat,
+    Truncated,
+    TooManyInterfaces,
+    UnknownInterface,
+    /// The interface timestamp resolution is finer than a u64 can hold
+    InvalidTimestampResolution(u8),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Format {
+    Pcap { nanoseconds: bool },
+    PcapNg,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Interface {
+    link_type: u32,
+    timestamp_resolution: u8,
+}
+
+/// A captured frame as found in a capture file
+#[derive(Clone, Copy, Debug)]
+pub struct Record<'a> {
+    /// Capture time in microseconds
+    pub timestamp: u64,
+    pub link_type: u32,
+    pub data: &'a [u8],
+}
+
+/// An IEEE 802.15.4 frame extracted from a record
+#[derive(Clone, Copy, Debug)]
+pub struct CapturedFrame<'a> {
+    /// The MAC frame without FCS
+    pub frame: &'a [u8],
+    pub fcs: Option<&'a [u8]>,
+    /// Capture time and, for TAP records, the radio information
+    pub metadata: RxMetadata,
+}
+
+impl<'a> Record<'a> {
+    /// Extract the IEEE 802.15.4 frame, returns `None` for link types that do not carry one
+    pub fn ieee802154_frame(&self) -> Option<CapturedFrame<'a>> {
+        let mut captured = CapturedFrame {
+            frame: self.data,
+            fcs: None,
+            metadata: RxMetadata {
+                timestamp: self.timestamp,
+                ..RxMetadata::default()
+            },
+        };
+        match self.link_type {
+            LINKTYPE_IEEE802_15_4_NOFCS => (),
+            LINKTYPE_IEEE802_15_4_WITHFCS => {
+                let (frame, fcs) = self.data.split_at(self.data.len().checked_sub(2)?);
+                captured.frame = frame;
+                captured.fcs = Some(fcs);
+            }
+            LINKTYPE_IEEE802_15_4_TAP => {
+                let header_size = usize::from(read_u16_le(self.data, 2)?);
+                if header_size < TAP_HEADER_SIZE || header_size > self.data.len() {
+                    return None;
+                }
+                let mut fcs_size = 0;
+                let mut offset = TAP_HEADER_SIZE;
+                while offset + 4 <= header_size {
+                    let tlv_type = read_u16_le(self.data, offset)?;
+                    let tlv_length = usize::from(read_u16_le(self.data, offset + 2)?);
+                    let value = self.data.get(offset + 4..offset + 4 + tlv_length)?;
+                    match tlv_type {
+                        TAP_FCS_TYPE => {
+                            fcs_size = match value.first() {
+                                Some(1) => 2,
+                                Some(2) => 4,
+                                _ => 0,
+                            };
+                        }
+                        TAP_RSS if tlv_length == 4 => {
+                            let rss = f32::from_le_bytes([value[0], value[1], value[2], value[3]]);
+                            captured.metadata.rssi = Some(rss as i8);
+                        }
+                        TAP_CHANNEL_ASSIGNMENT if tlv_length == 3 => {
+                            captured.metadata.channel = Some(value[0]);
+                        }
+                        TAP_LQI if tlv_length == 1 => {
+                            captured.metadata.lqi = Some(value[0]);
+                        }
+                        _ => (),
+                    }
+                    offset += 4 + ((tlv_length + 3) & !3);
+                }
+                let data = &self.data[header_size..];
+                let (frame, fcs) = data.split_at(data.len().checked_sub(fcs_size)?);
+                captured.frame = frame;
+                if fcs_size > 0 {
+                    captured.fcs = Some(fcs);
+                }
+            }
+            _ => return None,
+        }
+        // Only the 16-bit FCS is verified
+        if let Some(fcs) = captured.fcs.filter(|fcs| fcs.len() == fcs::FCS_SIZE) {
+            captured.metadata.fcs = fcs::check(captured.frame, fcs);
+        }
+        Some(captured)
+    }
+}
+
+fn read_u16_le(data: &[u8], offset: usize) -> Option<u16> {
+    let bytes = data.get(offset..offset + 2)?;
+    Some(u16::from_le_bytes([bytes[0], bytes[1]]))
+}
+
+/// Reader for pcap and pcapng capture files held in memory
+#[derive(Clone)]
+pub struct PcapReader<'a> {
+    data: &'a [u8],
+    offset: usize,
+    format: Format,
+    big_endian: bool,
+    link_type: u32,
+    interfaces: heapless::Vec<Interface, 8>,
+}
+
+impl<'a> PcapReader<'a> {
+    pub fn new(data: &'a [u8]) -> Result<Self, Error> {
+        let magic = data.get(0..4).ok_or(Error::Truncated)?;
+        let magic_le = u32::from_le_bytes([magic[0], magic[1], magic[2], magic[3]]);
+        let magic_be = u32::from_be_bytes([magic[0], magic[1], magic[2], magic[3]]);
+        let mut reader = PcapReader {
+            data,
+            offset: 0,
+            format: Format::PcapNg,
+            big_endian: false,
+            link_type: 0,
+            interfaces: heapless::Vec::new(),
+        };
+        if magic_le == MAGIC || magic_le == MAGIC_NANOSECONDS {
+            reader.format = Format::Pcap {
+                nanoseconds: magic_le == MAGIC_NANOSECONDS,
+            };
+        } else if magic_be == MAGIC || magic_be == MAGIC_NANOSECONDS {
+            reader.format = Format::Pcap {
+                nanoseconds: magic_be == MAGIC_NANOSECONDS,
+            };
+            reader.big_endian = true;
+        } else if magic_le == PCAPNG_SECTION_HEADER {
+            return Ok(reader);
+        } else {
+            return Err(Error::UnknownFormat);
+        }
+        reader.link_type = reader.read_u32(20).ok_or(Error::Truncated)?;
+        reader.offset = 24;
+        Ok(reader)
+    }
+
+    fn read_u16(&self, offset: usize) -> Option<u16> {
+        let bytes = self.data.get(offset..offset + 2)?;
+        let bytes = [bytes[0], bytes[1]];
+        Some(if self.big_endian {
+            u16::from_be_bytes(bytes)
+        } else {
+            u16::from_le_bytes(bytes)
+        })
+    }
+
+    fn read_u32(&self, offset: usize) -> Option<u32> {
+        let bytes = self.data.get(offset..offset + 4)?;
+        let bytes = [bytes[0], bytes[1], bytes[2], bytes[3]];
+        Some(if self.big_endian {
+            u32::from_be_bytes(bytes)
+        } else {
+            u32::from_le_bytes(bytes)
+        })
+    }
+
+    fn next_pcap_record(&mut self, nanoseconds: bool) -> Result<Record<'a>, Error> {
+        let seconds = self.read_u32(self.offset).ok_or(Error::Truncated)?;
+        let fraction = self.read_u32(self.offset + 4).ok_or(Error::Truncated)?;
+        let length = self.read_u32(self.offset + 8).ok_or(Error::Truncated)? as usize;
+        let start = self.offset + 16;
+        let data = self
+            .data
+            .get(start..start + length)
+            .ok_or(Error::Truncated)?;
+        self.offset = start + length;
+        let fraction = if nanoseconds {
+            u64::from(fraction) / 1000
+        } else {
+            u64::from(fraction)
+        };
+        Ok(Record {
+            timestamp: u64::from(seconds) * 1_000_000 + fraction,
+            link_type: self.link_type,
+            data,
+        })
+    }
+
+    fn parse_interface(&mut self, block: usize, block_length: usize) -> Result<(), Error> {
+        let link_type = self.read_u16(block + 8).ok_or(Error::Truncated)?;
+        let mut interface = Interface {
+            link_type: u32::from(link_type),
+            timestamp_resolution: 6,
+        };
+        let mut offset = block + 16;
+        while offset + 4 <= block + block_length - 4 {
+            let code = self.read_u16(offset).ok_or(Error::Truncated)?;
+            let length = usize::from(self.read_u16(offset + 2).ok_or(Error::Truncated)?);
+            if code == PCAPNG_OPTION_END {
+                break;
+            }
+            if code == PCAPNG_OPTION_TIMESTAMP_RESOLUTION && length == 1 {
+                let resolution = self.data[offset + 4];
+                if resolution & 0x80 == 0 && resolution > PCAPNG_MAX_DECIMAL_RESOLUTION {
+                    return Err(Error::InvalidTimestampResolution(resolution));
+                }
+                interface.timestamp_resolution = resolution;
+            }
+            offset += 4 + ((length + 3) & !3);
+        }
+        self.interfaces
+            .push(interface)
+            .map_err(|_| Error::TooManyInterfaces)
+    }
+
+    fn timestamp(resolution: u8, value: u64) -> u64 {
+        if resolution & 0x80 == 0x80 {
+            let shift = u32::from(resolution & 0x7f);
+            u64::try_from((u128::from(value) * 1_000_000) >> shift).unwrap_or(u64::MAX)
+        } else if resolution >= 6 {
+            value / 10u64.pow(u32::from(resolution - 6))
+        } else {
+            value.saturating_mul(10u64.pow(u32::from(6 - resolution)))
+        }
+    }
+
+    fn next_pcapng_record(&mut self) -> Result<Option<Record<'a>>, Error> {
+        loop {
+            if self.offset >= self.data.len() {
+                return Ok(None);
+            }
+            let block = self.offset;
+            let block_type = self.read_u32(block).ok_or(Error::Truncated)?;
+            if block_type == PCAPNG_SECTION_HEADER {
+                let magic = self
+                    .data
+                    .get(block + 8..block + 12)
+                    .ok_or(Error::Truncated)?;
+                let magic = [magic[0], magic[1], magic[2], magic[3]];
+                self.big_endian = if u32::from_le_bytes(magic) == PCAPNG_BYTE_ORDER_MAGIC {
+                    false
+                } else if u32::from_be_bytes(magic) == PCAPNG_BYTE_ORDER_MAGIC {
+                    true
+                } else {
+                    return Err(Error::UnknownFormat);
+                };
+                self.interfaces.clear();
+            }
+            let block_length = self.read_u32(block + 4).ok_or(Error::Truncated)? as usize;
+            if block_length < 12 || block + block_length > self.data.len() {
+                return Err(Error::Truncated);
+            }
+            self.offset = block + block_length;
+            match block_type {
+                PCAPNG_INTERFACE_DESCRIPTION => {
+                    self.parse_interface(block, block_length)?;
+                }
+                PCAPNG_ENHANCED_PACKET => {
+                    let interface = self.read_u32(block + 8).ok_or(Error::Truncated)?;
+                    let interface = *self
+                        .interfaces
+                        .get(interface as usize)
+                        .ok_or(Error::UnknownInterface)?;
+                    let high = self.read_u32(block + 12).ok_or(Error::Truncated)?;
+                    let low = self.read_u32(block + 16).ok_or(Error::Truncated)?;
+                    let length = self.read_u32(block + 20).ok_or(Error::Truncated)? as usize;
+                    let data = self
+                        .data
+                        .get(block + 28..block + 28 + length)
+                        .ok_or(Error::Truncated)?;
+                    let value = (u64::from(high) << 32) | u64::from(low);
+                    return Ok(Some(Record {
+                        timestamp: Self::timestamp(interface.timestamp_resolution, value),
+                        link_type: interface.link_type,
+                        data,
+                    }));
+                }
+                PCAPNG_SIMPLE_PACKET => {
+                    let interface = *self.interfaces.first().ok_or(Error::UnknownInterface)?;
+                    let length = self.read_u32(block + 8).ok_or(Error::Truncated)? as usize;
+                    let length = length.min(block_length.checked_sub(16).ok_or(Error::Truncated)?);
+                    let data = &self.data[block + 12..block + 12 + length];
+                    return Ok(Some(Record {
+                        timestamp: 0,
+                        link_type: interface.link_type,
+                        data,
+                    }));
+                }
+                _ => (),
+            }
+        }
+    }
+}
+
+impl<'a> Iterator for PcapReader<'a> {
+    type Item = Result<Record<'a>, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let result = match self.format {
+            Format::Pcap { nanoseconds } => {
+                if self.offset >= self.data.len() {
+                    return None;
+                }
+                self.next_pcap_record(nanoseconds).map(Some)
+            }
+            Format::PcapNg => self.next_pcapng_record(),
+        };
+        match result {
+            Ok(Some(record)) => Some(Ok(record)),
+            Ok(None) => None,
+            Err(e) => {
+                // Stop at the first error
+                self.offset = self.data.len();
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+/// Reader for a pcap stream that is read as it is written, e.g. the listener output on the serial
+/// port. Anything before the pcap file header, such as boot messages, is skipped.
+#[cfg(feature = "std")]
+pub struct StreamReader<R: std::io::Read> {
+    input: R,
+    big_endian: bool,
+    nanoseconds: bool,
+    snaplen: u32,
+    link_type: u32,
+    data: Vec<u8>,
+}
+
+#[cfg(feature = "std")]
+impl<R: std::io::Read> StreamReader<R> {
+    /// Find and read the pcap file header
+    pub fn new(mut input: R) -> std::io::Result<Self> {
+        let mut magic = [0u8; 4];
+        input.read_exact(&mut magic)?;
+        let (big_endian, nanoseconds) = loop {
+            match (u32::from_le_bytes(magic), u32::from_be_bytes(magic)) {
+                (MAGIC, _) => break (false, false),
+                (MAGIC_NANOSECONDS, _) => break (false, true),
+                (_, MAGIC) => break (true, false),
+                (_, MAGIC_NANOSECONDS) => break (true, true),
+                _ => {
+                    magic.copy_within(1.., 0);
+                    if let Err(e) = input.read_exact(&mut magic[3..]) {
+                        return Err(if e.kind() == std::io::ErrorKind::UnexpectedEof {
+                            std::io::Error::new(
+                                std::io::ErrorKind::InvalidData,
+                                "Not a pcap stream",
+                            )
+                        } else {
+                            e
+                        });
+                    }
+                }
+            }
+        };
+        let mut header = [0u8; 20];
+        input.read_exact(&mut header)?;
+        let mut reader = StreamReader {
+            input,
+            big_endian,
+            nanoseconds,
+            snaplen: 0,
+            link_type: 0,
+            data: Vec::new(),
+        };
+        reader.snaplen = reader.u32(&header[12..16]);
+        reader.link_type = reader.u32(&header[16..20]);
+        Ok(reader)
+    }
+
+    pub fn link_type(&self) -> u32 {
+        self.link_type
+    }
+
+    fn u32(&self, bytes: &[u8]) -> u32 {
+        let bytes = [bytes[0], bytes[1], bytes[2], bytes[3]];
+        if self.big_endian {
+            u32::from_be_bytes(bytes)
+        } else {
+            u32::from_le_bytes(bytes)
+        }
+    }
+
+    /// Read the next record, returns `None` when the stream ends between records
+    pub fn next_record(&mut self) -> std::io::Result<Option<Record<'_>>> {
+        let mut header = [0u8; 16];
+        match self.input.read_exact(&mut header) {
+            Ok(()) => (),
+            Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(None),
+            Err(e) => return Err(e),
+        }
+        let seconds = u64::from(self.u32(&header[0..4]));
+        let mut fraction = u64::from(self.u32(&header[4..8]));
+        if self.nanoseconds {
+            fraction /= 1000;
+        }
+        let length = self.u32(&header[8..12]);
+        if length > self.snaplen {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "Record larger than the snapshot length",
+            ));
+        }
+        self.data.resize(length as usize, 0);
+        self.input.read_exact(&mut self.data)?;
+        Ok(Some(Record {
+            timestamp: seconds * 1_000_000 + fraction,
+            link_type: self.link_type,
+            data: &self.data,
+        }))
+    }
+}
@@ -0,0 +1,314 @@
+//! Abstraction of the IEEE 802.15.4 radio used for sniffing.
+//!
+//! Besides the ESP32-C6 radio there is a scripted in-memory radio and a radio replaying a capture file,
+//! which lets the code built on top of the radio run on the host.
+
+use crate::decoded::RxMetadata;
+use crate::pcap::{self, PcapReader};
+
+/// Largest IEEE 802.15.4 PSDU
+pub const MAX_FRAME_SIZE: usize = 127;
+pub const FIRST_CHANNEL: u8 = 11;
+pub const LAST_CHANNEL: u8 = 26;
+
+pub type FrameBuffer = heapless::Vec<u8, MAX_FRAME_SIZE>;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Error {
+    /// Not an IEEE 802.15.4 channel in the 2.4 GHz band
+    InvalidChannel(u8),
+    /// The frame does not fit in a PSDU
+    FrameTooLarge(usize),
+    /// The PSDU length of a received frame is out of range
+    InvalidLength(usize),
+    /// No room for more frames
+    Full,
+    /// The radio failed to send the frame
+    Transmit,
+    /// The capture file could not be read
+    Capture(pcap::Error),
+}
+
+fn check_channel(channel: u8) -> Result<(), Error> {
+    if (FIRST_CHANNEL..=LAST_CHANNEL).contains(&channel) {
+        Ok(())
+    } else {
+        Err(Error::InvalidChannel(channel))
+    }
+}
+
+/// A frame as received by the radio
+#[derive(Clone, Debug, PartialEq)]
+pub struct ReceivedFrame {
+    /// The MAC frame without FCS
+    pub data: FrameBuffer,
+    pub metadata: RxMetadata,
+}
+
+impl ReceivedFrame {
+    pub fn new(data: &[u8], metadata: RxMetadata) -> Result<Self, Error> {
+        let data = FrameBuffer::from_slice(data).map_err(|_| Error::FrameTooLarge(data.len()))?;
+        Ok(ReceivedFrame { data, metadata })
+    }
+
+    /// Frame from the buffer of the ESP32-C6 radio, `[length, PSDU]`. The first byte is the PSDU
+    /// length, the radio replaces the two FCS bytes at the end of the PSDU with the signal
+    /// strength, at `length - 1`, and the link quality, at `length`, so the FCS can not be
+    /// verified.
+    pub fn from_raw(raw: &[u8], metadata: RxMetadata) -> Result<Self, Error> {
+        let size = raw.first().map_or(0, |size| usize::from(*size));
+        if !(3..=MAX_FRAME_SIZE).contains(&size) || size >= raw.len() {
+            return Err(Error::InvalidLength(size));
+        }
+        let metadata = RxMetadata {
+            rssi: Some(raw[size - 1] as i8),
+            lqi: Some(raw[size]),
+            ..metadata
+        };
+        ReceivedFrame::new(&raw[1..(size - 1)], metadata)
+    }
+}
+
+pub trait Radio {
+    type Error;
+
+    fn set_channel(&mut self, channel: u8) -> Result<(), Self::Error>;
+
+    /// Get a received frame if there is one, does not block
+    fn receive(&mut self) -> Result<Option<ReceivedFrame>, Self::Error>;
+
+    /// Current time in microseconds, in the same time base as the received frame timestamps
+    fn now(&self) -> u64;
+}
+
+/// A radio that can also send frames
+pub trait Transmit: Radio {
+    /// Send a MAC frame, the FCS is added by the radio
+    fn transmit(&mut self, data: &[u8]) -> Result<(), Self::Error>;
+}
+
+/// Radio fed with frames from memory
+///
+/// Time advances with `step` microseconds on each receive. A frame is delivered once its timestamp
+/// has passed, frames for other channels than the current are lost as with a real radio.
+pub struct MemoryRadio<const N: usize> {
+    frames: heapless::Deque<ReceivedFrame, N>,
+    transmitted: heapless::Vec<FrameBuffer, N>,
+    channel: u8,
+    now: u64,
+    step: u64,
+}
+
+impl<const N: usize> MemoryRadio<N> {
+    pub fn new(step: u64) -> Self {
+        MemoryRadio {
+            frames: heapless::Deque::new(),
+            transmitted: heapless::Vec::new(),
+            channel: FIRST_CHANNEL,
+            now: 0,
+            step,
+        }
+    }
+
+    /// Queue a frame, frames must be queued in timestamp order
+    pub fn push(&mut self, data: &[u8], metadata: RxMetadata) -> Result<(), Error> {
+        let frame = ReceivedFrame::new(data, metadata)?;
+        self.frames.push_back(frame).map_err(|_| Error::Full)
+    }
+
+    pub fn channel(&self) -> u8 {
+        self.channel
+    }
+
+    pub fn advance(&mut self, time: u64) {
+        self.now = self.now.saturating_add(time);
+    }
+
+    /// Frames left to receive
+    pub fn pending(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn transmitted(&self) -> &[FrameBuffer] {
+        &self.transmitted
+    }
+}
+
+impl<const N: usize> Radio for MemoryRadio<N> {
+    type Error = Error;
+
+    fn set_channel(&mut self, channel: u8) -> Result<(), Self::Error> {
+        check_channel(channel)?;
+        self.channel = channel;
+        Ok(())
+    }
+
+    fn receive(&mut self) -> Result<Option<ReceivedFrame>, Self::Error> {
+        self.advance(self.step);
+        while let Some(frame) = self.frames.front() {
+            if frame.metadata.timestamp > self.now {
+                break;
+            }
+            if let Some(mut frame) = self.frames.pop_front() {
+                if frame.metadata.channel.map_or(true, |c| c == self.channel) {
+                    frame.metadata.channel = Some(self.channel);
+                    return Ok(Some(frame));
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    fn now(&self) -> u64 {
+        self.now
+    }
+}
+
+impl<const N: usize> Transmit for MemoryRadio<N> {
+    fn transmit(&mut self, data: &[u8]) -> Result<(), Self::Error> {
+        let frame = FrameBuffer::from_slice(data).map_err(|_| Error::FrameTooLarge(data.len()))?;
+        self.transmitted.push(frame).map_err(|_| Error::Full)
+    }
+}
+
+/// Radio replaying the IEEE 802.15.4 frames of a pcap or pcapng capture
+///
+/// Time follows the capture timestamps. Frames captured on another channel than the current are
+/// skipped, frames without channel information are received on any channel. When the capture is
+/// exhausted the time jumps to the end of time.
+pub struct ReplayRadio<'a> {
+    reader: PcapReader<'a>,
+    channel: u8,
+    now: u64,
+}
+
+impl<'a> ReplayRadio<'a> {
+    pub fn new(data: &'a [u8]) -> Result<Self, Error> {
+        let reader = PcapReader::new(data).map_err(Error::Capture)?;
+        // Time starts at the first record
+        let now = reader
+            .clone()
+            .find_map(|record| record.ok())
+            .map_or(0, |record| record.timestamp);
+        Ok(ReplayRadio {
+            reader,
+            channel: FIRST_CHANNEL,
+            now,
+        })
+    }
+
+    pub fn channel(&self) -> u8 {
+        self.channel
+    }
+}
+
+impl<'a> Radio for ReplayRadio<'a> {
+    type Error = Error;
+
+    fn set_channel(&mut self, channel: u8) -> Result<(), Self::Error> {
+        check_channel(channel)?;
+        self.channel = channel;
+        Ok(())
+    }
+
+    fn receive(&mut self) -> Result<Option<ReceivedFrame>, Self::Error> {
+        for record in self.reader.by_ref() {
+            let record = record.map_err(Error::Capture)?;
+            self.now = record.timestamp;
+            let Some(captured) = record.ieee802154_frame() else {
+                continue;
+            };
+            let mut metadata = captured.metadata;
+            if metadata.channel.map_or(true, |c| c == self.channel) {
+                metadata.channel = Some(self.channel);
+                return ReceivedFrame::new(captured.frame, metadata).map(Some);
+            }
+        }
+        self.now = u64::MAX;
+        Ok(None)
+    }
+
+    fn now(&self) -> u64 {
+        self.now
+    }
+}
+
+/// The ESP32-C6 IEEE 802.15.4 radio in promiscuous mode
+#[cfg(feature = "esp32c6")]
+pub struct EspRadio<'a> {
+    driver: esp_ieee802154::Ieee802154<'a>,
+    config: esp_ieee802154::Config,
+}
+
+#[cfg(feature = "esp32c6")]
+impl<'a> EspRadio<'a> {
+    /// Configure the radio for sniffing on `channel` and start receiving
+    pub fn new(mut driver: esp_ieee802154::Ieee802154<'a>, channel: u8) -> Result<Self, Error> {
+        check_channel(channel)?;
+        let config = esp_ieee802154::Config {
+            channel,
+            promiscuous: true,
+            rx_when_idle: true,
+            auto_ack_rx: false,
+            auto_ack_tx: false,
+            ..esp_ieee802154::Config::default()
+        };
+        driver.set_config(config);
+        driver.start_receive();
+        Ok(EspRadio { driver, config })
+    }
+
+    pub fn channel(&self) -> u8 {
+        self.config.channel
+    }
+}
+
+#[cfg(feature = "esp32c6")]
+impl<'a> Radio for EspRadio<'a> {
+    type Error = Error;
+
+    fn set_channel(&mut self, channel: u8) -> Result<(), Self::Error> {
+        check_channel(channel)?;
+        self.config.channel = channel;
+        self.driver.set_config(self.config);
+        self.driver.start_receive();
+        Ok(())
+    }
+
+    fn receive(&mut self) -> Result<Option<ReceivedFrame>, Self::Error> {
+        let Some(received) = self.driver.get_raw_received() else {
+            return Ok(None);
+        };
+        let metadata = RxMetadata {
+            rssi: None,
+            lqi: None,
+            channel: Some(self.config.channel),
+            timestamp: self.now(),
+            fcs: crate::fcs::FcsStatus::Unknown,
+        };
+        match ReceivedFrame::from_raw(&received.data, metadata) {
+            Err(Error::InvalidLength(size)) => {
+                warn!("Received frame with invalid length {}", size);
+                Ok(None)
+            }
+            result => result.map(Some),
+        }
+    }
+
+    fn now(&self) -> u64 {
+        use hal::systimer::SystemTimer;
+        SystemTimer::now() / (SystemTimer::TICKS_PER_SECOND / 1_000_000)
+    }
+}
+
+#[cfg(feature = "esp32c6")]
+impl<'a> Transmit for EspRadio<'a> {
+    fn transmit(&mut self, data: &[u8]) -> Result<(), Self::Error> {
+        if data.len() + 2 > MAX_FRAME_SIZE {
+            return Err(Error::FrameTooLarge(data.len()));
+        }
+        let result = self.driver.transmit_raw(data).map_err(|_| Error::Transmit);
+        self.driver.start_receive();
+        result
+    }
+}
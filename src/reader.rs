@@ -0,0 +1,64 @@
+use psila_data::Error;
+
+/// Little-endian reader over a byte slice
+#[derive(Clone, Debug)]
+pub(crate) struct ByteReader<'a> {
+    data: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> ByteReader<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        ByteReader { data, offset: 0 }
+    }
+
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    pub fn remaining(&self) -> &'a [u8] {
+        &self.data[self.offset..]
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.offset >= self.data.len()
+    }
+
+    pub fn bytes(&mut self, size: usize) -> Result<&'a [u8], Error> {
+        if self.offset + size > self.data.len() {
+            return Err(Error::WrongNumberOfBytes);
+        }
+        let bytes = &self.data[self.offset..self.offset + size];
+        self.offset += size;
+        Ok(bytes)
+    }
+
+    pub fn u8(&mut self) -> Result<u8, Error> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    pub fn u16(&mut self) -> Result<u16, Error> {
+        let b = self.bytes(2)?;
+        Ok(u16::from_le_bytes([b[0], b[1]]))
+    }
+
+    pub fn u32(&mut self) -> Result<u32, Error> {
+        let b = self.bytes(4)?;
+        Ok(u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+    }
+
+    pub fn u64(&mut self) -> Result<u64, Error> {
+        let b = self.bytes(8)?;
+        let mut value = [0u8; 8];
+        value.copy_from_slice(b);
+        Ok(u64::from_le_bytes(value))
+    }
+
+    /// Read an unsigned integer of 1 to 8 bytes
+    pub fn uint(&mut self, size: usize) -> Result<u64, Error> {
+        let b = self.bytes(size)?;
+        Ok(b.iter()
+            .rev()
+            .fold(0u64, |value, b| (value << 8) | u64::from(*b)))
+    }
+}
@@ -1,105 +1,462 @@
 use core::convert::From;
 
+use aes::cipher::{generic_array::GenericArray, BlockEncrypt, KeyInit};
+use aes::Aes128;
 use psila_crypto_rust_crypto::RustCryptoBackend;
 use psila_data::application_service::commands::transport_key::NetworkKey;
-use psila_data::{common::key::Key, pack::Pack, security};
-use ufmt::uwrite;
+use psila_data::{common::key::Key, security, ExtendedAddress};
+
+use crate::fcs::crc16_x25;
+use crate::frame_counter::FrameCounterTable;
+use crate::mac_security::{self, AuxiliarySecurityHeader};
+
+const BLOCK_SIZE: usize = 16;
+
+/// Hash input used to derive the key-transport key from a link key
+pub const KEY_TRANSPORT_INPUT: u8 = 0x00;
+/// Hash input used to derive the key-load key from a link key
+pub const KEY_LOAD_INPUT: u8 = 0x02;
+
+/// Matyas–Meyer–Oseas hash built on AES-128, as specified in the Zigbee specification B.6
+///
+/// Only messages shorter than 2^16 bits are supported, which covers keys and install codes.
+#[derive(Clone)]
+pub struct MmoHash {
+    hash: [u8; BLOCK_SIZE],
+    block: [u8; BLOCK_SIZE],
+    fill: usize,
+    length: usize,
+}
+
+impl MmoHash {
+    pub fn new() -> Self {
+        MmoHash {
+            hash: [0; BLOCK_SIZE],
+            block: [0; BLOCK_SIZE],
+            fill: 0,
+            length: 0,
+        }
+    }
+
+    fn process_block(&mut self) {
+        let cipher = Aes128::new(GenericArray::from_slice(&self.hash));
+        let mut output = GenericArray::clone_from_slice(&self.block);
+        cipher.encrypt_block(&mut output);
+        for (hash, (encrypted, plain)) in self
+            .hash
+            .iter_mut()
+            .zip(output.iter().zip(self.block.iter()))
+        {
+            *hash = encrypted ^ plain;
+        }
+        self.fill = 0;
+    }
+
+    pub fn update(&mut self, data: &[u8]) {
+        for byte in data.iter() {
+            self.block[self.fill] = *byte;
+            self.fill += 1;
+            if self.fill == BLOCK_SIZE {
+                self.process_block();
+            }
+        }
+        self.length += data.len();
+    }
+
+    pub fn finalize(mut self) -> [u8; BLOCK_SIZE] {
+        let bits = (self.length * 8) as u16;
+        self.block[self.fill] = 0x80;
+        self.fill += 1;
+        if self.fill > BLOCK_SIZE - 2 {
+            self.block[self.fill..].fill(0);
+            self.process_block();
+        }
+        self.block[self.fill..BLOCK_SIZE - 2].fill(0);
+        self.block[BLOCK_SIZE - 2..].copy_from_slice(&bits.to_be_bytes());
+        self.process_block();
+        self.hash
+    }
+}
+
+impl Default for MmoHash {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Hash `data` with the Matyas–Meyer–Oseas hash
+pub fn mmo_hash(data: &[u8]) -> [u8; BLOCK_SIZE] {
+    let mut hash = MmoHash::new();
+    hash.update(data);
+    hash.finalize()
+}
+
+/// Keyed hash, HMAC using the Matyas–Meyer–Oseas hash, of a single byte as used to derive the
+/// key-transport and key-load keys from a link key
+pub fn hash_key(key: &Key, input: u8) -> Key {
+    let key: [u8; BLOCK_SIZE] = (*key).into();
+    let mut inner = MmoHash::new();
+    let mut outer = MmoHash::new();
+    for byte in key.iter() {
+        inner.update(&[byte ^ 0x36]);
+        outer.update(&[byte ^ 0x5c]);
+    }
+    inner.update(&[input]);
+    outer.update(&inner.finalize());
+    Key::from(outer.finalize())
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum InstallCodeError {
+    /// Install codes are 6, 8, 12 or 16 bytes followed by a 2 byte CRC
+    InvalidLength,
+    InvalidCrc,
+}
+
+/// Derive the link key from an install code, including the trailing CRC
+pub fn install_code_key(code: &[u8]) -> Result<Key, InstallCodeError> {
+    let size = match code.len() {
+        8 | 10 | 14 | 18 => code.len() - 2,
+        _ => return Err(InstallCodeError::InvalidLength),
+    };
+    let crc = u16::from_le_bytes([code[size], code[size + 1]]);
+    if crc16_x25(&code[..size]) != crc {
+        return Err(InstallCodeError::InvalidCrc);
+    }
+    Ok(Key::from(mmo_hash(code)))
+}
+
+/// A link key, optionally associated with the IEEE address of the device using it
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LinkKeyEntry {
+    pub address: Option<ExtendedAddress>,
+    pub key: Key,
+}
+
+/// A network key and its key sequence number, the sequence is unknown for keys provided by the
+/// user
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct NetworkKeyEntry {
+    pub sequence: Option<u8>,
+    pub key: Key,
+}
+
+/// An IEEE 802.15.4 MAC layer key, optionally for a key index of the auxiliary security header
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MacKeyEntry {
+    pub key_index: Option<u8>,
+    pub key: Key,
+}
+
+/// The network key sequence in use on a PAN
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ActiveNetworkKey {
+    pub pan_identifier: u16,
+    pub sequence: u8,
+}
+
+/// Decryption attempts with a stored key, derived keys are accounted to the link key
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct KeyStatistics {
+    pub key: Key,
+    pub successes: u32,
+    pub failures: u32,
+}
 
 pub struct SecurityService {
-    pub keys: heapless::Vec<Key, 16>,
+    pub network_keys: heapless::Vec<NetworkKeyEntry, 16>,
+    pub active_network_keys: heapless::Vec<ActiveNetworkKey, 8>,
+    pub link_keys: heapless::Vec<LinkKeyEntry, 16>,
+    pub mac_keys: heapless::Vec<MacKeyEntry, 16>,
+    pub frame_counters: FrameCounterTable,
+    pub key_statistics: heapless::Vec<KeyStatistics, 32>,
     crypto_provider: security::CryptoProvider<RustCryptoBackend>,
 }
 
 impl SecurityService {
     pub fn new() -> Self {
-        let mut keys: heapless::Vec<Key, 16> = heapless::Vec::new();
-        let _ = keys.push(Key::from(security::DEFAULT_LINK_KEY));
+        let mut link_keys: heapless::Vec<LinkKeyEntry, 16> = heapless::Vec::new();
+        let _ = link_keys.push(LinkKeyEntry {
+            address: None,
+            key: Key::from(security::DEFAULT_LINK_KEY),
+        });
         let backend = RustCryptoBackend::default();
         let crypto_provider = security::CryptoProvider::new(backend);
         SecurityService {
-            keys,
+            network_keys: heapless::Vec::new(),
+            active_network_keys: heapless::Vec::new(),
+            link_keys,
+            mac_keys: heapless::Vec::new(),
+            frame_counters: FrameCounterTable::new(),
+            key_statistics: heapless::Vec::new(),
             crypto_provider,
         }
     }
 
-    fn print_header(header: &security::SecurityHeader) {
-        let mut line: heapless::String<256> = heapless::String::new();
-
-        let level = match header.control.level {
-            security::SecurityLevel::None => "None",
-            security::SecurityLevel::Integrity32 => "32-bitIntegrity",
-            security::SecurityLevel::Integrity64 => "64-bitIntegrity",
-            security::SecurityLevel::Integrity128 => "128-bitIntegrity",
-            security::SecurityLevel::Encrypted => "Encrypted",
-            security::SecurityLevel::EncryptedIntegrity32 => "Encrypted, 32-bit Integrity",
-            security::SecurityLevel::EncryptedIntegrity64 => "Encrypted, 64-bit Integrity",
-            security::SecurityLevel::EncryptedIntegrity128 => "Encrypted, 128-bit Integrity",
+    fn record_attempt(&mut self, stored: Key, success: bool) {
+        let index = match self.key_statistics.iter().position(|s| s.key == stored) {
+            Some(index) => index,
+            None => {
+                let statistics = KeyStatistics {
+                    key: stored,
+                    successes: 0,
+                    failures: 0,
+                };
+                if self.key_statistics.push(statistics).is_err() {
+                    return;
+                }
+                self.key_statistics.len() - 1
+            }
         };
-        let identifier = match header.control.identifier {
-            security::KeyIdentifier::Data => "Data",
-            security::KeyIdentifier::Network => "Network",
-            security::KeyIdentifier::KeyTransport => "Key transport",
-            security::KeyIdentifier::KeyLoad => "Key load",
+        let statistics = &mut self.key_statistics[index];
+        if success {
+            statistics.successes = statistics.successes.wrapping_add(1);
+        } else {
+            statistics.failures = statistics.failures.wrapping_add(1);
+        }
+    }
+
+    /// Decrypt with `key`, the attempt is accounted to the `stored` key
+    fn decrypt_with_key(
+        &mut self,
+        key: Key,
+        stored: Key,
+        payload: &[u8],
+        offset: usize,
+        output: &mut [u8],
+    ) -> Option<usize> {
+        let key = key.into();
+        let result = match self.crypto_provider.decrypt_payload(
+            &key,
+            security::SecurityLevel::EncryptedIntegrity32,
+            payload,
+            offset,
+            output,
+        ) {
+            Ok(size) if size > 0 => Some(size),
+            _ => None,
         };
+        self.record_attempt(stored, result.is_some());
+        result
+    }
 
-        let _ = uwrite!(line, "SEC Level {} Key Identifier {}", level, identifier,);
-        if let Some(src) = header.source {
-            let _ = uwrite!(line, " SRC {:08x}", u64::from(src));
-        }
-        if let Some(seq) = header.sequence {
-            let _ = uwrite!(line, " Sequence {}", seq);
+    /// Decrypt the payload with the keys the security header identifies.
+    ///
+    /// Network keys are looked up by the key sequence number, keys with unknown sequence are
+    /// only tried if there is no key with the sequence. Link keys associated with the source of the
+    /// header are tried before other link keys.
+    pub fn decrypt(
+        &mut self,
+        header: &security::SecurityHeader,
+        payload: &[u8],
+        offset: usize,
+        output: &mut [u8],
+    ) -> Option<usize> {
+        match header.control.identifier {
+            security::KeyIdentifier::Network => {
+                if let Some(index) = self
+                    .network_keys
+                    .iter()
+                    .position(|e| e.sequence.is_some() && e.sequence == header.sequence)
+                {
+                    let key = self.network_keys[index].key;
+                    return self.decrypt_with_key(key, key, payload, offset, output);
+                }
+                for index in 0..self.network_keys.len() {
+                    let entry = self.network_keys[index];
+                    if entry.sequence.is_some() {
+                        continue;
+                    }
+                    if let Some(size) =
+                        self.decrypt_with_key(entry.key, entry.key, payload, offset, output)
+                    {
+                        // The key sequence is now known
+                        self.network_keys[index].sequence = header.sequence;
+                        return Some(size);
+                    }
+                }
+                None
+            }
+            security::KeyIdentifier::Data
+            | security::KeyIdentifier::KeyTransport
+            | security::KeyIdentifier::KeyLoad => {
+                // Keys associated with the sender are tried first
+                for associated in [true, false] {
+                    for index in 0..self.link_keys.len() {
+                        let entry = self.link_keys[index];
+                        let candidate = match entry.address {
+                            Some(address) => associated == (header.source == Some(address)),
+                            None => !associated,
+                        };
+                        if !candidate {
+                            continue;
+                        }
+                        let key = match header.control.identifier {
+                            security::KeyIdentifier::KeyTransport => {
+                                hash_key(&entry.key, KEY_TRANSPORT_INPUT)
+                            }
+                            security::KeyIdentifier::KeyLoad => {
+                                hash_key(&entry.key, KEY_LOAD_INPUT)
+                            }
+                            _ => entry.key,
+                        };
+                        if let Some(size) =
+                            self.decrypt_with_key(key, entry.key, payload, offset, output)
+                        {
+                            return Some(size);
+                        }
+                    }
+                }
+                None
+            }
         }
-        let _ = uwrite!(line, " Counter {}", header.counter);
-        defmt::info!("{}", line.as_str());
     }
 
-    pub fn decrypt(&mut self, payload: &[u8], offset: usize, mut output: &mut [u8]) -> usize {
-        match security::SecurityHeader::unpack(&payload[offset..]) {
-            Ok((header, _)) => {
-                Self::print_header(&header);
-            }
-            Err(ref e) => {
-                crate::print_error(e, "Failed to parse security header");
-                return 0;
+    /// Add a network key, `sequence` is the key sequence number if known. A key with known
+    /// sequence replaces any earlier key with the same sequence.
+    pub fn add_network_key(&mut self, key: Key, sequence: Option<u8>) {
+        let entry = NetworkKeyEntry { sequence, key };
+        if sequence.is_some() {
+            if let Some(existing) = self
+                .network_keys
+                .iter_mut()
+                .find(|e| e.sequence == sequence)
+            {
+                existing.key = key;
+                return;
             }
         }
-        for key_index in 0..self.keys.len() {
-            let key = self.keys[key_index].into();
-            let result = self.crypto_provider.decrypt_payload(
-                &key,
-                security::SecurityLevel::EncryptedIntegrity32,
-                &payload,
-                offset,
-                &mut output,
-            );
-            match result {
-                Ok(size) => {
-                    if size > 0 {
-                        defmt::info!("~~~ KEY {}", key_index);
-                        return size;
-                    }
-                }
-                Err(_e) => (),
+        if let Some(existing) = self
+            .network_keys
+            .iter_mut()
+            .find(|e| e.key == key && e.sequence.is_none())
+        {
+            existing.sequence = sequence;
+            return;
+        }
+        if !self.network_keys.contains(&entry) {
+            let _ = self.network_keys.push(entry);
+        }
+    }
+
+    /// Get the network key with key sequence number `sequence`
+    pub fn network_key(&self, sequence: u8) -> Option<&NetworkKeyEntry> {
+        self.network_keys
+            .iter()
+            .find(|e| e.sequence == Some(sequence))
+    }
+
+    /// Get the active network key sequence number of the PAN
+    pub fn active_sequence(&self, pan_identifier: u16) -> Option<u8> {
+        self.active_network_keys
+            .iter()
+            .find(|a| a.pan_identifier == pan_identifier)
+            .map(|a| a.sequence)
+    }
+
+    /// Get the active network key of the PAN
+    pub fn active_network_key(&self, pan_identifier: u16) -> Option<&NetworkKeyEntry> {
+        self.active_sequence(pan_identifier)
+            .and_then(|sequence| self.network_key(sequence))
+    }
+
+    /// Make the network key with `sequence` active on the PAN. Returns false if no key with the
+    /// sequence is stored, the sequence is recorded as active regardless.
+    pub fn switch_network_key(&mut self, pan_identifier: u16, sequence: u8) -> bool {
+        match self
+            .active_network_keys
+            .iter_mut()
+            .find(|a| a.pan_identifier == pan_identifier)
+        {
+            Some(active) => active.sequence = sequence,
+            None => {
+                let _ = self.active_network_keys.push(ActiveNetworkKey {
+                    pan_identifier,
+                    sequence,
+                });
             }
         }
-        defmt::warn!("No valid key found");
-        0
+        self.network_key(sequence).is_some()
     }
 
-    pub fn add_key_bytes(&mut self, key: [u8; 16]) {
-        let _ = self.keys.push(Key::from(key));
+    /// Add a link key, `address` is the IEEE address of the device using the key if known
+    pub fn add_link_key(&mut self, key: Key, address: Option<ExtendedAddress>) {
+        let entry = LinkKeyEntry { address, key };
+        if !self.link_keys.contains(&entry) {
+            let _ = self.link_keys.push(entry);
+        }
     }
 
-    pub fn add_key(&mut self, key: Key) {
-        let _ = self.keys.push(key);
+    /// Verify the install code of the device with `address` and add the link key derived from it
+    pub fn add_install_code(
+        &mut self,
+        address: ExtendedAddress,
+        code: &[u8],
+    ) -> Result<Key, InstallCodeError> {
+        let key = install_code_key(code)?;
+        self.add_link_key(key, Some(address));
+        Ok(key)
     }
 
     pub fn add_transport_key(&mut self, new_key: &NetworkKey) {
-        for key in self.keys.iter() {
-            if *key == new_key.key {
-                return;
+        self.add_network_key(new_key.key, Some(new_key.sequence));
+    }
+
+    /// Add a MAC layer key, `key_index` is the key index used in the auxiliary security header
+    /// if known
+    pub fn add_mac_key(&mut self, key: Key, key_index: Option<u8>) {
+        let entry = MacKeyEntry { key_index, key };
+        if !self.mac_keys.contains(&entry) {
+            let _ = self.mac_keys.push(entry);
+        }
+    }
+
+    /// Decrypt a MAC payload with the MAC keys, `source` is the IEEE address of the sender.
+    ///
+    /// Keys with the key index of the header are tried before keys without key index. Without
+    /// MIC, security level 4, any key decrypts so the first candidate is used and the attempt is
+    /// not counted in the key statistics. See `mac_security::ccm_star_decrypt` for `open` and
+    /// `private`.
+    pub fn decrypt_mac(
+        &mut self,
+        header: &AuxiliarySecurityHeader,
+        source: ExtendedAddress,
+        open: &[u8],
+        private: &[u8],
+        output: &mut [u8],
+    ) -> Option<usize> {
+        // Without frame counter the nonce is built from the absolute slot number, which is unknown
+        let counter = header.frame_counter?;
+        let nonce = mac_security::nonce(u64::from(source), counter, header.level);
+        for indexed in [true, false] {
+            for index in 0..self.mac_keys.len() {
+                let entry = self.mac_keys[index];
+                let candidate = match entry.key_index {
+                    Some(key_index) => indexed && header.key_index == Some(key_index),
+                    None => !indexed,
+                };
+                if !candidate {
+                    continue;
+                }
+                let key: [u8; BLOCK_SIZE] = entry.key.into();
+                let result = mac_security::ccm_star_decrypt(
+                    &key,
+                    &nonce,
+                    header.mic_length(),
+                    header.is_encrypted(),
+                    open,
+                    private,
+                    output,
+                );
+                if !header.is_authenticated() {
+                    return result;
+                }
+                self.record_attempt(entry.key, result.is_some());
+                if result.is_some() {
+                    return result;
+                }
             }
         }
-        let _ = self.keys.push(new_key.key);
+        None
     }
 }
@@ -0,0 +1,188 @@
+//! Versioned binary snapshot of the learned security state, for storing in flash or in a file.
+//!
+//! The snapshot is encoded with postcard, the first byte is the format version.
+
+use psila_data::{common::key::Key, ExtendedAddress};
+use serde::{Deserialize, Serialize};
+
+use crate::frame_counter::FrameCounterEntry;
+use crate::security::SecurityService;
+
+pub const SNAPSHOT_VERSION: u8 = 1;
+pub const MAX_SNAPSHOT_KEYS: usize = 48;
+pub const MAX_SNAPSHOT_ACTIVE_KEYS: usize = 8;
+pub const MAX_SNAPSHOT_FRAME_COUNTERS: usize = 64;
+/// Upper bound of the encoded size of a snapshot
+pub const MAX_SNAPSHOT_SIZE: usize = 2
+    + MAX_SNAPSHOT_KEYS * 30
+    + 1
+    + MAX_SNAPSHOT_ACTIVE_KEYS * 4
+    + 1
+    + MAX_SNAPSHOT_FRAME_COUNTERS * 17;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Error {
+    /// The snapshot was written by an unsupported version
+    UnsupportedVersion(u8),
+    /// Encoding or decoding failed
+    Encoding,
+}
+
+impl From<postcard::Error> for Error {
+    fn from(_: postcard::Error) -> Self {
+        Error::Encoding
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum KeyType {
+    Network,
+    Link,
+    Mac,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct StoredKey {
+    pub key_type: KeyType,
+    pub key: [u8; 16],
+    /// Key sequence number of network keys, key index of MAC keys
+    pub sequence: Option<u8>,
+    /// IEEE address of the device using a link key
+    pub source: Option<u64>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct StoredActiveKey {
+    pub pan_identifier: u16,
+    pub sequence: u8,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct StoredFrameCounter {
+    pub source: u64,
+    pub sequence: Option<u8>,
+    pub counter: u32,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub version: u8,
+    pub keys: heapless::Vec<StoredKey, MAX_SNAPSHOT_KEYS>,
+    pub active_keys: heapless::Vec<StoredActiveKey, MAX_SNAPSHOT_ACTIVE_KEYS>,
+    pub frame_counters: heapless::Vec<StoredFrameCounter, MAX_SNAPSHOT_FRAME_COUNTERS>,
+}
+
+impl Snapshot {
+    /// Capture the keys, active key sequences and frame counters of the service
+    pub fn new(service: &SecurityService) -> Self {
+        let mut keys = heapless::Vec::new();
+        for entry in service.network_keys.iter() {
+            let _ = keys.push(StoredKey {
+                key_type: KeyType::Network,
+                key: entry.key.into(),
+                sequence: entry.sequence,
+                source: None,
+            });
+        }
+        for entry in service.link_keys.iter() {
+            let _ = keys.push(StoredKey {
+                key_type: KeyType::Link,
+                key: entry.key.into(),
+                sequence: None,
+                source: entry.address.map(u64::from),
+            });
+        }
+        for entry in service.mac_keys.iter() {
+            let _ = keys.push(StoredKey {
+                key_type: KeyType::Mac,
+                key: entry.key.into(),
+                sequence: entry.key_index,
+                source: None,
+            });
+        }
+        let active_keys = service
+            .active_network_keys
+            .iter()
+            .map(|a| StoredActiveKey {
+                pan_identifier: a.pan_identifier,
+                sequence: a.sequence,
+            })
+            .collect();
+        let frame_counters = service
+            .frame_counters
+            .entries
+            .iter()
+            .map(|e| StoredFrameCounter {
+                source: u64::from(e.source),
+                sequence: e.sequence,
+                counter: e.counter,
+            })
+            .collect();
+        Snapshot {
+            version: SNAPSHOT_VERSION,
+            keys,
+            active_keys,
+            frame_counters,
+        }
+    }
+
+    /// Load the snapshot into the service, adding to what the service already holds
+    pub fn restore(&self, service: &mut SecurityService) {
+        for stored in self.keys.iter() {
+            let key = Key::from(stored.key);
+            match stored.key_type {
+                KeyType::Network => service.add_network_key(key, stored.sequence),
+                KeyType::Link => {
+                    service.add_link_key(key, stored.source.map(ExtendedAddress::from))
+                }
+                KeyType::Mac => service.add_mac_key(key, stored.sequence),
+            }
+        }
+        for active in self.active_keys.iter() {
+            service.switch_network_key(active.pan_identifier, active.sequence);
+        }
+        for stored in self.frame_counters.iter() {
+            let source = ExtendedAddress::from(stored.source);
+            let table = &mut service.frame_counters.entries;
+            match table
+                .iter_mut()
+                .find(|e| e.source == source && e.sequence == stored.sequence)
+            {
+                Some(entry) => entry.counter = stored.counter,
+                None => {
+                    let _ = table.push(FrameCounterEntry {
+                        source,
+                        sequence: stored.sequence,
+                        counter: stored.counter,
+                    });
+                }
+            }
+        }
+    }
+
+    /// Encode the snapshot into `buffer`, returns the used part of the buffer
+    pub fn encode<'a>(&self, buffer: &'a mut [u8]) -> Result<&'a mut [u8], Error> {
+        Ok(postcard::to_slice(self, buffer)?)
+    }
+
+    /// Decode a snapshot, trailing bytes such as erased flash are ignored
+    pub fn decode(data: &[u8]) -> Result<Self, Error> {
+        match data.first() {
+            Some(&SNAPSHOT_VERSION) => (),
+            Some(&version) => return Err(Error::UnsupportedVersion(version)),
+            None => return Err(Error::Encoding),
+        }
+        let (snapshot, _) = postcard::take_from_bytes(data)?;
+        Ok(snapshot)
+    }
+}
+
+impl SecurityService {
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot::new(self)
+    }
+
+    pub fn restore(&mut self, snapshot: &Snapshot) {
+        snapshot.restore(self)
+    }
+}
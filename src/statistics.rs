@@ -0,0 +1,263 @@
+//! Counters describing the capture and how well it decoded.
+
+use heapless::LinearMap;
+use ieee802154::mac;
+use psila_data::{
+    application_service::{self, ApplicationServiceHeader},
+    network::{self, NetworkHeader},
+};
+
+use crate::decoded::{DecodeError, DecodedFrame, Payload, RxMetadata};
+use crate::security::KeyStatistics;
+
+pub const MAX_PAN_IDENTIFIERS: usize = 16;
+pub const MAX_CHANNELS: usize = 16;
+
+fn increment<K: Eq, const N: usize>(map: &mut LinearMap<K, u32, N>, key: K) {
+    match map.get_mut(&key) {
+        Some(count) => *count = count.wrapping_add(1),
+        None => {
+            // Keys beyond the capacity are not counted
+            let _ = map.insert(key, 1);
+        }
+    }
+}
+
+pub fn mac_frame_type_name(frame_type: mac::FrameType) -> &'static str {
+    match frame_type {
+        mac::FrameType::Acknowledgement => "Acknowledgement",
+        mac::FrameType::Beacon => "Beacon",
+        mac::FrameType::Data => "Data",
+        mac::FrameType::MacCommand => "Command",
+        mac::FrameType::Multipurpose => "Multipurpose",
+        mac::FrameType::FragOrFragAck => "Fragment",
+        mac::FrameType::Extended => "Extended",
+    }
+}
+
+/// MAC command identifier
+pub fn mac_command_identifier(command: &mac::command::Command) -> u8 {
+    use mac::command::Command;
+    match command {
+        Command::AssociationRequest(_) => 0x01,
+        Command::AssociationResponse(..) => 0x02,
+        Command::DisassociationNotification(_) => 0x03,
+        Command::DataRequest => 0x04,
+        Command::PanIdConflictNotification => 0x05,
+        Command::OrphanNotification => 0x06,
+        Command::BeaconRequest => 0x07,
+        Command::CoordinatorRealignment(_) => 0x08,
+        Command::GuaranteedTimeSlotRequest(_) => 0x09,
+    }
+}
+
+/// Name of a MAC command identifier, including the IEEE 802.15.4-2015 commands
+pub fn mac_command_name(identifier: u8) -> &'static str {
+    match identifier {
+        0x01 => "Association request",
+        0x02 => "Association response",
+        0x03 => "Disassociation notification",
+        0x04 => "Data request",
+        0x05 => "PAN ID conflict notification",
+        0x06 => "Orphan notification",
+        0x07 => "Beacon request",
+        0x08 => "Coordinator realignment",
+        0x09 => "GTS request",
+        0x0a => "TRLE management request",
+        0x0b => "TRLE management response",
+        0x13 => "DSME association request",
+        0x14 => "DSME association response",
+        0x15 => "DSME GTS request",
+        0x16 => "DSME GTS reply",
+        0x17 => "DSME GTS notify",
+        0x18 => "DSME information request",
+        0x19 => "DSME information reply",
+        0x1a => "DSME beacon allocation notification",
+        0x1b => "DSME beacon collision notification",
+        0x1c => "DSME link report",
+        0x20 => "RIT data request",
+        0x21 => "DBS request",
+        0x22 => "DBS response",
+        0x23 => "RIT data response",
+        0x24 => "Vendor specific",
+        _ => "Unknown",
+    }
+}
+
+pub fn network_frame_type_name(header: &NetworkHeader) -> &'static str {
+    match header.control.frame_type {
+        network::header::FrameType::Data => "Data",
+        network::header::FrameType::Command => "Command",
+        network::header::FrameType::InterPan => "Inter-PAN",
+    }
+}
+
+/// NWK command identifier
+pub fn network_command_identifier(command: &network::commands::Command) -> u8 {
+    use network::commands::Command;
+    match command {
+        Command::RouteRequest(_) => 0x01,
+        Command::RouteReply(_) => 0x02,
+        Command::NetworkStatus(_) => 0x03,
+        Command::Leave(_) => 0x04,
+        Command::RouteRecord(_) => 0x05,
+        Command::RejoinRequest(_) => 0x06,
+        Command::RejoinResponse(_) => 0x07,
+        Command::LinkStatus(_) => 0x08,
+        Command::NetworkReport(_) => 0x09,
+        Command::NetworkUpdate(_) => 0x0a,
+        Command::EndDeviceTimeoutRequest(_) => 0x0b,
+        Command::EndDeviceTimeoutResponse(_) => 0x0c,
+    }
+}
+
+pub fn application_service_frame_type_name(header: &ApplicationServiceHeader) -> &'static str {
+    match header.control.frame_type {
+        application_service::header::FrameType::Data => "Data",
+        application_service::header::FrameType::Command => "Command",
+        application_service::header::FrameType::Acknowledgement => "Acknowledgement",
+        application_service::header::FrameType::InterPan => "Inter-PAN",
+    }
+}
+
+/// APS command identifier
+pub fn application_service_command_identifier(command: &application_service::Command) -> u8 {
+    use application_service::Command;
+    match command {
+        Command::SymmetricKeyKeyEstablishment1(_) => 0x01,
+        Command::SymmetricKeyKeyEstablishment2(_) => 0x02,
+        Command::SymmetricKeyKeyEstablishment3(_) => 0x03,
+        Command::SymmetricKeyKeyEstablishment4(_) => 0x04,
+        Command::TransportKey(_) => 0x05,
+        Command::UpdateDevice(_) => 0x06,
+        Command::RemoveDevice(_) => 0x07,
+        Command::RequestKey(_) => 0x08,
+        Command::SwitchKey(_) => 0x09,
+        Command::EntityAuthenticationInitiatorChallenge => 0x0a,
+        Command::EntityAuthenticationResponderChallenge => 0x0b,
+        Command::EntityAuthenticationInitiatorMacAndData => 0x0c,
+        Command::EntityAuthenticationResponderMacAndData => 0x0d,
+        Command::Tunnel(_) => 0x0e,
+        Command::VerifyKey(_) => 0x0f,
+        Command::ConfirmKey(_) => 0x10,
+    }
+}
+
+/// Frame and decoding counters, keyed by type name, command identifier or PAN identifier
+#[derive(Clone, Debug, Default)]
+pub struct Statistics {
+    pub frames: u32,
+    /// Frames that were not valid MAC frames
+    pub malformed: u32,
+    /// Frames skipped due to a bad FCS
+    pub dropped: u32,
+    /// Frames with a bad FCS per channel, channel 0 when the channel is unknown
+    pub bad_fcs: LinearMap<u8, u32, MAX_CHANNELS>,
+    pub mac_frame_types: LinearMap<&'static str, u32, 8>,
+    pub mac_commands: LinearMap<u8, u32, 16>,
+    pub network_frame_types: LinearMap<&'static str, u32, 4>,
+    pub network_commands: LinearMap<u8, u32, 16>,
+    pub application_service_frame_types: LinearMap<&'static str, u32, 4>,
+    pub application_service_commands: LinearMap<u8, u32, 24>,
+    /// Decoding failures per `psila_data::Error` variant, see `error_name`
+    pub decode_errors: LinearMap<&'static str, u32, 24>,
+    pub no_valid_key: u32,
+    pub payload_too_large: u32,
+    /// Frames per destination PAN identifier, or source PAN identifier when there is no
+    /// destination
+    pub pan_identifiers: LinearMap<u16, u32, MAX_PAN_IDENTIFIERS>,
+    /// Decryption attempts per key, filled in by `Parser::statistics`
+    pub keys: heapless::Vec<KeyStatistics, 32>,
+}
+
+impl Statistics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn update_fcs(&mut self, metadata: &RxMetadata) {
+        if metadata.fcs.is_bad() {
+            increment(&mut self.bad_fcs, metadata.channel.unwrap_or(0));
+        }
+    }
+
+    pub fn update(&mut self, frame: &DecodedFrame) {
+        self.frames = self.frames.wrapping_add(1);
+        self.update_fcs(&frame.metadata);
+        increment(
+            &mut self.mac_frame_types,
+            mac_frame_type_name(frame.mac.frame_type),
+        );
+        if let mac::FrameContent::Command(ref command) = frame.content {
+            increment(&mut self.mac_commands, mac_command_identifier(command));
+        }
+        let pan = match (frame.mac.destination, frame.mac.source) {
+            (Some(mac::Address::Short(pan, _)), _)
+            | (Some(mac::Address::Extended(pan, _)), _)
+            | (None, Some(mac::Address::Short(pan, _)))
+            | (None, Some(mac::Address::Extended(pan, _))) => Some(pan.0),
+            (None, None) => None,
+        };
+        if let Some(pan) = pan {
+            increment(&mut self.pan_identifiers, pan);
+        }
+        if let Some(ref header) = frame.network {
+            increment(
+                &mut self.network_frame_types,
+                network_frame_type_name(header),
+            );
+        }
+        if let Some(ref header) = frame.application_service {
+            increment(
+                &mut self.application_service_frame_types,
+                application_service_frame_type_name(header),
+            );
+        }
+        match frame.payload {
+            Payload::MacCommand { identifier, .. } => {
+                increment(&mut self.mac_commands, identifier);
+            }
+            Payload::NetworkCommand(ref command) => {
+                increment(
+                    &mut self.network_commands,
+                    network_command_identifier(command),
+                );
+            }
+            Payload::ApplicationServiceCommand(ref command) => {
+                increment(
+                    &mut self.application_service_commands,
+                    application_service_command_identifier(command),
+                );
+            }
+            _ => (),
+        }
+        match frame.error {
+            Some(DecodeError::NoValidKey) => self.no_valid_key = self.no_valid_key.wrapping_add(1),
+            Some(DecodeError::PayloadTooLarge) => {
+                self.payload_too_large = self.payload_too_large.wrapping_add(1)
+            }
+            Some(ref error) => {
+                if let Some(e) = error.error() {
+                    increment(&mut self.decode_errors, crate::error_name(e));
+                }
+            }
+            None => (),
+        }
+    }
+
+    /// Count a frame that was dropped before decoding
+    pub fn update_dropped(&mut self, metadata: &RxMetadata) {
+        self.dropped = self.dropped.wrapping_add(1);
+        self.update_fcs(metadata);
+    }
+
+    /// Count a frame that could not be parsed as a MAC frame
+    pub fn update_malformed(&mut self, metadata: &RxMetadata) {
+        self.malformed = self.malformed.wrapping_add(1);
+        self.update_fcs(metadata);
+    }
+
+    pub fn clear(&mut self) {
+        *self = Self::default();
+    }
+}
@@ -0,0 +1,245 @@
+//! Passive channel survey, listens on each channel in turn and summarises what is heard.
+
+use byte::BytesExt;
+use ieee802154::mac::{self, FooterMode};
+
+use crate::beacon::BeaconPayload;
+use crate::decoded::RxMetadata;
+use crate::radio::{Radio, FIRST_CHANNEL, LAST_CHANNEL};
+
+pub const CHANNEL_COUNT: usize = (LAST_CHANNEL - FIRST_CHANNEL + 1) as usize;
+pub const MAX_NETWORKS_PER_CHANNEL: usize = 8;
+/// Dwell time per channel by default, in microseconds
+pub const DEFAULT_DWELL_TIME: u64 = 2_000_000;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SurveyConfig {
+    /// Channels to survey, bit n for channel n
+    pub channels: u32,
+    /// Time spent on each channel, in microseconds
+    pub dwell_time: u64,
+    /// Time spent on a specific channel, overrides `dwell_time`
+    pub channel_dwell_time: [Option<u64>; CHANNEL_COUNT],
+    /// Number of passes over the channels
+    pub rounds: u32,
+}
+
+impl SurveyConfig {
+    pub fn dwell_time(&self, channel: u8) -> u64 {
+        self.channel_dwell_time
+            .get(usize::from(channel.wrapping_sub(FIRST_CHANNEL)))
+            .copied()
+            .flatten()
+            .unwrap_or(self.dwell_time)
+    }
+
+    fn includes(&self, channel: u8) -> bool {
+        self.channels & (1 << channel) != 0
+    }
+}
+
+impl Default for SurveyConfig {
+    fn default() -> Self {
+        SurveyConfig {
+            // Channels 11 to 26
+            channels: 0x07ff_f800,
+            dwell_time: DEFAULT_DWELL_TIME,
+            channel_dwell_time: [None; CHANNEL_COUNT],
+            rounds: 1,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ChannelSummary {
+    pub channel: u8,
+    /// Time spent listening, in microseconds
+    pub listened: u64,
+    pub frames: u32,
+    /// Frames that could not be parsed as MAC frames
+    pub malformed: u32,
+    /// Frames where the FCS did not match, these are not inspected further
+    pub bad_fcs: u32,
+    pub bytes: u32,
+    pub beacons: u32,
+    pub pan_identifiers: heapless::Vec<u16, MAX_NETWORKS_PER_CHANNEL>,
+    pub extended_pan_identifiers: heapless::Vec<u64, MAX_NETWORKS_PER_CHANNEL>,
+    /// Strongest signal heard, in dBm
+    pub strongest_rssi: Option<i8>,
+}
+
+impl ChannelSummary {
+    fn add_pan_identifier(&mut self, pan: u16) {
+        if pan != 0xffff && !self.pan_identifiers.contains(&pan) {
+            let _ = self.pan_identifiers.push(pan);
+        }
+    }
+
+    fn add_frame(&mut self, data: &[u8], metadata: &RxMetadata) {
+        self.frames = self.frames.wrapping_add(1);
+        self.bytes = self.bytes.wrapping_add(data.len() as u32);
+        if metadata.fcs.is_bad() {
+            self.bad_fcs = self.bad_fcs.wrapping_add(1);
+            return;
+        }
+        if let Some(rssi) = metadata.rssi {
+            if self
+                .strongest_rssi
+                .map_or(true, |strongest| rssi > strongest)
+            {
+                self.strongest_rssi = Some(rssi);
+            }
+        }
+        let frame = match data.read_with::<mac::Frame>(&mut 0, FooterMode::None) {
+            Ok(frame) => frame,
+            Err(_) => {
+                self.malformed = self.malformed.wrapping_add(1);
+                return;
+            }
+        };
+        for address in [frame.header.destination, frame.header.source] {
+            match address {
+                Some(mac::Address::Short(pan, _)) | Some(mac::Address::Extended(pan, _)) => {
+                    self.add_pan_identifier(pan.0)
+                }
+                None => (),
+            }
+        }
+        if let mac::FrameContent::Beacon(_) = frame.content {
+            self.beacons = self.beacons.wrapping_add(1);
+            if let Ok(beacon) = BeaconPayload::unpack(frame.payload) {
+                let extended = beacon.extended_pan_identifier;
+                if !self.extended_pan_identifiers.contains(&extended) {
+                    let _ = self.extended_pan_identifiers.push(extended);
+                }
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SurveySummary {
+    pub channels: heapless::Vec<ChannelSummary, CHANNEL_COUNT>,
+}
+
+impl SurveySummary {
+    pub fn channel(&self, channel: u8) -> Option<&ChannelSummary> {
+        self.channels.iter().find(|c| c.channel == channel)
+    }
+
+    /// The channel with the most traffic
+    pub fn busiest(&self) -> Option<&ChannelSummary> {
+        self.channels.iter().max_by_key(|c| c.frames)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SurveyState {
+    Listening,
+    Done,
+}
+
+/// Channel hopping survey driven by polling
+pub struct Survey {
+    config: SurveyConfig,
+    summary: SurveySummary,
+    channel: Option<u8>,
+    round: u32,
+    started: u64,
+}
+
+impl Survey {
+    pub fn new(config: SurveyConfig) -> Self {
+        let mut summary = SurveySummary::default();
+        for channel in FIRST_CHANNEL..=LAST_CHANNEL {
+            if config.includes(channel) {
+                let _ = summary.channels.push(ChannelSummary {
+                    channel,
+                    ..ChannelSummary::default()
+                });
+            }
+        }
+        Survey {
+            config,
+            summary,
+            channel: None,
+            round: 0,
+            started: 0,
+        }
+    }
+
+    pub fn summary(&self) -> &SurveySummary {
+        &self.summary
+    }
+
+    /// The channel currently listened to
+    pub fn channel(&self) -> Option<u8> {
+        self.channel
+    }
+
+    fn next_channel(&self) -> Option<u8> {
+        let first = self.channel.map_or(FIRST_CHANNEL, |c| c + 1);
+        (first..=LAST_CHANNEL).find(|c| self.config.includes(*c))
+    }
+
+    fn switch<R: Radio>(&mut self, radio: &mut R) -> Result<SurveyState, R::Error> {
+        let next = match self.next_channel() {
+            Some(channel) => Some(channel),
+            None => {
+                self.round += 1;
+                self.channel = None;
+                if self.round >= self.config.rounds {
+                    return Ok(SurveyState::Done);
+                }
+                self.next_channel()
+            }
+        };
+        let Some(channel) = next else {
+            return Ok(SurveyState::Done);
+        };
+        radio.set_channel(channel)?;
+        self.channel = Some(channel);
+        self.started = radio.now();
+        Ok(SurveyState::Listening)
+    }
+
+    /// Handle a received frame and change channel when the dwell time has passed
+    pub fn poll<R: Radio>(&mut self, radio: &mut R) -> Result<SurveyState, R::Error> {
+        let Some(channel) = self.channel else {
+            if self.round >= self.config.rounds {
+                return Ok(SurveyState::Done);
+            }
+            return self.switch(radio);
+        };
+        if let Some(received) = radio.receive()? {
+            if let Some(summary) = self
+                .summary
+                .channels
+                .iter_mut()
+                .find(|c| c.channel == channel)
+            {
+                summary.add_frame(&received.data, &received.metadata);
+            }
+        }
+        let now = radio.now();
+        let elapsed = now.saturating_sub(self.started);
+        if elapsed >= self.config.dwell_time(channel) {
+            if let Some(summary) = self
+                .summary
+                .channels
+                .iter_mut()
+                .find(|c| c.channel == channel)
+            {
+                summary.listened = summary.listened.saturating_add(elapsed);
+            }
+            return self.switch(radio);
+        }
+        Ok(SurveyState::Listening)
+    }
+
+    /// Poll until all rounds are done
+    pub fn run<R: Radio>(&mut self, radio: &mut R) -> Result<&SurveySummary, R::Error> {
+        while self.poll(radio)? == SurveyState::Listening {}
+        Ok(&self.summary)
+    }
+}
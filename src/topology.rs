@@ -0,0 +1,344 @@
+//! Network topology reconstructed from routing information in sniffed traffic.
+//!
+//! Links are learned from NWK Link Status and Route Record commands, source route subframes and ZDO
+//! Mgmt_Lqi responses. Route Reply commands only add the originator and responder as nodes. The
+//! model can be exported as Graphviz DOT or JSON.
+
+use core::fmt::{self, Write};
+
+use ieee802154::mac;
+use psila_data::network::{self, NetworkHeader};
+
+use crate::decoded::{DecodedFrame, Payload};
+use crate::zdo;
+
+pub const MAX_NODES: usize = 64;
+pub const MAX_LINKS: usize = 256;
+
+/// Addresses 0xfff8 to 0xffff are broadcast addresses
+fn is_unicast(address: u16) -> bool {
+    address < 0xfff8
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LinkSource {
+    /// Link Status command, with link costs
+    LinkStatus,
+    /// Hop of a route, from Route Record or source routing
+    Route,
+    /// ZDO Mgmt_Lqi response, with link quality
+    NeighborTable,
+}
+
+impl LinkSource {
+    fn name(&self) -> &'static str {
+        match self {
+            LinkSource::LinkStatus => "link-status",
+            LinkSource::Route => "route",
+            LinkSource::NeighborTable => "neighbor-table",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Node {
+    pub address: u16,
+    pub extended_address: Option<u64>,
+    pub last_update: u64,
+}
+
+/// Directed link between two nodes
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Link {
+    pub from: u16,
+    pub to: u16,
+    /// Link cost, 1 to 7, as reported in Link Status
+    pub cost: Option<u8>,
+    /// Link quality as reported in the neighbor table
+    pub link_quality: Option<u8>,
+    pub source: LinkSource,
+    pub last_update: u64,
+}
+
+pub struct Topology {
+    pub nodes: heapless::Vec<Node, MAX_NODES>,
+    pub links: heapless::Vec<Link, MAX_LINKS>,
+}
+
+impl Topology {
+    pub fn new() -> Self {
+        Topology {
+            nodes: heapless::Vec::new(),
+            links: heapless::Vec::new(),
+        }
+    }
+
+    pub fn node(&self, address: u16) -> Option<&Node> {
+        self.nodes.iter().find(|n| n.address == address)
+    }
+
+    fn update_node(&mut self, address: u16, extended_address: Option<u64>, timestamp: u64) {
+        if !is_unicast(address) {
+            return;
+        }
+        match self.nodes.iter_mut().find(|n| n.address == address) {
+            Some(node) => {
+                if extended_address.is_some() {
+                    node.extended_address = extended_address;
+                }
+                node.last_update = timestamp;
+            }
+            None => {
+                let _ = self.nodes.push(Node {
+                    address,
+                    extended_address,
+                    last_update: timestamp,
+                });
+            }
+        }
+    }
+
+    fn update_link(&mut self, link: Link) {
+        if !is_unicast(link.from) || !is_unicast(link.to) || link.from == link.to {
+            return;
+        }
+        self.update_node(link.from, None, link.last_update);
+        self.update_node(link.to, None, link.last_update);
+        match self
+            .links
+            .iter_mut()
+            .find(|l| l.from == link.from && l.to == link.to)
+        {
+            Some(existing) => {
+                // Route hops only confirm the link, keep costs learned from other sources
+                if link.source != LinkSource::Route {
+                    existing.source = link.source;
+                }
+                existing.cost = link.cost.or(existing.cost);
+                existing.link_quality = link.link_quality.or(existing.link_quality);
+                existing.last_update = link.last_update;
+            }
+            None => {
+                let _ = self.links.push(link);
+            }
+        }
+    }
+
+    fn update_path<I>(&mut self, path: I, timestamp: u64)
+    where
+        I: Iterator<Item = u16>,
+    {
+        let mut previous = None;
+        for address in path {
+            if let Some(from) = previous {
+                self.update_link(Link {
+                    from,
+                    to: address,
+                    cost: None,
+                    link_quality: None,
+                    source: LinkSource::Route,
+                    last_update: timestamp,
+                });
+            }
+            previous = Some(address);
+        }
+    }
+
+    /// `final_hop` tells if the frame is sent to the NWK destination by the MAC layer
+    fn update_network_command(
+        &mut self,
+        header: &NetworkHeader,
+        command: &network::commands::Command,
+        final_hop: bool,
+        timestamp: u64,
+    ) {
+        use network::commands::Command;
+        let source = u16::from(header.source_address);
+        let destination = u16::from(header.destination_address);
+        match command {
+            Command::LinkStatus(link_status) => {
+                for entry in link_status.entries() {
+                    let neighbor = u16::from(entry.address);
+                    self.update_link(Link {
+                        from: source,
+                        to: neighbor,
+                        cost: Some(entry.outgoing_cost),
+                        link_quality: None,
+                        source: LinkSource::LinkStatus,
+                        last_update: timestamp,
+                    });
+                    self.update_link(Link {
+                        from: neighbor,
+                        to: source,
+                        cost: Some(entry.incoming_cost),
+                        link_quality: None,
+                        source: LinkSource::LinkStatus,
+                        last_update: timestamp,
+                    });
+                }
+            }
+            Command::RouteRecord(route_record) => {
+                // Relays add themselves in order from the originator towards the concentrator,
+                // the path only reaches the concentrator on the last hop
+                let relays = route_record.entries().iter().map(|a| u16::from(*a));
+                let concentrator = final_hop.then_some(destination);
+                self.update_path(
+                    core::iter::once(source).chain(relays).chain(concentrator),
+                    timestamp,
+                );
+            }
+            Command::RouteReply(route_reply) => {
+                self.update_node(
+                    u16::from(route_reply.orginator_address),
+                    route_reply.orginator_ieee_address.map(u64::from),
+                    timestamp,
+                );
+                self.update_node(
+                    u16::from(route_reply.responder_address),
+                    route_reply.responder_ieee_address.map(u64::from),
+                    timestamp,
+                );
+            }
+            _ => (),
+        }
+    }
+
+    fn update_neighbor_table(&mut self, source: u16, frame: &zdo::Frame, timestamp: u64) {
+        let Ok(zdo::Message::ManagementLqiResponse(table)) = frame.message() else {
+            return;
+        };
+        for entry in table.entries {
+            let Ok(zdo::Entry::Neighbor(neighbor)) = entry else {
+                continue;
+            };
+            self.update_node(
+                neighbor.network_address,
+                Some(neighbor.extended_address),
+                timestamp,
+            );
+            self.update_link(Link {
+                from: neighbor.network_address,
+                to: source,
+                cost: None,
+                link_quality: Some(neighbor.link_quality),
+                source: LinkSource::NeighborTable,
+                last_update: timestamp,
+            });
+        }
+    }
+
+    /// Update the model with a decoded frame
+    pub fn update(&mut self, frame: &DecodedFrame) {
+        let timestamp = frame.metadata.timestamp;
+        let Some(ref header) = frame.network else {
+            return;
+        };
+        let source = u16::from(header.source_address);
+        let destination = u16::from(header.destination_address);
+        self.update_node(source, header.source_ieee_address.map(u64::from), timestamp);
+        if let Some(ref source_route) = header.source_route_frame {
+            // The relay list is ordered from the destination side
+            let relays = source_route.entries().iter().rev().map(|a| u16::from(*a));
+            self.update_path(
+                core::iter::once(source)
+                    .chain(relays)
+                    .chain(core::iter::once(destination)),
+                timestamp,
+            );
+        }
+        match frame.payload {
+            Payload::NetworkCommand(ref command) => {
+                let final_hop = matches!(
+                    frame.mac.destination,
+                    Some(mac::Address::Short(_, address)) if address.0 == destination
+                );
+                self.update_network_command(header, command, final_hop, timestamp)
+            }
+            Payload::DeviceObject(ref zdo_frame) => {
+                self.update_neighbor_table(source, zdo_frame, timestamp)
+            }
+            _ => (),
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.nodes.clear();
+        self.links.clear();
+    }
+
+    /// Write the topology as a Graphviz DOT digraph
+    pub fn write_dot<W: Write>(&self, writer: &mut W) -> fmt::Result {
+        writeln!(writer, "digraph zigbee {{")?;
+        for node in self.nodes.iter() {
+            write!(
+                writer,
+                "  \"{:04x}\" [label=\"{:04x}",
+                node.address, node.address
+            )?;
+            if let Some(extended) = node.extended_address {
+                write!(writer, "\\n{:016x}", extended)?;
+            }
+            writeln!(writer, "\"];")?;
+        }
+        for link in self.links.iter() {
+            write!(writer, "  \"{:04x}\" -> \"{:04x}\" [", link.from, link.to)?;
+            match (link.cost, link.link_quality) {
+                (Some(cost), _) => write!(writer, "label=\"{}\"", cost)?,
+                (None, Some(lqi)) => write!(writer, "label=\"LQI {}\"", lqi)?,
+                (None, None) => write!(writer, "style=dashed")?,
+            }
+            writeln!(writer, "];")?;
+        }
+        writeln!(writer, "}}")
+    }
+
+    /// Write the topology as JSON, `{"nodes": [...], "links": [...]}`
+    pub fn write_json<W: Write>(&self, writer: &mut W) -> fmt::Result {
+        write!(writer, "{{\"nodes\":[")?;
+        for (index, node) in self.nodes.iter().enumerate() {
+            if index > 0 {
+                write!(writer, ",")?;
+            }
+            write!(
+                writer,
+                "{{\"address\":\"{:04x}\",\"extended_address\":",
+                node.address
+            )?;
+            match node.extended_address {
+                Some(extended) => write!(writer, "\"{:016x}\"", extended)?,
+                None => write!(writer, "null")?,
+            }
+            write!(writer, ",\"last_update\":{}}}", node.last_update)?;
+        }
+        write!(writer, "],\"links\":[")?;
+        for (index, link) in self.links.iter().enumerate() {
+            if index > 0 {
+                write!(writer, ",")?;
+            }
+            write!(
+                writer,
+                "{{\"from\":\"{:04x}\",\"to\":\"{:04x}\",\"source\":\"{}\",\"cost\":",
+                link.from,
+                link.to,
+                link.source.name()
+            )?;
+            match link.cost {
+                Some(cost) => write!(writer, "{}", cost)?,
+                None => write!(writer, "null")?,
+            }
+            write!(writer, ",\"link_quality\":")?;
+            match link.link_quality {
+                Some(lqi) => write!(writer, "{}", lqi)?,
+                None => write!(writer, "null")?,
+            }
+            write!(writer, ",\"last_update\":{}}}", link.last_update)?;
+        }
+        writeln!(writer, "]}}")
+    }
+}
+
+impl Default for Topology {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,982 @@
+//! Zigbee Cluster Library (ZCL) frame decoding
+//!
+//! The header is decoded when the frame is parsed, the command payload is decoded on demand with
+//! [`Frame::command`] since attribute lists borrow from the payload.
+
+use psila_data::Error;
+
+use crate::decoded::PayloadBuffer;
+use crate::reader::ByteReader;
+
+pub const CLUSTER_BASIC: u16 = 0x0000;
+pub const CLUSTER_POWER_CONFIGURATION: u16 = 0x0001;
+pub const CLUSTER_IDENTIFY: u16 = 0x0003;
+pub const CLUSTER_GROUPS: u16 = 0x0004;
+pub const CLUSTER_SCENES: u16 = 0x0005;
+pub const CLUSTER_ON_OFF: u16 = 0x0006;
+pub const CLUSTER_LEVEL_CONTROL: u16 = 0x0008;
+pub const CLUSTER_OTA_UPGRADE: u16 = 0x0019;
+pub const CLUSTER_POLL_CONTROL: u16 = 0x0020;
+pub const CLUSTER_COLOR_CONTROL: u16 = 0x0300;
+pub const CLUSTER_TEMPERATURE_MEASUREMENT: u16 = 0x0402;
+pub const CLUSTER_RELATIVE_HUMIDITY: u16 = 0x0405;
+pub const CLUSTER_OCCUPANCY_SENSING: u16 = 0x0406;
+pub const CLUSTER_IAS_ZONE: u16 = 0x0500;
+pub const CLUSTER_METERING: u16 = 0x0702;
+pub const CLUSTER_ELECTRICAL_MEASUREMENT: u16 = 0x0b04;
+pub const CLUSTER_TOUCHLINK: u16 = 0x1000;
+
+pub fn cluster_name(cluster: u16) -> &'static str {
+    match cluster {
+        CLUSTER_BASIC => "Basic",
+        CLUSTER_POWER_CONFIGURATION => "Power Configuration",
+        0x0002 => "Device Temperature",
+        CLUSTER_IDENTIFY => "Identify",
+        CLUSTER_GROUPS => "Groups",
+        CLUSTER_SCENES => "Scenes",
+        CLUSTER_ON_OFF => "On/Off",
+        0x0007 => "On/Off Switch Configuration",
+        CLUSTER_LEVEL_CONTROL => "Level Control",
+        0x0009 => "Alarms",
+        0x000a => "Time",
+        0x000f => "Binary Input",
+        CLUSTER_OTA_UPGRADE => "OTA Upgrade",
+        CLUSTER_POLL_CONTROL => "Poll Control",
+        0x0021 => "Green Power",
+        0x0101 => "Door Lock",
+        0x0102 => "Window Covering",
+        0x0201 => "Thermostat",
+        0x0202 => "Fan Control",
+        CLUSTER_COLOR_CONTROL => "Color Control",
+        0x0400 => "Illuminance Measurement",
+        CLUSTER_TEMPERATURE_MEASUREMENT => "Temperature Measurement",
+        0x0403 => "Pressure Measurement",
+        CLUSTER_RELATIVE_HUMIDITY => "Relative Humidity",
+        CLUSTER_OCCUPANCY_SENSING => "Occupancy Sensing",
+        CLUSTER_IAS_ZONE => "IAS Zone",
+        0x0501 => "IAS ACE",
+        0x0502 => "IAS WD",
+        CLUSTER_METERING => "Metering",
+        CLUSTER_ELECTRICAL_MEASUREMENT => "Electrical Measurement",
+        0x0b05 => "Diagnostics",
+        CLUSTER_TOUCHLINK => "Touchlink",
+        _ => "Unknown",
+    }
+}
+
+pub fn status_name(status: u8) -> &'static str {
+    match status {
+        0x00 => "Success",
+        0x01 => "Failure",
+        0x7e => "Not authorized",
+        0x80 => "Malformed command",
+        0x81 => "Unsupported cluster command",
+        0x82 => "Unsupported general command",
+        0x83 => "Unsupported manufacturer cluster command",
+        0x84 => "Unsupported manufacturer general command",
+        0x85 => "Invalid field",
+        0x86 => "Unsupported attribute",
+        0x87 => "Invalid value",
+        0x88 => "Read only",
+        0x89 => "Insufficient space",
+        0x8a => "Duplicate exists",
+        0x8b => "Not found",
+        0x8c => "Unreportable attribute",
+        0x8d => "Invalid data type",
+        0x8e => "Invalid selector",
+        0x8f => "Write only",
+        0x90 => "Inconsistent startup state",
+        0x91 => "Defined out of band",
+        0x92 => "Inconsistent",
+        0x93 => "Action denied",
+        0x94 => "Timeout",
+        0x95 => "Abort",
+        0x96 => "Invalid image",
+        0x97 => "Wait for data",
+        0x98 => "No image available",
+        0x99 => "Require more image",
+        0x9a => "Notification pending",
+        0xc0 => "Hardware failure",
+        0xc1 => "Software failure",
+        0xc2 => "Calibration error",
+        0xc3 => "Unsupported cluster",
+        _ => "Unknown",
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FrameType {
+    Global,
+    ClusterSpecific,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Direction {
+    ClientToServer,
+    ServerToClient,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Header {
+    pub frame_type: FrameType,
+    pub direction: Direction,
+    pub disable_default_response: bool,
+    pub manufacturer_code: Option<u16>,
+    pub transaction_sequence: u8,
+    pub command: u8,
+}
+
+impl Header {
+    pub fn unpack(data: &[u8]) -> Result<(Self, usize), Error> {
+        let mut reader = ByteReader::new(data);
+        let control = reader.u8()?;
+        let frame_type = match control & 0x03 {
+            0 => FrameType::Global,
+            1 => FrameType::ClusterSpecific,
+            _ => return Err(Error::UnknownFrameType),
+        };
+        let manufacturer_code = if control & 0x04 == 0x04 {
+            Some(reader.u16()?)
+        } else {
+            None
+        };
+        let direction = if control & 0x08 == 0x08 {
+            Direction::ServerToClient
+        } else {
+            Direction::ClientToServer
+        };
+        let header = Header {
+            frame_type,
+            direction,
+            disable_default_response: control & 0x10 == 0x10,
+            manufacturer_code,
+            transaction_sequence: reader.u8()?,
+            command: reader.u8()?,
+        };
+        Ok((header, reader.offset()))
+    }
+}
+
+/// A ZCL frame together with the profile and cluster from the APS header
+#[derive(Clone, Debug)]
+pub struct Frame {
+    pub profile: u16,
+    pub cluster: u16,
+    pub header: Header,
+    pub payload: PayloadBuffer,
+}
+
+impl Frame {
+    pub fn unpack(profile: u16, cluster: u16, data: &[u8]) -> Result<Self, Error> {
+        let (header, used) = Header::unpack(data)?;
+        let payload =
+            PayloadBuffer::from_slice(&data[used..]).map_err(|_| Error::NotEnoughSpace)?;
+        Ok(Frame {
+            profile,
+            cluster,
+            header,
+            payload,
+        })
+    }
+
+    pub fn command(&self) -> Result<Command<'_>, Error> {
+        match self.header.frame_type {
+            FrameType::Global => Command::unpack_global(self.header.command, &self.payload),
+            FrameType::ClusterSpecific => {
+                if self.header.manufacturer_code.is_some() {
+                    return Ok(Command::Other(&self.payload));
+                }
+                ClusterCommand::unpack(
+                    self.cluster,
+                    self.header.direction,
+                    self.header.command,
+                    &self.payload,
+                )
+                .map(Command::Cluster)
+            }
+        }
+    }
+}
+
+pub mod data_type {
+    pub const NO_DATA: u8 = 0x00;
+    pub const DATA8: u8 = 0x08;
+    pub const DATA64: u8 = 0x0f;
+    pub const BOOLEAN: u8 = 0x10;
+    pub const BITMAP8: u8 = 0x18;
+    pub const BITMAP64: u8 = 0x1f;
+    pub const UINT8: u8 = 0x20;
+    pub const UINT64: u8 = 0x27;
+    pub const INT8: u8 = 0x28;
+    pub const INT64: u8 = 0x2f;
+    pub const ENUM8: u8 = 0x30;
+    pub const ENUM16: u8 = 0x31;
+    pub const FLOAT_SEMI: u8 = 0x38;
+    pub const FLOAT_SINGLE: u8 = 0x39;
+    pub const FLOAT_DOUBLE: u8 = 0x3a;
+    pub const OCTET_STRING: u8 = 0x41;
+    pub const CHARACTER_STRING: u8 = 0x42;
+    pub const LONG_OCTET_STRING: u8 = 0x43;
+    pub const LONG_CHARACTER_STRING: u8 = 0x44;
+    pub const ARRAY: u8 = 0x48;
+    pub const STRUCTURE: u8 = 0x4c;
+    pub const SET: u8 = 0x50;
+    pub const BAG: u8 = 0x51;
+    pub const TIME_OF_DAY: u8 = 0xe0;
+    pub const DATE: u8 = 0xe1;
+    pub const UTC_TIME: u8 = 0xe2;
+    pub const CLUSTER_ID: u8 = 0xe8;
+    pub const ATTRIBUTE_ID: u8 = 0xe9;
+    pub const BACNET_OID: u8 = 0xea;
+    pub const IEEE_ADDRESS: u8 = 0xf0;
+    pub const SECURITY_KEY: u8 = 0xf1;
+
+    /// Analog data types have a reportable change field in reporting configurations
+    pub fn is_analog(data_type: u8) -> bool {
+        matches!(data_type, UINT8..=INT64 | FLOAT_SEMI..=FLOAT_DOUBLE | TIME_OF_DAY..=UTC_TIME)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AttributeValue<'a> {
+    NoData,
+    Data(&'a [u8]),
+    Boolean(Option<bool>),
+    Bitmap(u64),
+    Unsigned(u64),
+    Signed(i64),
+    Enumeration(u16),
+    Float(f64),
+    OctetString(&'a [u8]),
+    CharacterString(&'a [u8]),
+    /// Arrays, structures, sets and bags are kept as raw elements
+    Collection(u8, &'a [u8]),
+    TimeOfDay {
+        hours: u8,
+        minutes: u8,
+        seconds: u8,
+        hundredths: u8,
+    },
+    Date {
+        year: u8,
+        month: u8,
+        day: u8,
+        weekday: u8,
+    },
+    UtcTime(u32),
+    ClusterId(u16),
+    AttributeId(u16),
+    BacnetOid(u32),
+    IeeeAddress(u64),
+    SecurityKey(&'a [u8]),
+}
+
+fn half_to_f64(value: u16) -> f64 {
+    let sign = if value & 0x8000 == 0x8000 { -1.0 } else { 1.0 };
+    let exponent = i32::from((value >> 10) & 0x1f);
+    let mantissa = f64::from(value & 0x03ff);
+    let magnitude = match exponent {
+        0 => mantissa * (1.0 / (1u32 << 24) as f64),
+        0x1f => f64::INFINITY,
+        _ => {
+            let value = 1.0 + mantissa / 1024.0;
+            if exponent >= 15 {
+                value * (1u32 << (exponent - 15)) as f64
+            } else {
+                value / (1u32 << (15 - exponent)) as f64
+            }
+        }
+    };
+    sign * magnitude
+}
+
+impl<'a> AttributeValue<'a> {
+    fn read(data_type: u8, reader: &mut ByteReader<'a>) -> Result<Self, Error> {
+        use self::data_type::*;
+        let value = match data_type {
+            NO_DATA => AttributeValue::NoData,
+            DATA8..=DATA64 => {
+                AttributeValue::Data(reader.bytes(usize::from(data_type - DATA8) + 1)?)
+            }
+            BOOLEAN => AttributeValue::Boolean(match reader.u8()? {
+                0 => Some(false),
+                1 => Some(true),
+                _ => None,
+            }),
+            BITMAP8..=BITMAP64 => {
+                AttributeValue::Bitmap(reader.uint(usize::from(data_type - BITMAP8) + 1)?)
+            }
+            UINT8..=UINT64 => {
+                AttributeValue::Unsigned(reader.uint(usize::from(data_type - UINT8) + 1)?)
+            }
+            INT8..=INT64 => {
+                let size = usize::from(data_type - INT8) + 1;
+                let value = reader.uint(size)?;
+                let shift = 64 - size * 8;
+                AttributeValue::Signed(((value << shift) as i64) >> shift)
+            }
+            ENUM8 => AttributeValue::Enumeration(u16::from(reader.u8()?)),
+            ENUM16 => AttributeValue::Enumeration(reader.u16()?),
+            FLOAT_SEMI => AttributeValue::Float(half_to_f64(reader.u16()?)),
+            FLOAT_SINGLE => AttributeValue::Float(f64::from(f32::from_bits(reader.u32()?))),
+            FLOAT_DOUBLE => AttributeValue::Float(f64::from_bits(reader.u64()?)),
+            OCTET_STRING | CHARACTER_STRING => {
+                let length = reader.u8()?;
+                // 0xff marks an invalid string
+                let length = if length == 0xff {
+                    0
+                } else {
+                    usize::from(length)
+                };
+                let value = reader.bytes(length)?;
+                if data_type == OCTET_STRING {
+                    AttributeValue::OctetString(value)
+                } else {
+                    AttributeValue::CharacterString(value)
+                }
+            }
+            LONG_OCTET_STRING | LONG_CHARACTER_STRING => {
+                let length = reader.u16()?;
+                let length = if length == 0xffff {
+                    0
+                } else {
+                    usize::from(length)
+                };
+                let value = reader.bytes(length)?;
+                if data_type == LONG_OCTET_STRING {
+                    AttributeValue::OctetString(value)
+                } else {
+                    AttributeValue::CharacterString(value)
+                }
+            }
+            ARRAY | SET | BAG => {
+                let element_type = reader.u8()?;
+                let count = reader.u16()?;
+                let start = reader.remaining();
+                let before = reader.offset();
+                if count != 0xffff {
+                    for _ in 0..count {
+                        let _ = Self::read(element_type, reader)?;
+                    }
+                }
+                AttributeValue::Collection(data_type, &start[..reader.offset() - before])
+            }
+            STRUCTURE => {
+                let count = reader.u16()?;
+                let start = reader.remaining();
+                let before = reader.offset();
+                if count != 0xffff {
+                    for _ in 0..count {
+                        let element_type = reader.u8()?;
+                        let _ = Self::read(element_type, reader)?;
+                    }
+                }
+                AttributeValue::Collection(data_type, &start[..reader.offset() - before])
+            }
+            TIME_OF_DAY => AttributeValue::TimeOfDay {
+                hours: reader.u8()?,
+                minutes: reader.u8()?,
+                seconds: reader.u8()?,
+                hundredths: reader.u8()?,
+            },
+            DATE => AttributeValue::Date {
+                year: reader.u8()?,
+                month: reader.u8()?,
+                day: reader.u8()?,
+                weekday: reader.u8()?,
+            },
+            UTC_TIME => AttributeValue::UtcTime(reader.u32()?),
+            CLUSTER_ID => AttributeValue::ClusterId(reader.u16()?),
+            ATTRIBUTE_ID => AttributeValue::AttributeId(reader.u16()?),
+            BACNET_OID => AttributeValue::BacnetOid(reader.u32()?),
+            IEEE_ADDRESS => AttributeValue::IeeeAddress(reader.u64()?),
+            SECURITY_KEY => AttributeValue::SecurityKey(reader.bytes(16)?),
+            _ => return Err(Error::UnsupportedAttributeValue),
+        };
+        Ok(value)
+    }
+}
+
+/// Attribute identifier and value
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Attribute<'a> {
+    pub identifier: u16,
+    pub data_type: u8,
+    pub value: AttributeValue<'a>,
+}
+
+/// Attribute identifier with status and the value when the status is success
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AttributeStatus<'a> {
+    pub identifier: u16,
+    pub status: u8,
+    pub attribute: Option<Attribute<'a>>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ReportingConfiguration<'a> {
+    Send {
+        identifier: u16,
+        data_type: u8,
+        minimum_interval: u16,
+        maximum_interval: u16,
+        reportable_change: Option<AttributeValue<'a>>,
+    },
+    Receive {
+        identifier: u16,
+        timeout: u16,
+    },
+}
+
+impl<'a> ReportingConfiguration<'a> {
+    fn read(reader: &mut ByteReader<'a>) -> Result<Self, Error> {
+        let direction = reader.u8()?;
+        let identifier = reader.u16()?;
+        Self::read_body(direction, identifier, reader)
+    }
+
+    fn read_body(
+        direction: u8,
+        identifier: u16,
+        reader: &mut ByteReader<'a>,
+    ) -> Result<Self, Error> {
+        if direction == 0 {
+            let data_type = reader.u8()?;
+            let minimum_interval = reader.u16()?;
+            let maximum_interval = reader.u16()?;
+            let reportable_change = if data_type::is_analog(data_type) {
+                Some(AttributeValue::read(data_type, reader)?)
+            } else {
+                None
+            };
+            Ok(ReportingConfiguration::Send {
+                identifier,
+                data_type,
+                minimum_interval,
+                maximum_interval,
+                reportable_change,
+            })
+        } else {
+            Ok(ReportingConfiguration::Receive {
+                identifier,
+                timeout: reader.u16()?,
+            })
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ReportingConfigurationStatus<'a> {
+    pub status: u8,
+    pub direction: u8,
+    pub identifier: u16,
+    pub configuration: Option<ReportingConfiguration<'a>>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum RecordKind {
+    Identifier,
+    Attribute,
+    ReadStatus,
+    WriteStatus,
+    Reporting,
+    ReportingStatus,
+    ReadReporting,
+    ReadReportingStatus,
+    Discovered,
+}
+
+/// A list of records in a foundation command, decoded while iterating
+#[derive(Clone, Debug)]
+pub struct Records<'a> {
+    kind: RecordKind,
+    reader: ByteReader<'a>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Record<'a> {
+    Identifier(u16),
+    Attribute(Attribute<'a>),
+    Status(AttributeStatus<'a>),
+    Reporting(ReportingConfiguration<'a>),
+    ReportingStatus(ReportingConfigurationStatus<'a>),
+    ReadReporting { direction: u8, identifier: u16 },
+    Discovered { identifier: u16, data_type: u8 },
+}
+
+impl<'a> Records<'a> {
+    fn new(kind: RecordKind, data: &'a [u8]) -> Self {
+        Records {
+            kind,
+            reader: ByteReader::new(data),
+        }
+    }
+
+    fn read(&mut self) -> Result<Record<'a>, Error> {
+        let reader = &mut self.reader;
+        let record = match self.kind {
+            RecordKind::Identifier => Record::Identifier(reader.u16()?),
+            RecordKind::Attribute => {
+                let identifier = reader.u16()?;
+                let data_type = reader.u8()?;
+                Record::Attribute(Attribute {
+                    identifier,
+                    data_type,
+                    value: AttributeValue::read(data_type, reader)?,
+                })
+            }
+            RecordKind::ReadStatus => {
+                let identifier = reader.u16()?;
+                let status = reader.u8()?;
+                let attribute = if status == 0 {
+                    let data_type = reader.u8()?;
+                    Some(Attribute {
+                        identifier,
+                        data_type,
+                        value: AttributeValue::read(data_type, reader)?,
+                    })
+                } else {
+                    None
+                };
+                Record::Status(AttributeStatus {
+                    identifier,
+                    status,
+                    attribute,
+                })
+            }
+            RecordKind::WriteStatus => {
+                let status = reader.u8()?;
+                // A single success status covers all attributes
+                let identifier = if reader.is_empty() {
+                    0xffff
+                } else {
+                    reader.u16()?
+                };
+                Record::Status(AttributeStatus {
+                    identifier,
+                    status,
+                    attribute: None,
+                })
+            }
+            RecordKind::Reporting => Record::Reporting(ReportingConfiguration::read(reader)?),
+            RecordKind::ReportingStatus => {
+                let status = reader.u8()?;
+                let (direction, identifier) = if reader.is_empty() {
+                    (0, 0xffff)
+                } else {
+                    (reader.u8()?, reader.u16()?)
+                };
+                Record::ReportingStatus(ReportingConfigurationStatus {
+                    status,
+                    direction,
+                    identifier,
+                    configuration: None,
+                })
+            }
+            RecordKind::ReadReporting => Record::ReadReporting {
+                direction: reader.u8()?,
+                identifier: reader.u16()?,
+            },
+            RecordKind::ReadReportingStatus => {
+                let status = reader.u8()?;
+                let direction = reader.u8()?;
+                let identifier = reader.u16()?;
+                let configuration = if status == 0 {
+                    Some(ReportingConfiguration::read_body(
+                        direction, identifier, reader,
+                    )?)
+                } else {
+                    None
+                };
+                Record::ReportingStatus(ReportingConfigurationStatus {
+                    status,
+                    direction,
+                    identifier,
+                    configuration,
+                })
+            }
+            RecordKind::Discovered => Record::Discovered {
+                identifier: reader.u16()?,
+                data_type: reader.u8()?,
+            },
+        };
+        Ok(record)
+    }
+}
+
+impl<'a> Iterator for Records<'a> {
+    type Item = Result<Record<'a>, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.reader.is_empty() {
+            return None;
+        }
+        let result = self.read();
+        if result.is_err() {
+            // Stop at the first broken record
+            let _ = self.reader.bytes(self.reader.remaining().len());
+        }
+        Some(result)
+    }
+}
+
+pub mod command {
+    pub const READ_ATTRIBUTES: u8 = 0x00;
+    pub const READ_ATTRIBUTES_RESPONSE: u8 = 0x01;
+    pub const WRITE_ATTRIBUTES: u8 = 0x02;
+    pub const WRITE_ATTRIBUTES_UNDIVIDED: u8 = 0x03;
+    pub const WRITE_ATTRIBUTES_RESPONSE: u8 = 0x04;
+    pub const WRITE_ATTRIBUTES_NO_RESPONSE: u8 = 0x05;
+    pub const CONFIGURE_REPORTING: u8 = 0x06;
+    pub const CONFIGURE_REPORTING_RESPONSE: u8 = 0x07;
+    pub const READ_REPORTING_CONFIGURATION: u8 = 0x08;
+    pub const READ_REPORTING_CONFIGURATION_RESPONSE: u8 = 0x09;
+    pub const REPORT_ATTRIBUTES: u8 = 0x0a;
+    pub const DEFAULT_RESPONSE: u8 = 0x0b;
+    pub const DISCOVER_ATTRIBUTES: u8 = 0x0c;
+    pub const DISCOVER_ATTRIBUTES_RESPONSE: u8 = 0x0d;
+}
+
+#[derive(Clone, Debug)]
+pub enum Command<'a> {
+    ReadAttributes(Records<'a>),
+    ReadAttributesResponse(Records<'a>),
+    WriteAttributes(Records<'a>),
+    WriteAttributesUndivided(Records<'a>),
+    WriteAttributesResponse(Records<'a>),
+    WriteAttributesNoResponse(Records<'a>),
+    ConfigureReporting(Records<'a>),
+    ConfigureReportingResponse(Records<'a>),
+    ReadReportingConfiguration(Records<'a>),
+    ReadReportingConfigurationResponse(Records<'a>),
+    ReportAttributes(Records<'a>),
+    DefaultResponse {
+        command: u8,
+        status: u8,
+    },
+    DiscoverAttributes {
+        start: u16,
+        maximum: u8,
+    },
+    DiscoverAttributesResponse {
+        complete: bool,
+        attributes: Records<'a>,
+    },
+    Cluster(ClusterCommand<'a>),
+    Other(&'a [u8]),
+}
+
+impl<'a> Command<'a> {
+    fn unpack_global(identifier: u8, payload: &'a [u8]) -> Result<Self, Error> {
+        use command::*;
+        let command = match identifier {
+            READ_ATTRIBUTES => {
+                Command::ReadAttributes(Records::new(RecordKind::Identifier, payload))
+            }
+            READ_ATTRIBUTES_RESPONSE => {
+                Command::ReadAttributesResponse(Records::new(RecordKind::ReadStatus, payload))
+            }
+            WRITE_ATTRIBUTES => {
+                Command::WriteAttributes(Records::new(RecordKind::Attribute, payload))
+            }
+            WRITE_ATTRIBUTES_UNDIVIDED => {
+                Command::WriteAttributesUndivided(Records::new(RecordKind::Attribute, payload))
+            }
+            WRITE_ATTRIBUTES_RESPONSE => {
+                Command::WriteAttributesResponse(Records::new(RecordKind::WriteStatus, payload))
+            }
+            WRITE_ATTRIBUTES_NO_RESPONSE => {
+                Command::WriteAttributesNoResponse(Records::new(RecordKind::Attribute, payload))
+            }
+            CONFIGURE_REPORTING => {
+                Command::ConfigureReporting(Records::new(RecordKind::Reporting, payload))
+            }
+            CONFIGURE_REPORTING_RESPONSE => Command::ConfigureReportingResponse(Records::new(
+                RecordKind::ReportingStatus,
+                payload,
+            )),
+            READ_REPORTING_CONFIGURATION => Command::ReadReportingConfiguration(Records::new(
+                RecordKind::ReadReporting,
+                payload,
+            )),
+            READ_REPORTING_CONFIGURATION_RESPONSE => Command::ReadReportingConfigurationResponse(
+                Records::new(RecordKind::ReadReportingStatus, payload),
+            ),
+            REPORT_ATTRIBUTES => {
+                Command::ReportAttributes(Records::new(RecordKind::Attribute, payload))
+            }
+            DEFAULT_RESPONSE => {
+                let mut reader = ByteReader::new(payload);
+                Command::DefaultResponse {
+                    command: reader.u8()?,
+                    status: reader.u8()?,
+                }
+            }
+            DISCOVER_ATTRIBUTES => {
+                let mut reader = ByteReader::new(payload);
+                Command::DiscoverAttributes {
+                    start: reader.u16()?,
+                    maximum: reader.u8()?,
+                }
+            }
+            DISCOVER_ATTRIBUTES_RESPONSE => {
+                let mut reader = ByteReader::new(payload);
+                let complete = reader.u8()? != 0;
+                Command::DiscoverAttributesResponse {
+                    complete,
+                    attributes: Records::new(RecordKind::Discovered, reader.remaining()),
+                }
+            }
+            _ => Command::Other(payload),
+        };
+        Ok(command)
+    }
+}
+
+/// Cluster specific commands for a selection of common clusters
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ClusterCommand<'a> {
+    Identify {
+        time: u16,
+    },
+    IdentifyQuery,
+    IdentifyQueryResponse {
+        timeout: u16,
+    },
+    TriggerEffect {
+        effect: u8,
+        variant: u8,
+    },
+    AddGroup {
+        group: u16,
+        name: &'a [u8],
+    },
+    ViewGroup {
+        group: u16,
+    },
+    GetGroupMembership {
+        groups: &'a [u8],
+    },
+    RemoveGroup {
+        group: u16,
+    },
+    RemoveAllGroups,
+    AddGroupIfIdentifying {
+        group: u16,
+        name: &'a [u8],
+    },
+    AddGroupResponse {
+        status: u8,
+        group: u16,
+    },
+    ViewGroupResponse {
+        status: u8,
+        group: u16,
+        name: &'a [u8],
+    },
+    GetGroupMembershipResponse {
+        capacity: u8,
+        groups: &'a [u8],
+    },
+    RemoveGroupResponse {
+        status: u8,
+        group: u16,
+    },
+    Off,
+    On,
+    Toggle,
+    OffWithEffect {
+        effect: u8,
+        variant: u8,
+    },
+    OnWithRecallGlobalScene,
+    OnWithTimedOff {
+        control: u8,
+        on_time: u16,
+        off_wait_time: u16,
+    },
+    MoveToLevel {
+        level: u8,
+        transition_time: u16,
+        with_on_off: bool,
+    },
+    Move {
+        mode: u8,
+        rate: u8,
+        with_on_off: bool,
+    },
+    Step {
+        mode: u8,
+        size: u8,
+        transition_time: u16,
+        with_on_off: bool,
+    },
+    Stop {
+        with_on_off: bool,
+    },
+    MoveToHue {
+        hue: u8,
+        direction: u8,
+        transition_time: u16,
+    },
+    MoveToSaturation {
+        saturation: u8,
+        transition_time: u16,
+    },
+    MoveToHueAndSaturation {
+        hue: u8,
+        saturation: u8,
+        transition_time: u16,
+    },
+    MoveToColor {
+        x: u16,
+        y: u16,
+        transition_time: u16,
+    },
+    MoveToColorTemperature {
+        mireds: u16,
+        transition_time: u16,
+    },
+    StopMoveStep,
+    Other {
+        command: u8,
+        payload: &'a [u8],
+    },
+}
+
+impl<'a> ClusterCommand<'a> {
+    fn read_string(reader: &mut ByteReader<'a>) -> Result<&'a [u8], Error> {
+        let length = reader.u8()?;
+        reader.bytes(usize::from(length))
+    }
+
+    fn read_groups(reader: &mut ByteReader<'a>) -> Result<&'a [u8], Error> {
+        let count = reader.u8()?;
+        reader.bytes(usize::from(count) * 2)
+    }
+
+    pub fn unpack(
+        cluster: u16,
+        direction: Direction,
+        command: u8,
+        payload: &'a [u8],
+    ) -> Result<Self, Error> {
+        let mut reader = ByteReader::new(payload);
+        let reader = &mut reader;
+        let client_to_server = direction == Direction::ClientToServer;
+        let command = match (cluster, client_to_server, command) {
+            (CLUSTER_IDENTIFY, true, 0x00) => ClusterCommand::Identify {
+                time: reader.u16()?,
+            },
+            (CLUSTER_IDENTIFY, true, 0x01) => ClusterCommand::IdentifyQuery,
+            (CLUSTER_IDENTIFY, true, 0x40) => ClusterCommand::TriggerEffect {
+                effect: reader.u8()?,
+                variant: reader.u8()?,
+            },
+            (CLUSTER_IDENTIFY, false, 0x00) => ClusterCommand::IdentifyQueryResponse {
+                timeout: reader.u16()?,
+            },
+            (CLUSTER_GROUPS, true, 0x00) => ClusterCommand::AddGroup {
+                group: reader.u16()?,
+                name: Self::read_string(reader)?,
+            },
+            (CLUSTER_GROUPS, true, 0x01) => ClusterCommand::ViewGroup {
+                group: reader.u16()?,
+            },
+            (CLUSTER_GROUPS, true, 0x02) => ClusterCommand::GetGroupMembership {
+                groups: Self::read_groups(reader)?,
+            },
+            (CLUSTER_GROUPS, true, 0x03) => ClusterCommand::RemoveGroup {
+                group: reader.u16()?,
+            },
+            (CLUSTER_GROUPS, true, 0x04) => ClusterCommand::RemoveAllGroups,
+            (CLUSTER_GROUPS, true, 0x05) => ClusterCommand::AddGroupIfIdentifying {
+                group: reader.u16()?,
+                name: Self::read_string(reader)?,
+            },
+            (CLUSTER_GROUPS, false, 0x00) => ClusterCommand::AddGroupResponse {
+                status: reader.u8()?,
+                group: reader.u16()?,
+            },
+            (CLUSTER_GROUPS, false, 0x01) => ClusterCommand::ViewGroupResponse {
+                status: reader.u8()?,
+                group: reader.u16()?,
+                name: Self::read_string(reader)?,
+            },
+            (CLUSTER_GROUPS, false, 0x02) => ClusterCommand::GetGroupMembershipResponse {
+                capacity: reader.u8()?,
+                groups: Self::read_groups(reader)?,
+            },
+            (CLUSTER_GROUPS, false, 0x03) => ClusterCommand::RemoveGroupResponse {
+                status: reader.u8()?,
+                group: reader.u16()?,
+            },
+            (CLUSTER_ON_OFF, true, 0x00) => ClusterCommand::Off,
+            (CLUSTER_ON_OFF, true, 0x01) => ClusterCommand::On,
+            (CLUSTER_ON_OFF, true, 0x02) => ClusterCommand::Toggle,
+            (CLUSTER_ON_OFF, true, 0x40) => ClusterCommand::OffWithEffect {
+                effect: reader.u8()?,
+                variant: reader.u8()?,
+            },
+            (CLUSTER_ON_OFF, true, 0x41) => ClusterCommand::OnWithRecallGlobalScene,
+            (CLUSTER_ON_OFF, true, 0x42) => ClusterCommand::OnWithTimedOff {
+                control: reader.u8()?,
+                on_time: reader.u16()?,
+                off_wait_time: reader.u16()?,
+            },
+            (CLUSTER_LEVEL_CONTROL, true, 0x00) | (CLUSTER_LEVEL_CONTROL, true, 0x04) => {
+                ClusterCommand::MoveToLevel {
+                    level: reader.u8()?,
+                    transition_time: reader.u16()?,
+                    with_on_off: command == 0x04,
+                }
+            }
+            (CLUSTER_LEVEL_CONTROL, true, 0x01) | (CLUSTER_LEVEL_CONTROL, true, 0x05) => {
+                ClusterCommand::Move {
+                    mode: reader.u8()?,
+                    rate: reader.u8()?,
+                    with_on_off: command == 0x05,
+                }
+            }
+            (CLUSTER_LEVEL_CONTROL, true, 0x02) | (CLUSTER_LEVEL_CONTROL, true, 0x06) => {
+                ClusterCommand::Step {
+                    mode: reader.u8()?,
+                    size: reader.u8()?,
+                    transition_time: reader.u16()?,
+                    with_on_off: command == 0x06,
+                }
+            }
+            (CLUSTER_LEVEL_CONTROL, true, 0x03) | (CLUSTER_LEVEL_CONTROL, true, 0x07) => {
+                ClusterCommand::Stop {
+                    with_on_off: command == 0x07,
+                }
+            }
+            (CLUSTER_COLOR_CONTROL, true, 0x00) => ClusterCommand::MoveToHue {
+                hue: reader.u8()?,
+                direction: reader.u8()?,
+                transition_time: reader.u16()?,
+            },
+            (CLUSTER_COLOR_CONTROL, true, 0x03) => ClusterCommand::MoveToSaturation {
+                saturation: reader.u8()?,
+                transition_time: reader.u16()?,
+            },
+            (CLUSTER_COLOR_CONTROL, true, 0x06) => ClusterCommand::MoveToHueAndSaturation {
+                hue: reader.u8()?,
+                saturation: reader.u8()?,
+                transition_time: reader.u16()?,
+            },
+            (CLUSTER_COLOR_CONTROL, true, 0x07) => ClusterCommand::MoveToColor {
+                x: reader.u16()?,
+                y: reader.u16()?,
+                transition_time: reader.u16()?,
+            },
+            (CLUSTER_COLOR_CONTROL, true, 0x0a) => ClusterCommand::MoveToColorTemperature {
+                mireds: reader.u16()?,
+                transition_time: reader.u16()?,
+            },
+            (CLUSTER_COLOR_CONTROL, true, 0x47) => ClusterCommand::StopMoveStep,
+            _ => ClusterCommand::Other { command, payload },
+        };
+        Ok(command)
+    }
+}
@@ -0,0 +1,808 @@
+//! Zigbee Device Object (ZDO) message decoding, the frames in profile 0x0000
+//!
+//! Like ZCL frames, the message is decoded on demand with [`Frame::message`] since lists in the
+//! messages borrow from the payload.
+
+use psila_data::Error;
+
+use crate::decoded::PayloadBuffer;
+use crate::reader::ByteReader;
+
+pub const NETWORK_ADDRESS_REQUEST: u16 = 0x0000;
+pub const IEEE_ADDRESS_REQUEST: u16 = 0x0001;
+pub const NODE_DESCRIPTOR_REQUEST: u16 = 0x0002;
+pub const POWER_DESCRIPTOR_REQUEST: u16 = 0x0003;
+pub const SIMPLE_DESCRIPTOR_REQUEST: u16 = 0x0004;
+pub const ACTIVE_ENDPOINT_REQUEST: u16 = 0x0005;
+pub const MATCH_DESCRIPTOR_REQUEST: u16 = 0x0006;
+pub const DEVICE_ANNOUNCE: u16 = 0x0013;
+pub const BIND_REQUEST: u16 = 0x0021;
+pub const UNBIND_REQUEST: u16 = 0x0022;
+pub const MANAGEMENT_LQI_REQUEST: u16 = 0x0031;
+pub const MANAGEMENT_ROUTING_REQUEST: u16 = 0x0032;
+pub const MANAGEMENT_BIND_REQUEST: u16 = 0x0033;
+pub const MANAGEMENT_LEAVE_REQUEST: u16 = 0x0034;
+pub const MANAGEMENT_PERMIT_JOINING_REQUEST: u16 = 0x0036;
+pub const MANAGEMENT_NETWORK_UPDATE_REQUEST: u16 = 0x0038;
+
+/// Responses use the cluster of the request with the high bit set
+pub const RESPONSE: u16 = 0x8000;
+pub const NETWORK_ADDRESS_RESPONSE: u16 = RESPONSE | NETWORK_ADDRESS_REQUEST;
+pub const IEEE_ADDRESS_RESPONSE: u16 = RESPONSE | IEEE_ADDRESS_REQUEST;
+pub const NODE_DESCRIPTOR_RESPONSE: u16 = RESPONSE | NODE_DESCRIPTOR_REQUEST;
+pub const POWER_DESCRIPTOR_RESPONSE: u16 = RESPONSE | POWER_DESCRIPTOR_REQUEST;
+pub const SIMPLE_DESCRIPTOR_RESPONSE: u16 = RESPONSE | SIMPLE_DESCRIPTOR_REQUEST;
+pub const ACTIVE_ENDPOINT_RESPONSE: u16 = RESPONSE | ACTIVE_ENDPOINT_REQUEST;
+pub const MATCH_DESCRIPTOR_RESPONSE: u16 = RESPONSE | MATCH_DESCRIPTOR_REQUEST;
+pub const BIND_RESPONSE: u16 = RESPONSE | BIND_REQUEST;
+pub const UNBIND_RESPONSE: u16 = RESPONSE | UNBIND_REQUEST;
+pub const MANAGEMENT_LQI_RESPONSE: u16 = RESPONSE | MANAGEMENT_LQI_REQUEST;
+pub const MANAGEMENT_ROUTING_RESPONSE: u16 = RESPONSE | MANAGEMENT_ROUTING_REQUEST;
+pub const MANAGEMENT_BIND_RESPONSE: u16 = RESPONSE | MANAGEMENT_BIND_REQUEST;
+pub const MANAGEMENT_LEAVE_RESPONSE: u16 = RESPONSE | MANAGEMENT_LEAVE_REQUEST;
+pub const MANAGEMENT_PERMIT_JOINING_RESPONSE: u16 = RESPONSE | MANAGEMENT_PERMIT_JOINING_REQUEST;
+/// Sent in response to a network update request with scan duration 0 to 5
+pub const MANAGEMENT_NETWORK_UPDATE_NOTIFY: u16 = RESPONSE | MANAGEMENT_NETWORK_UPDATE_REQUEST;
+
+pub fn status_name(status: u8) -> &'static str {
+    match status {
+        0x00 => "Success",
+        0x80 => "Invalid request type",
+        0x81 => "Device not found",
+        0x82 => "Invalid endpoint",
+        0x83 => "Not active",
+        0x84 => "Not supported",
+        0x85 => "Timeout",
+        0x86 => "No match",
+        0x88 => "No entry",
+        0x89 => "No descriptor",
+        0x8a => "Insufficient space",
+        0x8b => "Not permitted",
+        0x8c => "Table full",
+        0x8d => "Not authorized",
+        0x8e => "Device binding table full",
+        0x8f => "Invalid index",
+        _ => "Unknown",
+    }
+}
+
+/// A ZDO frame, the cluster identifies the message
+#[derive(Clone, Debug)]
+pub struct Frame {
+    pub cluster: u16,
+    pub transaction_sequence: u8,
+    pub payload: PayloadBuffer,
+}
+
+impl Frame {
+    pub fn unpack(cluster: u16, data: &[u8]) -> Result<Self, Error> {
+        let (transaction_sequence, payload) =
+            data.split_first().ok_or(Error::WrongNumberOfBytes)?;
+        let payload = PayloadBuffer::from_slice(payload).map_err(|_| Error::NotEnoughSpace)?;
+        Ok(Frame {
+            cluster,
+            transaction_sequence: *transaction_sequence,
+            payload,
+        })
+    }
+
+    pub fn message(&self) -> Result<Message<'_>, Error> {
+        Message::unpack(self.cluster, &self.payload)
+    }
+}
+
+/// List of 16-bit values, i.e. cluster identifiers or network addresses
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct U16List<'a>(&'a [u8]);
+
+impl<'a> U16List<'a> {
+    fn read(reader: &mut ByteReader<'a>) -> Result<Self, Error> {
+        let count = reader.u8()?;
+        Ok(U16List(reader.bytes(usize::from(count) * 2)?))
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len() / 2
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = u16> + 'a {
+        self.0
+            .chunks_exact(2)
+            .map(|b| u16::from_le_bytes([b[0], b[1]]))
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LogicalType {
+    Coordinator,
+    Router,
+    EndDevice,
+    Reserved(u8),
+}
+
+impl From<u8> for LogicalType {
+    fn from(value: u8) -> Self {
+        match value {
+            0 => LogicalType::Coordinator,
+            1 => LogicalType::Router,
+            2 => LogicalType::EndDevice,
+            _ => LogicalType::Reserved(value),
+        }
+    }
+}
+
+/// MAC capability flags as sent in association requests and device announcements
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Capability {
+    pub alternate_pan_coordinator: bool,
+    pub full_function_device: bool,
+    pub mains_power: bool,
+    pub receiver_on_when_idle: bool,
+    pub security: bool,
+    pub allocate_address: bool,
+}
+
+impl From<u8> for Capability {
+    fn from(value: u8) -> Self {
+        Capability {
+            alternate_pan_coordinator: value & 0x01 == 0x01,
+            full_function_device: value & 0x02 == 0x02,
+            mains_power: value & 0x04 == 0x04,
+            receiver_on_when_idle: value & 0x08 == 0x08,
+            security: value & 0x40 == 0x40,
+            allocate_address: value & 0x80 == 0x80,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct NodeDescriptor {
+    pub logical_type: LogicalType,
+    pub complex_descriptor: bool,
+    pub user_descriptor: bool,
+    pub frequency_bands: u8,
+    pub capability: Capability,
+    pub manufacturer_code: u16,
+    pub maximum_buffer_size: u8,
+    pub maximum_incoming_transfer_size: u16,
+    pub server_mask: u16,
+    pub maximum_outgoing_transfer_size: u16,
+    pub descriptor_capability: u8,
+}
+
+impl NodeDescriptor {
+    fn read(reader: &mut ByteReader) -> Result<Self, Error> {
+        let flags = reader.u8()?;
+        let bands = reader.u8()?;
+        Ok(NodeDescriptor {
+            logical_type: LogicalType::from(flags & 0x07),
+            complex_descriptor: flags & 0x08 == 0x08,
+            user_descriptor: flags & 0x10 == 0x10,
+            frequency_bands: bands >> 3,
+            capability: Capability::from(reader.u8()?),
+            manufacturer_code: reader.u16()?,
+            maximum_buffer_size: reader.u8()?,
+            maximum_incoming_transfer_size: reader.u16()?,
+            server_mask: reader.u16()?,
+            maximum_outgoing_transfer_size: reader.u16()?,
+            descriptor_capability: reader.u8()?,
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PowerDescriptor {
+    pub mode: u8,
+    pub available_sources: u8,
+    pub current_source: u8,
+    pub level: u8,
+}
+
+impl PowerDescriptor {
+    fn read(reader: &mut ByteReader) -> Result<Self, Error> {
+        let first = reader.u8()?;
+        let second = reader.u8()?;
+        Ok(PowerDescriptor {
+            mode: first & 0x0f,
+            available_sources: first >> 4,
+            current_source: second & 0x0f,
+            level: second >> 4,
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SimpleDescriptor<'a> {
+    pub endpoint: u8,
+    pub profile: u16,
+    pub device: u16,
+    pub device_version: u8,
+    pub input_clusters: U16List<'a>,
+    pub output_clusters: U16List<'a>,
+}
+
+impl<'a> SimpleDescriptor<'a> {
+    fn read(reader: &mut ByteReader<'a>) -> Result<Self, Error> {
+        Ok(SimpleDescriptor {
+            endpoint: reader.u8()?,
+            profile: reader.u16()?,
+            device: reader.u16()?,
+            device_version: reader.u8()? & 0x0f,
+            input_clusters: U16List::read(reader)?,
+            output_clusters: U16List::read(reader)?,
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BindingDestination {
+    Group(u16),
+    Extended(u64, u8),
+}
+
+impl BindingDestination {
+    fn read(reader: &mut ByteReader) -> Result<Self, Error> {
+        match reader.u8()? {
+            0x01 => Ok(BindingDestination::Group(reader.u16()?)),
+            0x03 => Ok(BindingDestination::Extended(reader.u64()?, reader.u8()?)),
+            _ => Err(Error::InvalidValue),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Binding {
+    pub source: u64,
+    pub source_endpoint: u8,
+    pub cluster: u16,
+    pub destination: BindingDestination,
+}
+
+impl Binding {
+    fn read(reader: &mut ByteReader) -> Result<Self, Error> {
+        Ok(Binding {
+            source: reader.u64()?,
+            source_endpoint: reader.u8()?,
+            cluster: reader.u16()?,
+            destination: BindingDestination::read(reader)?,
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Relationship {
+    Parent,
+    Child,
+    Sibling,
+    None,
+    PreviousChild,
+    Reserved(u8),
+}
+
+impl From<u8> for Relationship {
+    fn from(value: u8) -> Self {
+        match value {
+            0 => Relationship::Parent,
+            1 => Relationship::Child,
+            2 => Relationship::Sibling,
+            3 => Relationship::None,
+            4 => Relationship::PreviousChild,
+            _ => Relationship::Reserved(value),
+        }
+    }
+}
+
+/// Neighbor table entry from a Mgmt_Lqi_rsp
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Neighbor {
+    pub extended_pan_identifier: u64,
+    pub extended_address: u64,
+    pub network_address: u16,
+    pub device_type: LogicalType,
+    /// 0 off, 1 on, 2 unknown
+    pub receiver_on_when_idle: u8,
+    pub relationship: Relationship,
+    /// 0 not accepting, 1 accepting, 2 unknown
+    pub permit_joining: u8,
+    pub depth: u8,
+    pub link_quality: u8,
+}
+
+impl Neighbor {
+    fn read(reader: &mut ByteReader) -> Result<Self, Error> {
+        let extended_pan_identifier = reader.u64()?;
+        let extended_address = reader.u64()?;
+        let network_address = reader.u16()?;
+        let flags = reader.u8()?;
+        Ok(Neighbor {
+            extended_pan_identifier,
+            extended_address,
+            network_address,
+            device_type: LogicalType::from(flags & 0x03),
+            receiver_on_when_idle: (flags >> 2) & 0x03,
+            relationship: Relationship::from((flags >> 4) & 0x07),
+            permit_joining: reader.u8()? & 0x03,
+            depth: reader.u8()?,
+            link_quality: reader.u8()?,
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RouteStatus {
+    Active,
+    DiscoveryUnderway,
+    DiscoveryFailed,
+    Inactive,
+    ValidationUnderway,
+    Reserved(u8),
+}
+
+impl From<u8> for RouteStatus {
+    fn from(value: u8) -> Self {
+        match value {
+            0 => RouteStatus::Active,
+            1 => RouteStatus::DiscoveryUnderway,
+            2 => RouteStatus::DiscoveryFailed,
+            3 => RouteStatus::Inactive,
+            4 => RouteStatus::ValidationUnderway,
+            _ => RouteStatus::Reserved(value),
+        }
+    }
+}
+
+/// Routing table entry from a Mgmt_Rtg_rsp
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Route {
+    pub destination: u16,
+    pub status: RouteStatus,
+    pub memory_constrained: bool,
+    pub many_to_one: bool,
+    pub route_record_required: bool,
+    pub next_hop: u16,
+}
+
+impl Route {
+    fn read(reader: &mut ByteReader) -> Result<Self, Error> {
+        let destination = reader.u16()?;
+        let flags = reader.u8()?;
+        Ok(Route {
+            destination,
+            status: RouteStatus::from(flags & 0x07),
+            memory_constrained: flags & 0x08 == 0x08,
+            many_to_one: flags & 0x10 == 0x10,
+            route_record_required: flags & 0x20 == 0x20,
+            next_hop: reader.u16()?,
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum EntryKind {
+    Neighbor,
+    Route,
+    Binding,
+}
+
+/// Table entries in a management response, decoded while iterating
+#[derive(Clone, Debug)]
+pub struct Entries<'a> {
+    kind: EntryKind,
+    count: u8,
+    reader: ByteReader<'a>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Entry {
+    Neighbor(Neighbor),
+    Route(Route),
+    Binding(Binding),
+}
+
+impl<'a> Entries<'a> {
+    pub fn len(&self) -> usize {
+        usize::from(self.count)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+}
+
+impl<'a> Iterator for Entries<'a> {
+    type Item = Result<Entry, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.count == 0 {
+            return None;
+        }
+        self.count -= 1;
+        let result = match self.kind {
+            EntryKind::Neighbor => Neighbor::read(&mut self.reader).map(Entry::Neighbor),
+            EntryKind::Route => Route::read(&mut self.reader).map(Entry::Route),
+            EntryKind::Binding => Binding::read(&mut self.reader).map(Entry::Binding),
+        };
+        if result.is_err() {
+            self.count = 0;
+        }
+        Some(result)
+    }
+}
+
+/// Table slice in a management response
+#[derive(Clone, Debug)]
+pub struct Table<'a> {
+    pub status: u8,
+    pub total: u8,
+    pub start_index: u8,
+    pub entries: Entries<'a>,
+}
+
+impl<'a> Table<'a> {
+    fn read(kind: EntryKind, reader: &mut ByteReader<'a>) -> Result<Self, Error> {
+        let status = reader.u8()?;
+        if status != 0 {
+            return Ok(Table {
+                status,
+                total: 0,
+                start_index: 0,
+                entries: Entries {
+                    kind,
+                    count: 0,
+                    reader: ByteReader::new(&[]),
+                },
+            });
+        }
+        let total = reader.u8()?;
+        let start_index = reader.u8()?;
+        let count = reader.u8()?;
+        Ok(Table {
+            status,
+            total,
+            start_index,
+            entries: Entries {
+                kind,
+                count,
+                reader: ByteReader::new(reader.remaining()),
+            },
+        })
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum Message<'a> {
+    NetworkAddressRequest {
+        address: u64,
+        extended: bool,
+        start_index: u8,
+    },
+    IeeeAddressRequest {
+        address: u16,
+        extended: bool,
+        start_index: u8,
+    },
+    NodeDescriptorRequest {
+        address: u16,
+    },
+    PowerDescriptorRequest {
+        address: u16,
+    },
+    SimpleDescriptorRequest {
+        address: u16,
+        endpoint: u8,
+    },
+    ActiveEndpointRequest {
+        address: u16,
+    },
+    MatchDescriptorRequest {
+        address: u16,
+        profile: u16,
+        input_clusters: U16List<'a>,
+        output_clusters: U16List<'a>,
+    },
+    DeviceAnnounce {
+        network_address: u16,
+        extended_address: u64,
+        capability: Capability,
+    },
+    BindRequest(Binding),
+    UnbindRequest(Binding),
+    ManagementLqiRequest {
+        start_index: u8,
+    },
+    ManagementRoutingRequest {
+        start_index: u8,
+    },
+    ManagementBindRequest {
+        start_index: u8,
+    },
+    ManagementLeaveRequest {
+        address: u64,
+        remove_children: bool,
+        rejoin: bool,
+    },
+    ManagementPermitJoiningRequest {
+        duration: u8,
+        trust_center_significance: bool,
+    },
+    ManagementNetworkUpdateRequest {
+        channels: u32,
+        scan_duration: u8,
+        scan_count: Option<u8>,
+        update_identifier: Option<u8>,
+        manager_address: Option<u16>,
+    },
+    AddressResponse {
+        ieee_address_response: bool,
+        status: u8,
+        extended_address: u64,
+        network_address: u16,
+        start_index: u8,
+        associated_devices: U16List<'a>,
+    },
+    NodeDescriptorResponse {
+        status: u8,
+        address: u16,
+        descriptor: Option<NodeDescriptor>,
+    },
+    PowerDescriptorResponse {
+        status: u8,
+        address: u16,
+        descriptor: Option<PowerDescriptor>,
+    },
+    SimpleDescriptorResponse {
+        status: u8,
+        address: u16,
+        descriptor: Option<SimpleDescriptor<'a>>,
+    },
+    ActiveEndpointResponse {
+        status: u8,
+        address: u16,
+        endpoints: &'a [u8],
+    },
+    MatchDescriptorResponse {
+        status: u8,
+        address: u16,
+        endpoints: &'a [u8],
+    },
+    BindResponse {
+        status: u8,
+    },
+    UnbindResponse {
+        status: u8,
+    },
+    ManagementLqiResponse(Table<'a>),
+    ManagementRoutingResponse(Table<'a>),
+    ManagementBindResponse(Table<'a>),
+    ManagementLeaveResponse {
+        status: u8,
+    },
+    ManagementPermitJoiningResponse {
+        status: u8,
+    },
+    ManagementNetworkUpdateNotify {
+        status: u8,
+        channels: u32,
+        transmissions: u16,
+        failures: u16,
+        energies: &'a [u8],
+    },
+    Other {
+        cluster: u16,
+        payload: &'a [u8],
+    },
+}
+
+impl<'a> Message<'a> {
+    fn read_endpoints(reader: &mut ByteReader<'a>) -> Result<&'a [u8], Error> {
+        let count = reader.u8()?;
+        reader.bytes(usize::from(count))
+    }
+
+    pub fn unpack(cluster: u16, payload: &'a [u8]) -> Result<Self, Error> {
+        let mut reader = ByteReader::new(payload);
+        let reader = &mut reader;
+        let message = match cluster {
+            NETWORK_ADDRESS_REQUEST => Message::NetworkAddressRequest {
+                address: reader.u64()?,
+                extended: reader.u8()? == 1,
+                start_index: reader.u8()?,
+            },
+            IEEE_ADDRESS_REQUEST => Message::IeeeAddressRequest {
+                address: reader.u16()?,
+                extended: reader.u8()? == 1,
+                start_index: reader.u8()?,
+            },
+            NODE_DESCRIPTOR_REQUEST => Message::NodeDescriptorRequest {
+                address: reader.u16()?,
+            },
+            POWER_DESCRIPTOR_REQUEST => Message::PowerDescriptorRequest {
+                address: reader.u16()?,
+            },
+            SIMPLE_DESCRIPTOR_REQUEST => Message::SimpleDescriptorRequest {
+                address: reader.u16()?,
+                endpoint: reader.u8()?,
+            },
+            ACTIVE_ENDPOINT_REQUEST => Message::ActiveEndpointRequest {
+                address: reader.u16()?,
+            },
+            MATCH_DESCRIPTOR_REQUEST => Message::MatchDescriptorRequest {
+                address: reader.u16()?,
+                profile: reader.u16()?,
+                input_clusters: U16List::read(reader)?,
+                output_clusters: U16List::read(reader)?,
+            },
+            DEVICE_ANNOUNCE => Message::DeviceAnnounce {
+                network_address: reader.u16()?,
+                extended_address: reader.u64()?,
+                capability: Capability::from(reader.u8()?),
+            },
+            BIND_REQUEST => Message::BindRequest(Binding::read(reader)?),
+            UNBIND_REQUEST => Message::UnbindRequest(Binding::read(reader)?),
+            MANAGEMENT_LQI_REQUEST => Message::ManagementLqiRequest {
+                start_index: reader.u8()?,
+            },
+            MANAGEMENT_ROUTING_REQUEST => Message::ManagementRoutingRequest {
+                start_index: reader.u8()?,
+            },
+            MANAGEMENT_BIND_REQUEST => Message::ManagementBindRequest {
+                start_index: reader.u8()?,
+            },
+            MANAGEMENT_LEAVE_REQUEST => {
+                let address = reader.u64()?;
+                let flags = reader.u8()?;
+                Message::ManagementLeaveRequest {
+                    address,
+                    remove_children: flags & 0x40 == 0x40,
+                    rejoin: flags & 0x80 == 0x80,
+                }
+            }
+            MANAGEMENT_PERMIT_JOINING_REQUEST => Message::ManagementPermitJoiningRequest {
+                duration: reader.u8()?,
+                trust_center_significance: reader.u8()? == 1,
+            },
+            MANAGEMENT_NETWORK_UPDATE_REQUEST => {
+                let channels = reader.u32()?;
+                let scan_duration = reader.u8()?;
+                let scan_count = if scan_duration <= 5 {
+                    Some(reader.u8()?)
+                } else {
+                    None
+                };
+                let update_identifier = if scan_duration >= 0xfe {
+                    Some(reader.u8()?)
+                } else {
+                    None
+                };
+                let manager_address = if scan_duration == 0xff {
+                    Some(reader.u16()?)
+                } else {
+                    None
+                };
+                Message::ManagementNetworkUpdateRequest {
+                    channels,
+                    scan_duration,
+                    scan_count,
+                    update_identifier,
+                    manager_address,
+                }
+            }
+            NETWORK_ADDRESS_RESPONSE | IEEE_ADDRESS_RESPONSE => {
+                let status = reader.u8()?;
+                let extended_address = reader.u64()?;
+                let network_address = reader.u16()?;
+                let (start_index, associated_devices) = if reader.is_empty() {
+                    (0, U16List(&[]))
+                } else {
+                    let count = reader.u8()?;
+                    let start_index = if count > 0 { reader.u8()? } else { 0 };
+                    let devices = reader.bytes(usize::from(count) * 2)?;
+                    (start_index, U16List(devices))
+                };
+                Message::AddressResponse {
+                    ieee_address_response: cluster == IEEE_ADDRESS_RESPONSE,
+                    status,
+                    extended_address,
+                    network_address,
+                    start_index,
+                    associated_devices,
+                }
+            }
+            NODE_DESCRIPTOR_RESPONSE => {
+                let status = reader.u8()?;
+                let address = reader.u16()?;
+                let descriptor = if status == 0 {
+                    Some(NodeDescriptor::read(reader)?)
+                } else {
+                    None
+                };
+                Message::NodeDescriptorResponse {
+                    status,
+                    address,
+                    descriptor,
+                }
+            }
+            POWER_DESCRIPTOR_RESPONSE => {
+                let status = reader.u8()?;
+                let address = reader.u16()?;
+                let descriptor = if status == 0 {
+                    Some(PowerDescriptor::read(reader)?)
+                } else {
+                    None
+                };
+                Message::PowerDescriptorResponse {
+                    status,
+                    address,
+                    descriptor,
+                }
+            }
+            SIMPLE_DESCRIPTOR_RESPONSE => {
+                let status = reader.u8()?;
+                let address = reader.u16()?;
+                let length = reader.u8()?;
+                let descriptor = if status == 0 && length > 0 {
+                    Some(SimpleDescriptor::read(reader)?)
+                } else {
+                    None
+                };
+                Message::SimpleDescriptorResponse {
+                    status,
+                    address,
+                    descriptor,
+                }
+            }
+            ACTIVE_ENDPOINT_RESPONSE | MATCH_DESCRIPTOR_RESPONSE => {
+                let status = reader.u8()?;
+                let address = reader.u16()?;
+                let endpoints = Self::read_endpoints(reader)?;
+                if cluster == ACTIVE_ENDPOINT_RESPONSE {
+                    Message::ActiveEndpointResponse {
+                        status,
+                        address,
+                        endpoints,
+                    }
+                } else {
+                    Message::MatchDescriptorResponse {
+                        status,
+                        address,
+                        endpoints,
+                    }
+                }
+            }
+            BIND_RESPONSE => Message::BindResponse {
+                status: reader.u8()?,
+            },
+            UNBIND_RESPONSE => Message::UnbindResponse {
+                status: reader.u8()?,
+            },
+            MANAGEMENT_LQI_RESPONSE => {
+                Message::ManagementLqiResponse(Table::read(EntryKind::Neighbor, reader)?)
+            }
+            MANAGEMENT_ROUTING_RESPONSE => {
+                Message::ManagementRoutingResponse(Table::read(EntryKind::Route, reader)?)
+            }
+            MANAGEMENT_BIND_RESPONSE => {
+                Message::ManagementBindResponse(Table::read(EntryKind::Binding, reader)?)
+            }
+            MANAGEMENT_LEAVE_RESPONSE => Message::ManagementLeaveResponse {
+                status: reader.u8()?,
+            },
+            MANAGEMENT_PERMIT_JOINING_RESPONSE => Message::ManagementPermitJoiningResponse {
+                status: reader.u8()?,
+            },
+            MANAGEMENT_NETWORK_UPDATE_NOTIFY => Message::ManagementNetworkUpdateNotify {
+                status: reader.u8()?,
+                channels: reader.u32()?,
+                transmissions: reader.u16()?,
+                failures: reader.u16()?,
+                energies: Self::read_endpoints(reader)?,
+            },
+            _ => Message::Other { cluster, payload },
+        };
+        Ok(message)
+    }
+}
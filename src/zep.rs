@@ -0,0 +1,82 @@
+//! Encoder for the Zigbee Encapsulation Protocol (ZEP) version 2.
+//!
+//! ZEP wraps IEEE 802.15.4 frames in UDP datagrams, Wireshark decodes these on UDP port 17754.
+
+use crate::decoded::RxMetadata;
+use crate::fcs;
+
+pub const ZEP_PORT: u16 = 17754;
+pub const HEADER_SIZE: usize = 32;
+pub const FCS_SIZE: usize = 2;
+
+const PREAMBLE: [u8; 2] = *b"EX";
+const VERSION: u8 = 2;
+const TYPE_DATA: u8 = 1;
+const MODE_CRC: u8 = 1;
+/// Seconds between the NTP epoch, 1900, and the UNIX epoch, 1970
+const NTP_UNIX_OFFSET: u64 = 2_208_988_800;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Error {
+    NotEnoughSpace,
+    FrameTooLarge,
+}
+
+pub struct ZepEncoder {
+    device_id: u16,
+    sequence: u32,
+}
+
+impl ZepEncoder {
+    pub fn new(device_id: u16) -> Self {
+        ZepEncoder {
+            device_id,
+            sequence: 0,
+        }
+    }
+
+    /// Encode a MAC frame without FCS into a ZEP datagram, the FCS is added. When the metadata
+    /// flags a bad FCS the added FCS is inverted so that Wireshark also reports it as bad.
+    /// The metadata timestamp is in microseconds since the UNIX epoch, a missing channel is
+    /// written as 0 and a missing LQI as 255. Returns the datagram size.
+    pub fn encode(
+        &mut self,
+        metadata: &RxMetadata,
+        frame: &[u8],
+        output: &mut [u8],
+    ) -> Result<usize, Error> {
+        let timestamp = metadata.timestamp;
+        let length = frame.len() + FCS_SIZE;
+        if length > usize::from(u8::MAX) {
+            return Err(Error::FrameTooLarge);
+        }
+        let size = HEADER_SIZE + length;
+        if output.len() < size {
+            return Err(Error::NotEnoughSpace);
+        }
+        let seconds = (timestamp / 1_000_000 + NTP_UNIX_OFFSET) as u32;
+        let fraction = (((timestamp % 1_000_000) << 32) / 1_000_000) as u32;
+
+        output[0..2].copy_from_slice(&PREAMBLE);
+        output[2] = VERSION;
+        output[3] = TYPE_DATA;
+        output[4] = metadata.channel.unwrap_or(0);
+        output[5..7].copy_from_slice(&self.device_id.to_be_bytes());
+        output[7] = MODE_CRC;
+        output[8] = metadata.lqi.unwrap_or(u8::MAX);
+        output[9..13].copy_from_slice(&seconds.to_be_bytes());
+        output[13..17].copy_from_slice(&fraction.to_be_bytes());
+        output[17..21].copy_from_slice(&self.sequence.to_be_bytes());
+        output[21..31].fill(0);
+        output[31] = length as u8;
+        output[HEADER_SIZE..HEADER_SIZE + frame.len()].copy_from_slice(frame);
+        let mut check = fcs::fcs(frame);
+        if metadata.fcs.is_bad() {
+            check.iter_mut().for_each(|b| *b = !*b);
+        }
+        output[HEADER_SIZE + frame.len()..size].copy_from_slice(&check);
+
+        self.sequence = self.sequence.wrapping_add(1);
+        Ok(size)
+    }
+}
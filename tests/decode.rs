@@ -0,0 +1,386 @@
+//! Decode known frames through the parser, run with `cargo test-host`.
+
+use esp32c6_psila::fcs::{self, BadFcsPolicy, FcsStatus};
+use esp32c6_psila::information_element::{ElementKind, ZigbeeElement, ZIGBEE_OUI};
+use esp32c6_psila::{DecodeError, Parser, Payload, RxMetadata};
+use ieee802154::mac;
+use psila_data::application_service::commands::TransportKey;
+use psila_data::application_service::Command as ApplicationServiceCommand;
+use psila_data::common::key::Key;
+use psila_data::network::commands::Command;
+use psila_data::security::KeyIdentifier;
+use psila_data::ExtendedAddress;
+
+/// Zigbee PRO beacon from the coordinator of PAN 1a62, permitting joining
+const BEACON: [u8; 26] = [
+    0x00, 0x80, 0x4d, 0x62, 0x1a, 0x00, 0x00, 0xff, 0xcf, 0x00, 0x00, 0x00, 0x22, 0x84, 0x01, 0x02,
+    0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0xff, 0xff, 0xff, 0x00,
+];
+
+/// NWK Leave command from 1234 to the coordinator
+const LEAVE: [u8; 19] = [
+    0x41, 0x88, 0x11, 0x62, 0x1a, 0x00, 0x00, 0x34, 0x12, 0x09, 0x00, 0x00, 0x00, 0x34, 0x12, 0x01,
+    0x22, 0x04, 0x00,
+];
+
+/// On/Off On command with APS security using the default trust center link key
+const SECURED_ON: [u8; 45] = [
+    0x41, 0x88, 0x10, 0x62, 0x1a, 0x00, 0x00, 0x34, 0x12, 0x08, 0x00, 0x00, 0x00, 0x34, 0x12, 0x1e,
+    0x21, 0x20, 0x01, 0x06, 0x00, 0x04, 0x01, 0x01, 0x33, 0x20, 0x05, 0x00, 0x00, 0x00, 0x04, 0x03,
+    0x02, 0x01, 0x00, 0x4b, 0x12, 0x00, 0x49, 0x44, 0x14, 0xec, 0x87, 0x33, 0xab,
+];
+
+/// Transport Key from the trust center 0011223344556677 carrying network key sequence 0, secured
+/// with the key-transport key derived from the default trust center link key
+const TRANSPORT_KEY: [u8; 71] = [
+    0x41, 0x88, 0x12, 0x62, 0x1a, 0x34, 0x12, 0x00, 0x00, 0x08, 0x00, 0x34, 0x12, 0x00, 0x00, 0x1e,
+    0x22, 0x21, 0x44, 0x30, 0x01, 0x00, 0x00, 0x00, 0x77, 0x66, 0x55, 0x44, 0x33, 0x22, 0x11, 0x00,
+    0x8b, 0xf0, 0x5a, 0xb2, 0x8f, 0x53, 0xe8, 0x31, 0xdd, 0xf4, 0x03, 0xf5, 0x44, 0x1d, 0xc8, 0xe2,
+    0x66, 0xeb, 0xa9, 0x4a, 0x35, 0xf1, 0xa7, 0x0b, 0x0a, 0xf7, 0x34, 0xfb, 0x96, 0x4e, 0xdb, 0xca,
+    0x55, 0xda, 0x74, 0xdb, 0xcd, 0xd5, 0xa2,
+];
+
+const NETWORK_KEY: [u8; 16] = [
+    0x01, 0x03, 0x05, 0x07, 0x09, 0x0b, 0x0d, 0x0f, 0x00, 0x02, 0x04, 0x06, 0x08, 0x0a, 0x0c, 0x0d,
+];
+
+/// IEEE 802.15.4-2015 NWK Leave from 1234 to the coordinator, with a CSL header IE and a header
+/// termination IE before the MAC payload
+const LEAVE_WITH_HEADER_IE: [u8; 27] = [
+    0x41, 0xaa, 0x11, 0x62, 0x1a, 0x00, 0x00, 0x34, 0x12, 0x04, 0x0d, 0x01, 0x00, 0x02, 0x00, 0x80,
+    0x3f, 0x09, 0x00, 0x00, 0x00, 0x34, 0x12, 0x01, 0x22, 0x04, 0x00,
+];
+
+/// IEEE 802.15.4-2015 enhanced beacon from the coordinator of PAN 1a62, the Zigbee beacon payload
+/// is carried in a Zigbee payload IE
+const ENHANCED_BEACON: [u8; 31] = [
+    0x00, 0xa2, 0x4e, 0x62, 0x1a, 0x00, 0x00, 0x00, 0x3f, 0x14, 0x90, 0x1b, 0x19, 0x4a, 0x8f, 0x00,
+    0x00, 0x22, 0x84, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0xff, 0xff, 0xff, 0x00,
+];
+
+/// IEEE 802.15.4-2015 multipurpose frame with short frame control from 1234 to the broadcast
+/// address
+const MULTIPURPOSE: [u8; 9] = [0xa5, 0x07, 0xff, 0xff, 0x34, 0x12, 0x01, 0x02, 0x03];
+
+/// IEEE 802.15.4-2015 RIT data request from 1234 to the coordinator
+const RIT_DATA_REQUEST: [u8; 12] = [
+    0x43, 0xa8, 0x05, 0x62, 0x1a, 0x00, 0x00, 0x34, 0x12, 0x20, 0x01, 0x02,
+];
+
+fn with_fcs(frame: &[u8], valid: bool) -> Vec<u8> {
+    let mut data = frame.to_vec();
+    let mut check = fcs::fcs(frame);
+    if !valid {
+        check[0] ^= 0xff;
+    }
+    data.extend_from_slice(&check);
+    data
+}
+
+#[test]
+fn beacon() {
+    let mut parser = Parser::new();
+    let decoded = parser.parse_frame(&BEACON, &RxMetadata::default()).unwrap();
+    assert!(decoded.error.is_none());
+    assert_eq!(decoded.mac.seq, 0x4d);
+    match decoded.content {
+        mac::FrameContent::Beacon(ref beacon) => {
+            assert!(beacon.superframe_spec.pan_coordinator);
+            assert!(beacon.superframe_spec.association_permit);
+        }
+        _ => panic!("Not a beacon"),
+    }
+    match decoded.payload {
+        Payload::Beacon(ref beacon) => {
+            assert_eq!(beacon.stack_profile, 2);
+            assert_eq!(beacon.protocol_version, 2);
+            assert_eq!(beacon.device_depth, 0);
+            assert!(beacon.router_capacity);
+            assert!(beacon.end_device_capacity);
+            assert_eq!(beacon.extended_pan_identifier, 0x0807_0605_0403_0201);
+            assert_eq!(beacon.tx_offset, Some(0x00ff_ffff));
+            assert_eq!(beacon.update_identifier, Some(0));
+        }
+        _ => panic!("No beacon payload"),
+    }
+
+    // Data pending for 1234
+    let mut data = BEACON[..10].to_vec();
+    data.extend_from_slice(&[0x01, 0x34, 0x12]);
+    data.extend_from_slice(&BEACON[11..]);
+    let decoded = parser.parse_frame(&data, &RxMetadata::default()).unwrap();
+    assert!(decoded.error.is_none());
+    match decoded.content {
+        mac::FrameContent::Beacon(ref beacon) => {
+            let pending = beacon.pending_address.short_addresses();
+            assert_eq!(pending.len(), 1);
+            assert_eq!(pending[0].0, 0x1234);
+            assert!(beacon.pending_address.extended_addresses().is_empty());
+        }
+        _ => panic!("Not a beacon"),
+    }
+    assert!(matches!(decoded.payload, Payload::Beacon(_)));
+}
+
+#[test]
+fn network_command() {
+    let mut parser = Parser::new();
+    let decoded = parser.parse_frame(&LEAVE, &RxMetadata::default()).unwrap();
+    assert!(decoded.error.is_none());
+    let network = decoded.network.unwrap();
+    assert_eq!(u16::from(network.source_address), 0x1234);
+    assert_eq!(u16::from(network.destination_address), 0x0000);
+    match decoded.payload {
+        Payload::NetworkCommand(Command::Leave(ref leave)) => {
+            assert!(!leave.rejoin);
+            assert!(!leave.request);
+            assert!(!leave.remove_children);
+        }
+        _ => panic!("No leave command"),
+    }
+}
+
+#[test]
+fn unknown_mac_command() {
+    let mut parser = Parser::new();
+    let decoded = parser
+        .parse_frame(&RIT_DATA_REQUEST, &RxMetadata::default())
+        .unwrap();
+    assert!(decoded.error.is_none());
+    assert!(matches!(decoded.mac.frame_type, mac::FrameType::MacCommand));
+    match decoded.payload {
+        Payload::MacCommand {
+            identifier,
+            name,
+            ref body,
+        } => {
+            assert_eq!(identifier, 0x20);
+            assert_eq!(name, "RIT data request");
+            assert_eq!(body.as_slice(), &[0x01, 0x02]);
+        }
+        _ => panic!("No MAC command"),
+    }
+    let statistics = parser.statistics();
+    assert_eq!(statistics.malformed, 0);
+    assert_eq!(statistics.mac_commands.get(&0x20), Some(&1));
+}
+
+#[test]
+fn secured_application_service_frame() {
+    let mut parser = Parser::new();
+    let decoded = parser
+        .parse_frame(&SECURED_ON, &RxMetadata::default())
+        .unwrap();
+    assert!(decoded.error.is_none());
+    assert!(decoded.network_security.is_none());
+    let security = decoded.application_service_security.unwrap();
+    assert_eq!(security.counter, 5);
+    let header = decoded.application_service.unwrap();
+    assert_eq!(header.profile, Some(0x0104));
+    assert_eq!(header.cluster, Some(0x0006));
+    match decoded.payload {
+        Payload::ClusterLibrary(ref frame) => {
+            assert_eq!(frame.header.transaction_sequence, 0x42);
+            assert_eq!(frame.header.command, 0x01);
+            assert!(frame.payload.is_empty());
+        }
+        _ => panic!("No ZCL frame"),
+    }
+    let statistics = parser.statistics();
+    assert_eq!(statistics.keys.iter().map(|k| k.successes).sum::<u32>(), 1);
+}
+
+#[test]
+fn secured_without_key() {
+    let mut parser = Parser::new();
+    parser.security.link_keys.clear();
+    let decoded = parser
+        .parse_frame(&SECURED_ON, &RxMetadata::default())
+        .unwrap();
+    assert!(matches!(decoded.error, Some(DecodeError::NoValidKey)));
+    assert!(matches!(decoded.payload, Payload::None));
+}
+
+#[test]
+fn link_key_of_sender() {
+    let key = Key::from(*b"ZigBeeAlliance09");
+    let mut parser = Parser::new();
+    parser.security.link_keys.clear();
+    // A key for another device is not tried
+    parser
+        .security
+        .add_link_key(key, Some(ExtendedAddress::from(0x0012_4b00_0102_0305u64)));
+    let decoded = parser
+        .parse_frame(&SECURED_ON, &RxMetadata::default())
+        .unwrap();
+    assert!(matches!(decoded.error, Some(DecodeError::NoValidKey)));
+
+    parser
+        .security
+        .add_link_key(key, Some(ExtendedAddress::from(0x0012_4b00_0102_0304u64)));
+    let decoded = parser
+        .parse_frame(&SECURED_ON, &RxMetadata::default())
+        .unwrap();
+    assert!(decoded.error.is_none());
+    assert!(matches!(decoded.payload, Payload::ClusterLibrary(_)));
+}
+
+#[test]
+fn transport_key() {
+    let mut parser = Parser::new();
+    let decoded = parser
+        .parse_frame(&TRANSPORT_KEY, &RxMetadata::default())
+        .unwrap();
+    assert!(decoded.error.is_none());
+    let security = decoded.application_service_security.unwrap();
+    assert!(matches!(
+        security.control.identifier,
+        KeyIdentifier::KeyTransport
+    ));
+    assert_eq!(security.counter, 1);
+    match decoded.payload {
+        Payload::ApplicationServiceCommand(ApplicationServiceCommand::TransportKey(
+            TransportKey::StandardNetworkKey(ref key),
+        )) => {
+            assert_eq!(key.key, Key::from(NETWORK_KEY));
+            assert_eq!(key.sequence, 0);
+        }
+        _ => panic!("No Transport Key"),
+    }
+    // The key is learned and in use on the PAN
+    assert_eq!(
+        parser.security.network_key(0).map(|e| e.key),
+        Some(Key::from(NETWORK_KEY))
+    );
+    assert_eq!(parser.security.active_sequence(0x1a62), Some(0));
+}
+
+#[test]
+fn good_fcs() {
+    let mut parser = Parser::new();
+    let data = with_fcs(&BEACON, true);
+    let decoded = parser
+        .parse_frame_with_fcs(&data, &RxMetadata::default())
+        .unwrap();
+    assert_eq!(decoded.metadata.fcs, FcsStatus::Good);
+    assert!(decoded.error.is_none());
+}
+
+#[test]
+fn bad_fcs() {
+    let data = with_fcs(&BEACON, false);
+
+    let mut parser = Parser::new();
+    let decoded = parser
+        .parse_frame_with_fcs(&data, &RxMetadata::default())
+        .unwrap();
+    assert_eq!(decoded.metadata.fcs, FcsStatus::Bad);
+    assert!(matches!(decoded.error, Some(DecodeError::BadFcs)));
+    assert!(matches!(decoded.payload, Payload::None));
+
+    parser.bad_fcs_policy = BadFcsPolicy::Decode;
+    let decoded = parser
+        .parse_frame_with_fcs(&data, &RxMetadata::default())
+        .unwrap();
+    assert!(matches!(decoded.payload, Payload::Beacon(_)));
+
+    parser.bad_fcs_policy = BadFcsPolicy::Drop;
+    assert!(parser
+        .parse_frame_with_fcs(&data, &RxMetadata::default())
+        .is_none());
+    assert_eq!(parser.statistics().dropped, 1);
+}
+
+#[test]
+fn header_information_elements() {
+    let mut parser = Parser::new();
+    let decoded = parser
+        .parse_frame(&LEAVE_WITH_HEADER_IE, &RxMetadata::default())
+        .unwrap();
+    assert!(decoded.error.is_none());
+    assert_eq!(decoded.information_elements.len(), 1);
+    let element = &decoded.information_elements[0];
+    assert_eq!(element.kind, ElementKind::Header);
+    assert_eq!(element.identifier, 0x1a);
+    assert_eq!(element.name(), "CSL");
+    assert_eq!(element.content.as_slice(), &[0x01, 0x00, 0x02, 0x00]);
+    // The payload after the termination IE is decoded as usual
+    assert!(matches!(
+        decoded.payload,
+        Payload::NetworkCommand(Command::Leave(_))
+    ));
+}
+
+#[test]
+fn enhanced_beacon() {
+    let mut parser = Parser::new();
+    let decoded = parser
+        .parse_frame(&ENHANCED_BEACON, &RxMetadata::default())
+        .unwrap();
+    assert!(decoded.error.is_none());
+    assert!(matches!(decoded.mac.frame_type, mac::FrameType::Beacon));
+    assert_eq!(decoded.information_elements.len(), 1);
+    let element = &decoded.information_elements[0];
+    assert_eq!(element.kind, ElementKind::Payload);
+    assert_eq!(element.name(), "Vendor specific");
+    assert_eq!(element.vendor_oui(), Some(ZIGBEE_OUI));
+    let zigbee = element
+        .zigbee_elements()
+        .unwrap()
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    assert!(matches!(zigbee[..], [ZigbeeElement::BeaconPayload(_)]));
+    match decoded.payload {
+        Payload::Beacon(ref beacon) => {
+            assert_eq!(beacon.stack_profile, 2);
+            assert_eq!(beacon.extended_pan_identifier, 0x0807_0605_0403_0201);
+        }
+        _ => panic!("No beacon payload"),
+    }
+
+    // A Zigbee IE cut short is reported, the MAC frame is still decoded
+    let mut data = ENHANCED_BEACON;
+    data[14] = 0x90;
+    let decoded = parser.parse_frame(&data, &RxMetadata::default()).unwrap();
+    assert!(matches!(
+        decoded.error,
+        Some(DecodeError::InformationElement(_))
+    ));
+    assert!(!matches!(decoded.payload, Payload::Beacon(_)));
+}
+
+#[test]
+fn multipurpose() {
+    let mut parser = Parser::new();
+    let decoded = parser
+        .parse_frame(&MULTIPURPOSE, &RxMetadata::default())
+        .unwrap();
+    assert!(decoded.error.is_none());
+    assert!(matches!(
+        decoded.mac.frame_type,
+        mac::FrameType::Multipurpose
+    ));
+    assert_eq!(decoded.mac.seq, 0x07);
+    assert!(matches!(
+        decoded.mac.source,
+        Some(mac::Address::Short(_, address)) if address.0 == 0x1234
+    ));
+    match decoded.payload {
+        Payload::MacData(ref data) => assert_eq!(data.as_slice(), &[0x01, 0x02, 0x03]),
+        _ => panic!("No MAC payload"),
+    }
+}
+
+#[test]
+fn truncated_information_element() {
+    let mut parser = Parser::new();
+    // The CSL header IE claims more content than the frame holds
+    let mut data = LEAVE_WITH_HEADER_IE[..13].to_vec();
+    data[9] = 0x0a;
+    assert!(parser.parse_frame(&data, &RxMetadata::default()).is_none());
+    // The Zigbee payload IE claims more content than the frame holds
+    let mut data = ENHANCED_BEACON;
+    data[9] = 0x20;
+    assert!(parser.parse_frame(&data, &RxMetadata::default()).is_none());
+    assert_eq!(parser.statistics().malformed, 2);
+}
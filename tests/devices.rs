@@ -0,0 +1,89 @@
+//! Merge the addresses seen in the different layers into devices, run with `cargo test-host`.
+
+use esp32c6_psila::devices::{DeviceTable, Role};
+use esp32c6_psila::{Parser, RxMetadata};
+
+const DEVICE: u64 = 0x0011_2233_4455_6677;
+const OTHER_DEVICE: u64 = 0x8899_aabb_ccdd_eeff;
+
+/// NWK Leave command from 1234 to the coordinator
+const LEAVE: [u8; 19] = [
+    0x41, 0x88, 0x11, 0x62, 0x1a, 0x00, 0x00, 0x34, 0x12, 0x09, 0x00, 0x00, 0x00, 0x34, 0x12, 0x01,
+    0x22, 0x04, 0x00,
+];
+
+/// MAC data request from an IEEE address to the coordinator
+fn data_request(extended_address: u64) -> Vec<u8> {
+    let mut data = vec![0x63, 0xc8, 0x05, 0x62, 0x1a, 0x00, 0x00];
+    data.extend_from_slice(&extended_address.to_le_bytes());
+    data.push(0x04);
+    data
+}
+
+/// Device announce broadcast by 1234, a mains powered router
+fn device_announce(extended_address: u64) -> Vec<u8> {
+    let mut data = vec![
+        0x41, 0x88, 0x06, 0x62, 0x1a, 0xff, 0xff, 0x34, 0x12, 0x08, 0x00, 0xfd, 0xff, 0x34, 0x12,
+        0x1e, 0x07, 0x08, 0x00, 0x13, 0x00, 0x00, 0x00, 0x00, 0x10, 0x01, 0x34, 0x12,
+    ];
+    data.extend_from_slice(&extended_address.to_le_bytes());
+    data.push(0x8e);
+    data
+}
+
+fn update(devices: &mut DeviceTable, data: &[u8], timestamp: u64, rssi: i8) {
+    let metadata = RxMetadata {
+        rssi: Some(rssi),
+        timestamp,
+        ..RxMetadata::default()
+    };
+    let mut parser = Parser::new();
+    let decoded = parser.parse_frame(data, &metadata).unwrap();
+    devices.update(&decoded);
+}
+
+#[test]
+fn merge_short_into_extended() {
+    let mut devices = DeviceTable::new();
+    update(&mut devices, &LEAVE, 10, -40);
+    update(&mut devices, &data_request(DEVICE), 20, -50);
+    assert_eq!(devices.devices.len(), 2);
+    let device = devices.by_short_address(0x1234).unwrap();
+    assert_eq!(device.extended_address, None);
+    assert_eq!(device.frames, 1);
+    assert_eq!(device.network_frames, 1);
+    let device = devices.by_extended_address(DEVICE).unwrap();
+    assert_eq!(device.short_address, None);
+    assert_eq!(device.frames, 1);
+
+    // The announcement ties the short address to the IEEE address
+    update(&mut devices, &device_announce(DEVICE), 30, -60);
+    assert_eq!(devices.devices.len(), 1);
+    let device = devices.devices[0];
+    assert_eq!(device.short_address, Some(0x1234));
+    assert_eq!(device.extended_address, Some(DEVICE));
+    assert_eq!(device.role, Role::Router);
+    assert_eq!(device.frames, 3);
+    assert_eq!(device.network_frames, 2);
+    assert_eq!(device.last_seen, 30);
+    assert_eq!(device.last_rssi, Some(-60));
+}
+
+#[test]
+fn short_address_reassigned() {
+    let mut devices = DeviceTable::new();
+    update(&mut devices, &device_announce(DEVICE), 10, -40);
+    assert_eq!(devices.devices.len(), 1);
+
+    // Another device joins and is given the same short address
+    update(&mut devices, &device_announce(OTHER_DEVICE), 20, -50);
+    assert_eq!(devices.devices.len(), 2);
+    assert_eq!(
+        devices.by_short_address(0x1234).unwrap().extended_address,
+        Some(OTHER_DEVICE)
+    );
+    assert_eq!(
+        devices.by_extended_address(DEVICE).unwrap().short_address,
+        None
+    );
+}
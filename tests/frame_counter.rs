@@ -0,0 +1,93 @@
+//! Classify frame counters per source and key, run with `cargo test-host`.
+
+use esp32c6_psila::{FrameCounterStatus, FrameCounterTable, MAX_FRAME_COUNTERS};
+use psila_data::{pack::Pack, security::SecurityHeader, ExtendedAddress};
+
+const DEVICE: u64 = 0x0012_4b00_0102_0304;
+const OTHER_DEVICE: u64 = 0x0011_2233_4455_6677;
+
+/// Security header with the extended nonce, with the network key sequence when given
+fn header(source: u64, sequence: Option<u8>, counter: u32) -> SecurityHeader {
+    // Network or data key, extended nonce
+    let mut data = vec![if sequence.is_some() { 0x28 } else { 0x20 }];
+    data.extend_from_slice(&counter.to_le_bytes());
+    data.extend_from_slice(&source.to_le_bytes());
+    data.extend(sequence);
+    SecurityHeader::unpack(&data).unwrap().0
+}
+
+#[test]
+fn classify() {
+    let mut table = FrameCounterTable::new();
+    table.jump_threshold = 100;
+    let mut check = |counter| table.check(&header(DEVICE, Some(0), counter));
+    assert_eq!(check(1000), Some(FrameCounterStatus::New));
+    assert_eq!(check(1001), Some(FrameCounterStatus::Valid));
+    assert_eq!(check(1001), Some(FrameCounterStatus::Replay));
+    assert_eq!(check(1101), Some(FrameCounterStatus::Valid));
+    assert_eq!(check(1202), Some(FrameCounterStatus::Jump(1101)));
+    // The table follows the counter after a jump
+    assert_eq!(check(1203), Some(FrameCounterStatus::Valid));
+    assert_eq!(check(5), Some(FrameCounterStatus::Regression(1203)));
+    assert_eq!(check(6), Some(FrameCounterStatus::Valid));
+    assert!(FrameCounterStatus::Replay.is_suspicious());
+    assert!(FrameCounterStatus::Jump(0).is_suspicious());
+    assert!(!FrameCounterStatus::Valid.is_suspicious());
+}
+
+#[test]
+fn per_source_and_key() {
+    let mut table = FrameCounterTable::new();
+    assert_eq!(
+        table.check(&header(DEVICE, Some(0), 10)),
+        Some(FrameCounterStatus::New)
+    );
+    // Another key sequence, the link key and another device are tracked separately
+    assert_eq!(
+        table.check(&header(DEVICE, Some(1), 10)),
+        Some(FrameCounterStatus::New)
+    );
+    assert_eq!(
+        table.check(&header(DEVICE, None, 10)),
+        Some(FrameCounterStatus::New)
+    );
+    assert_eq!(
+        table.check(&header(OTHER_DEVICE, Some(0), 10)),
+        Some(FrameCounterStatus::New)
+    );
+    assert_eq!(
+        table.check(&header(DEVICE, Some(0), 10)),
+        Some(FrameCounterStatus::Replay)
+    );
+    assert_eq!(table.entries.len(), 4);
+    assert_eq!(table.entries[2].source, ExtendedAddress::from(DEVICE));
+    assert_eq!(table.entries[2].sequence, None);
+
+    // Without the extended nonce the source is unknown
+    let mut data = vec![0x08];
+    data.extend_from_slice(&10u32.to_le_bytes());
+    data.push(0);
+    let (anonymous, _) = SecurityHeader::unpack(&data).unwrap();
+    assert_eq!(table.check(&anonymous), None);
+
+    table.clear();
+    assert!(table.entries.is_empty());
+}
+
+#[test]
+fn full_table() {
+    let mut table = FrameCounterTable::new();
+    for source in 0..MAX_FRAME_COUNTERS as u64 + 1 {
+        table.check(&header(source, Some(0), 1));
+    }
+    assert_eq!(table.entries.len(), MAX_FRAME_COUNTERS);
+    // The oldest entry is replaced
+    assert_eq!(
+        table.check(&header(0, Some(0), 1)),
+        Some(FrameCounterStatus::New)
+    );
+    assert_eq!(
+        table.check(&header(MAX_FRAME_COUNTERS as u64, Some(0), 1)),
+        Some(FrameCounterStatus::Replay)
+    );
+}
@@ -0,0 +1,106 @@
+//! Parse keys in the notations of various tools, run with `cargo test-host`.
+
+use esp32c6_psila::{
+    parse_key, parse_key_with_order, parse_link_key, parse_wireshark_key, ByteOrder, KeyError,
+};
+use psila_data::{common::key::Key, ExtendedAddress};
+
+const KEY: [u8; 16] = [
+    0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09, 0x0a, 0x0b, 0x0c, 0x0d, 0x0e, 0x0f,
+];
+
+fn reversed() -> Key {
+    let mut key = KEY;
+    key.reverse();
+    Key::from(key)
+}
+
+#[test]
+fn notations() {
+    for notation in [
+        "000102030405060708090a0b0c0d0e0f",
+        "000102030405060708090A0B0C0D0E0F",
+        "0x000102030405060708090a0b0c0d0e0f",
+        "00:01:02:03:04:05:06:07:08:09:0a:0b:0c:0d:0e:0f",
+        "00 01 02 03 04 05 06 07 08 09 0a 0b 0c 0d 0e 0f",
+        "00-01-02-03-04-05-06-07-08-09-0a-0b-0c-0d-0e-0f",
+        "0:1:2:3:4:5:6:7:8:9:a:b:c:d:e:f",
+        "  000102030405060708090a0b0c0d0e0f\n",
+    ] {
+        assert_eq!(parse_key(notation), Ok(Key::from(KEY)), "{}", notation);
+    }
+    assert_eq!(
+        parse_key_with_order("000102030405060708090a0b0c0d0e0f", ByteOrder::Reverse),
+        Ok(reversed())
+    );
+}
+
+#[test]
+fn errors() {
+    assert_eq!(
+        parse_key("000102030405060708090a0b0c0d0e0g"),
+        Err(KeyError::InvalidCharacter('g'))
+    );
+    assert_eq!(
+        parse_key("000102030405060708090a0b0c0d0e"),
+        Err(KeyError::InvalidLength(15))
+    );
+    assert_eq!(
+        parse_key("000102030405060708090a0b0c0d0e0f10"),
+        Err(KeyError::InvalidLength(17))
+    );
+    assert_eq!(parse_key(""), Err(KeyError::InvalidLength(0)));
+}
+
+#[test]
+fn wireshark() {
+    assert_eq!(
+        parse_wireshark_key(r#""000102030405060708090a0b0c0d0e0f","Normal","Network""#),
+        Ok((Key::from(KEY), "Network"))
+    );
+    assert_eq!(
+        parse_wireshark_key(r#""000102030405060708090a0b0c0d0e0f","Reverse","Network""#),
+        Ok((reversed(), "Network"))
+    );
+    assert_eq!(
+        parse_wireshark_key(r#""00:01:02:03:04:05:06:07:08:09:0a:0b:0c:0d:0e:0f""#),
+        Ok((Key::from(KEY), ""))
+    );
+    assert_eq!(
+        parse_wireshark_key(r#""000102030405060708090a0b0c0d0e0f","Sideways","""#),
+        Err(KeyError::InvalidEntry)
+    );
+    assert_eq!(
+        parse_wireshark_key("000102030405060708090a0b0c0d0e0f"),
+        Err(KeyError::InvalidEntry)
+    );
+    // Entries are recognised by the leading quote
+    assert_eq!(
+        parse_key(r#""000102030405060708090a0b0c0d0e0f","Reverse","""#),
+        Ok(reversed())
+    );
+}
+
+#[test]
+fn link_key() {
+    assert_eq!(
+        parse_link_key("000102030405060708090a0b0c0d0e0f"),
+        Ok((Key::from(KEY), None))
+    );
+    assert_eq!(
+        parse_link_key("000102030405060708090a0b0c0d0e0f@0011223344556677"),
+        Ok((
+            Key::from(KEY),
+            Some(ExtendedAddress::from(0x0011_2233_4455_6677u64))
+        ))
+    );
+    assert_eq!(
+        parse_link_key("000102030405060708090a0b0c0d0e0f@device"),
+        Err(KeyError::InvalidAddress)
+    );
+    // An @ in a Wireshark label is not an address
+    assert_eq!(
+        parse_link_key(r#""000102030405060708090a0b0c0d0e0f","Normal","tc@home""#),
+        Ok((Key::from(KEY), None))
+    );
+}
@@ -0,0 +1,235 @@
+//! IEEE 802.15.4 MAC layer security known answers, run with `cargo test-host`.
+
+use esp32c6_psila::mac_security::{
+    ccm_star_decrypt, nonce, AuxiliarySecurityHeader, KeyIdentifierMode,
+};
+use esp32c6_psila::{DecodeError, Parser, RxMetadata};
+use ieee802154::mac;
+use psila_data::common::key::Key;
+
+/// Key and sender of the IEEE 802.15.4-2006 Annex C examples
+const KEY: [u8; 16] = [
+    0xc0, 0xc1, 0xc2, 0xc3, 0xc4, 0xc5, 0xc6, 0xc7, 0xc8, 0xc9, 0xca, 0xcb, 0xcc, 0xcd, 0xce, 0xcf,
+];
+const SOURCE: u64 = 0xacde_4800_0000_0001;
+
+/// Annex C.2.1, beacon frame with MIC-64, the payload is sent in the clear
+const BEACON: [u8; 34] = [
+    0x08, 0xd0, 0x84, 0x21, 0x43, 0x01, 0x00, 0x00, 0x00, 0x00, 0x48, 0xde, 0xac, 0x02, 0x05, 0x00,
+    0x00, 0x00, 0x55, 0xcf, 0x00, 0x00, 0x51, 0x52, 0x53, 0x54, 0x22, 0x3b, 0xc1, 0xec, 0x84, 0x1a,
+    0xb5, 0x53,
+];
+
+/// Annex C.2.2, data frame with ENC, the payload "abcd" is encrypted without MIC
+const DATA: [u8; 30] = [
+    0x69, 0xdc, 0x84, 0x21, 0x43, 0x02, 0x00, 0x00, 0x00, 0x00, 0x48, 0xde, 0xac, 0x01, 0x00, 0x00,
+    0x00, 0x00, 0x48, 0xde, 0xac, 0x04, 0x05, 0x00, 0x00, 0x00, 0xd4, 0x3e, 0x02, 0x2b,
+];
+
+/// Annex C.2.3, association request with ENC-MIC-64, the command identifier is sent in the clear
+const ASSOCIATION_REQUEST: [u8; 38] = [
+    0x2b, 0xdc, 0x84, 0x21, 0x43, 0x02, 0x00, 0x00, 0x00, 0x00, 0x48, 0xde, 0xac, 0xff, 0xff, 0x01,
+    0x00, 0x00, 0x00, 0x00, 0x48, 0xde, 0xac, 0x06, 0x05, 0x00, 0x00, 0x00, 0x01, 0xd8, 0x4f, 0xde,
+    0x52, 0x90, 0x61, 0xf9, 0xc6, 0xf1,
+];
+
+#[test]
+fn auxiliary_security_header() {
+    let (header, used) = AuxiliarySecurityHeader::unpack(&[0x05, 0x01, 0x02, 0x03, 0x04]).unwrap();
+    assert_eq!(used, 5);
+    assert_eq!(
+        header,
+        AuxiliarySecurityHeader {
+            level: 5,
+            key_identifier_mode: KeyIdentifierMode::Implicit,
+            frame_counter: Some(0x0403_0201),
+            key_source: None,
+            key_index: None,
+            asn_in_nonce: false,
+        }
+    );
+    assert!(header.is_encrypted());
+    assert!(header.is_authenticated());
+    assert_eq!(header.mic_length(), 4);
+
+    let (header, used) =
+        AuxiliarySecurityHeader::unpack(&[0x0e, 0x01, 0x02, 0x03, 0x04, 0x07]).unwrap();
+    assert_eq!(used, 6);
+    assert_eq!(header.key_identifier_mode, KeyIdentifierMode::Index);
+    assert_eq!(header.key_source, None);
+    assert_eq!(header.key_index, Some(7));
+    assert_eq!(header.mic_length(), 8);
+
+    let data = [0x13, 0x01, 0x02, 0x03, 0x04, 0x11, 0x22, 0x33, 0x44, 0x08];
+    let (header, used) = AuxiliarySecurityHeader::unpack(&data).unwrap();
+    assert_eq!(used, 10);
+    assert_eq!(header.level, 3);
+    assert!(!header.is_encrypted());
+    assert_eq!(header.mic_length(), 16);
+    assert_eq!(header.key_identifier_mode, KeyIdentifierMode::Source4);
+    assert_eq!(header.key_source, Some(0x4433_2211));
+    assert_eq!(header.key_index, Some(8));
+
+    let data = [
+        0x1c, 0x01, 0x02, 0x03, 0x04, 0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77, 0x88, 0x09,
+    ];
+    let (header, used) = AuxiliarySecurityHeader::unpack(&data).unwrap();
+    assert_eq!(used, 14);
+    assert_eq!(header.key_identifier_mode, KeyIdentifierMode::Source8);
+    assert_eq!(header.key_source, Some(0x8877_6655_4433_2211));
+    assert_eq!(header.key_index, Some(9));
+    assert!(header.is_encrypted());
+    assert!(!header.is_authenticated());
+
+    // IEEE 802.15.4-2015 frame counter suppression, the nonce uses the absolute slot number
+    let (header, used) = AuxiliarySecurityHeader::unpack(&[0x6d, 0x01]).unwrap();
+    assert_eq!(used, 2);
+    assert_eq!(header.frame_counter, None);
+    assert_eq!(header.key_index, Some(1));
+    assert!(header.asn_in_nonce);
+
+    assert!(AuxiliarySecurityHeader::unpack(&[0x05, 0x01, 0x02, 0x03]).is_err());
+    assert!(AuxiliarySecurityHeader::unpack(&[0x2d]).is_err());
+    assert!(AuxiliarySecurityHeader::unpack(&[]).is_err());
+}
+
+#[test]
+fn ccm_star() {
+    let mut output = [0u8; 16];
+
+    // MIC only, everything is authenticated
+    let (open, mic) = BEACON.split_at(26);
+    assert_eq!(
+        ccm_star_decrypt(&KEY, &nonce(SOURCE, 5, 2), 8, false, open, mic, &mut output),
+        Some(0)
+    );
+    let mut tampered = BEACON;
+    tampered[22] ^= 0x01;
+    let (open, mic) = tampered.split_at(26);
+    assert_eq!(
+        ccm_star_decrypt(&KEY, &nonce(SOURCE, 5, 2), 8, false, open, mic, &mut output),
+        None
+    );
+
+    // Encrypted and authenticated
+    let (open, private) = ASSOCIATION_REQUEST.split_at(29);
+    assert_eq!(
+        ccm_star_decrypt(
+            &KEY,
+            &nonce(SOURCE, 5, 6),
+            8,
+            true,
+            open,
+            private,
+            &mut output
+        ),
+        Some(1)
+    );
+    assert_eq!(output[0], 0xce);
+    // The frame counter is part of the nonce
+    assert_eq!(
+        ccm_star_decrypt(
+            &KEY,
+            &nonce(SOURCE, 6, 6),
+            8,
+            true,
+            open,
+            private,
+            &mut output
+        ),
+        None
+    );
+    assert_eq!(output[0], 0x00);
+
+    // Encrypted only
+    let (open, private) = DATA.split_at(26);
+    assert_eq!(
+        ccm_star_decrypt(
+            &KEY,
+            &nonce(SOURCE, 5, 4),
+            0,
+            true,
+            open,
+            private,
+            &mut output
+        ),
+        Some(4)
+    );
+    assert_eq!(&output[..4], b"abcd");
+}
+
+fn parser() -> Parser {
+    let mut parser = Parser::new();
+    parser.security.add_mac_key(Key::from(KEY), None);
+    parser
+}
+
+fn attempts(parser: &Parser) -> Option<(u32, u32)> {
+    parser
+        .security
+        .key_statistics
+        .iter()
+        .find(|s| s.key == Key::from(KEY))
+        .map(|s| (s.successes, s.failures))
+}
+
+#[test]
+fn decrypt_frames() {
+    let mut parser = parser();
+    let decoded = parser.parse_frame(&BEACON, &RxMetadata::default()).unwrap();
+    assert!(decoded.error.is_none());
+    assert!(!decoded.mac_unverified);
+    assert_eq!(decoded.mac_security.unwrap().level, 2);
+    match decoded.content {
+        mac::FrameContent::Beacon(ref beacon) => {
+            assert!(beacon.superframe_spec.pan_coordinator);
+            assert!(beacon.superframe_spec.association_permit);
+        }
+        _ => panic!("Not a beacon"),
+    }
+
+    let decoded = parser
+        .parse_frame(&ASSOCIATION_REQUEST, &RxMetadata::default())
+        .unwrap();
+    assert!(decoded.error.is_none());
+    assert!(!decoded.mac_unverified);
+    assert_eq!(decoded.mac_security.unwrap().level, 6);
+    match decoded.content {
+        mac::FrameContent::Command(mac::command::Command::AssociationRequest(capability)) => {
+            assert!(capability.full_function_device);
+            assert!(capability.mains_power);
+            assert!(capability.idle_receive);
+            assert!(capability.frame_protection);
+            assert!(capability.allocate_address);
+        }
+        _ => panic!("No association request"),
+    }
+    assert_eq!(attempts(&parser), Some((2, 0)));
+}
+
+#[test]
+fn wrong_key() {
+    let mut parser = Parser::new();
+    let mut key = KEY;
+    key[0] ^= 0x01;
+    parser.security.add_mac_key(Key::from(key), None);
+    let decoded = parser
+        .parse_frame(&ASSOCIATION_REQUEST, &RxMetadata::default())
+        .unwrap();
+    assert!(matches!(decoded.error, Some(DecodeError::NoValidKey)));
+    assert!(!decoded.mac_unverified);
+    assert_eq!(decoded.mac_security.unwrap().level, 6);
+    assert_eq!(parser.security.key_statistics[0].failures, 1);
+    assert_eq!(parser.security.key_statistics[0].successes, 0);
+}
+
+#[test]
+fn encrypted_without_mic() {
+    // Any key decrypts, the frame is flagged and the key is not credited
+    let mut parser = parser();
+    let decoded = parser.parse_frame(&DATA, &RxMetadata::default()).unwrap();
+    assert!(decoded.mac_unverified);
+    assert!(!matches!(decoded.error, Some(DecodeError::NoValidKey)));
+    assert_eq!(decoded.mac_security.unwrap().level, 4);
+    assert_eq!(attempts(&parser), None);
+}
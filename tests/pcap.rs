@@ -0,0 +1,177 @@
+//! Read pcapng captures and pcap streams, run with `cargo test-host`.
+
+use esp32c6_psila::fcs::FcsStatus;
+use esp32c6_psila::pcap::{Error, IoSink, LinkType, PcapReader, PcapWriter, StreamReader};
+use esp32c6_psila::RxMetadata;
+
+const LINKTYPE_IEEE802_15_4_NOFCS: u16 = 230;
+
+/// MAC acknowledgement
+const ACK: [u8; 3] = [0x02, 0x00, 0x11];
+
+/// A little endian pcapng block with the body padded to 32 bits
+fn block(block_type: u32, body: &[u8]) -> Vec<u8> {
+    let padding = (4 - body.len() % 4) % 4;
+    let length = (12 + body.len() + padding) as u32;
+    let mut data = Vec::new();
+    data.extend_from_slice(&block_type.to_le_bytes());
+    data.extend_from_slice(&length.to_le_bytes());
+    data.extend_from_slice(body);
+    data.extend_from_slice(&[0; 3][..padding]);
+    data.extend_from_slice(&length.to_le_bytes());
+    data
+}
+
+fn section_header() -> Vec<u8> {
+    let mut body = Vec::new();
+    body.extend_from_slice(&0x1a2b_3c4du32.to_le_bytes());
+    body.extend_from_slice(&1u16.to_le_bytes());
+    body.extend_from_slice(&0u16.to_le_bytes());
+    body.extend_from_slice(&u64::MAX.to_le_bytes());
+    block(0x0a0d_0d0a, &body)
+}
+
+/// Interface description, with an if_tsresol option when `resolution` is given
+fn interface(resolution: Option<u8>) -> Vec<u8> {
+    let mut body = Vec::new();
+    body.extend_from_slice(&LINKTYPE_IEEE802_15_4_NOFCS.to_le_bytes());
+    body.extend_from_slice(&0u16.to_le_bytes());
+    body.extend_from_slice(&256u32.to_le_bytes());
+    if let Some(resolution) = resolution {
+        body.extend_from_slice(&9u16.to_le_bytes());
+        body.extend_from_slice(&1u16.to_le_bytes());
+        body.extend_from_slice(&[resolution, 0, 0, 0]);
+        body.extend_from_slice(&[0; 4]);
+    }
+    block(1, &body)
+}
+
+fn enhanced_packet(timestamp: u64, frame: &[u8]) -> Vec<u8> {
+    let mut body = Vec::new();
+    body.extend_from_slice(&0u32.to_le_bytes());
+    body.extend_from_slice(&((timestamp >> 32) as u32).to_le_bytes());
+    body.extend_from_slice(&(timestamp as u32).to_le_bytes());
+    body.extend_from_slice(&(frame.len() as u32).to_le_bytes());
+    body.extend_from_slice(&(frame.len() as u32).to_le_bytes());
+    body.extend_from_slice(frame);
+    block(6, &body)
+}
+
+#[test]
+fn pcapng() {
+    let mut data = section_header();
+    data.extend(interface(Some(9)));
+    data.extend(enhanced_packet(1_500_000_000, &ACK));
+    let mut body = (ACK.len() as u32).to_le_bytes().to_vec();
+    body.extend_from_slice(&ACK);
+    data.extend(block(3, &body));
+
+    let mut reader = PcapReader::new(&data).unwrap();
+    let record = reader.next().unwrap().unwrap();
+    assert_eq!(record.timestamp, 1_500_000);
+    assert_eq!(record.link_type, u32::from(LINKTYPE_IEEE802_15_4_NOFCS));
+    assert_eq!(record.data, &ACK);
+    let record = reader.next().unwrap().unwrap();
+    assert_eq!(record.timestamp, 0);
+    assert_eq!(record.data, &ACK);
+    assert!(reader.next().is_none());
+}
+
+#[test]
+fn timestamp_resolution() {
+    // Binary resolution, 2^-10 seconds
+    let mut data = section_header();
+    data.extend(interface(Some(0x8a)));
+    data.extend(enhanced_packet(2048, &ACK));
+    let record = PcapReader::new(&data).unwrap().next().unwrap().unwrap();
+    assert_eq!(record.timestamp, 2_000_000);
+
+    // Seconds, saturating instead of overflowing
+    let mut data = section_header();
+    data.extend(interface(Some(0)));
+    data.extend(enhanced_packet(u64::MAX / 1000, &ACK));
+    let record = PcapReader::new(&data).unwrap().next().unwrap().unwrap();
+    assert_eq!(record.timestamp, u64::MAX);
+
+    // 10^-25 seconds is the finest resolution that fits
+    let mut data = section_header();
+    data.extend(interface(Some(25)));
+    data.extend(enhanced_packet(u64::MAX, &ACK));
+    let record = PcapReader::new(&data).unwrap().next().unwrap().unwrap();
+    assert_eq!(record.timestamp, 1);
+
+    let mut data = section_header();
+    data.extend(interface(Some(26)));
+    data.extend(enhanced_packet(0, &ACK));
+    let mut reader = PcapReader::new(&data).unwrap();
+    assert_eq!(
+        reader.next().unwrap().unwrap_err(),
+        Error::InvalidTimestampResolution(26)
+    );
+    assert!(reader.next().is_none());
+}
+
+#[test]
+fn short_simple_packet() {
+    // A simple packet block without room for the original length
+    let mut data = section_header();
+    data.extend(interface(None));
+    data.extend(block(3, &[]));
+    let mut reader = PcapReader::new(&data).unwrap();
+    assert_eq!(reader.next().unwrap().unwrap_err(), Error::Truncated);
+    assert!(reader.next().is_none());
+}
+
+#[test]
+fn stream() {
+    // Boot messages precede the capture on the serial port
+    let mut data = b"ESP-ROM:esp32c6-20220919\r\n".to_vec();
+    let mut writer = PcapWriter::new(IoSink(Vec::new()), LinkType::Ieee802154Tap).unwrap();
+    let metadata = RxMetadata {
+        rssi: Some(-60),
+        lqi: Some(200),
+        channel: Some(25),
+        timestamp: 2_000_001,
+        fcs: FcsStatus::Unknown,
+    };
+    writer.write_frame(&metadata, &ACK).unwrap();
+    writer
+        .write_frame(
+            &RxMetadata {
+                timestamp: 3_000_000,
+                ..RxMetadata::default()
+            },
+            &ACK[..2],
+        )
+        .unwrap();
+    data.extend(writer.into_inner().0);
+
+    let mut reader = StreamReader::new(data.as_slice()).unwrap();
+    assert_eq!(reader.link_type(), 283);
+    let record = reader.next_record().unwrap().unwrap();
+    assert_eq!(record.timestamp, 2_000_001);
+    let captured = record.ieee802154_frame().unwrap();
+    assert_eq!(captured.frame, &ACK);
+    assert_eq!(captured.metadata, metadata);
+    let record = reader.next_record().unwrap().unwrap();
+    let captured = record.ieee802154_frame().unwrap();
+    assert_eq!(captured.frame, &ACK[..2]);
+    assert_eq!(captured.metadata.timestamp, 3_000_000);
+    assert_eq!(captured.metadata.channel, None);
+    assert!(reader.next_record().unwrap().is_none());
+}
+
+#[test]
+fn stream_errors() {
+    let error = StreamReader::new(&b"no capture here"[..]).err().unwrap();
+    assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+
+    let mut writer = PcapWriter::new(IoSink(Vec::new()), LinkType::Ieee802154NoFcs).unwrap();
+    writer.write_frame(&RxMetadata::default(), &ACK).unwrap();
+    let mut data = writer.into_inner().0;
+    // Cut short in the frame
+    data.pop();
+    let mut reader = StreamReader::new(data.as_slice()).unwrap();
+    let error = reader.next_record().err().unwrap();
+    assert_eq!(error.kind(), std::io::ErrorKind::UnexpectedEof);
+}
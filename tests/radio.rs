@@ -0,0 +1,186 @@
+//! Scripted and replayed radios, run with `cargo test-host`.
+
+use esp32c6_psila::pcap::{self, LinkType, PcapWriter};
+use esp32c6_psila::radio::{
+    Error, MemoryRadio, Radio, ReceivedFrame, ReplayRadio, Transmit, MAX_FRAME_SIZE,
+};
+use esp32c6_psila::RxMetadata;
+
+/// NWK Leave command from 1234 to the coordinator
+const LEAVE: [u8; 19] = [
+    0x41, 0x88, 0x11, 0x62, 0x1a, 0x00, 0x00, 0x34, 0x12, 0x09, 0x00, 0x00, 0x00, 0x34, 0x12, 0x01,
+    0x22, 0x04, 0x00,
+];
+
+/// MAC acknowledgement
+const ACK: [u8; 3] = [0x02, 0x00, 0x11];
+
+fn metadata(channel: Option<u8>, timestamp: u64) -> RxMetadata {
+    RxMetadata {
+        channel,
+        timestamp,
+        ..RxMetadata::default()
+    }
+}
+
+#[test]
+fn memory_channel_filtering() {
+    let mut radio = MemoryRadio::<4>::new(10);
+    radio.push(&LEAVE, metadata(Some(11), 0)).unwrap();
+    radio.push(&ACK, metadata(Some(15), 0)).unwrap();
+    radio.push(&ACK, metadata(None, 0)).unwrap();
+    radio.push(&LEAVE, metadata(Some(15), 0)).unwrap();
+    assert_eq!(radio.channel(), 11);
+
+    let received = radio.receive().unwrap().unwrap();
+    assert_eq!(received.data.as_slice(), &LEAVE);
+    assert_eq!(received.metadata.channel, Some(11));
+    // The frame for channel 15 is lost, the frame without channel is received on channel 11
+    let received = radio.receive().unwrap().unwrap();
+    assert_eq!(received.data.as_slice(), &ACK);
+    assert_eq!(received.metadata.channel, Some(11));
+    assert_eq!(radio.pending(), 1);
+
+    radio.set_channel(15).unwrap();
+    assert_eq!(radio.channel(), 15);
+    let received = radio.receive().unwrap().unwrap();
+    assert_eq!(received.data.as_slice(), &LEAVE);
+    assert_eq!(received.metadata.channel, Some(15));
+    assert_eq!(radio.receive(), Ok(None));
+    assert_eq!(radio.pending(), 0);
+}
+
+#[test]
+fn memory_timestamps() {
+    let mut radio = MemoryRadio::<2>::new(100);
+    radio.push(&ACK, metadata(None, 250)).unwrap();
+    radio.push(&LEAVE, metadata(None, 1000)).unwrap();
+    assert_eq!(radio.now(), 0);
+    assert_eq!(radio.receive(), Ok(None));
+    assert_eq!(radio.now(), 100);
+    assert_eq!(radio.receive(), Ok(None));
+    let received = radio.receive().unwrap().unwrap();
+    assert_eq!(radio.now(), 300);
+    assert_eq!(received.data.as_slice(), &ACK);
+    assert_eq!(received.metadata.timestamp, 250);
+    radio.advance(500);
+    assert_eq!(radio.receive(), Ok(None));
+    assert_eq!(radio.now(), 900);
+    assert!(radio.receive().unwrap().is_some());
+}
+
+#[test]
+fn memory_errors() {
+    let mut radio = MemoryRadio::<1>::new(100);
+    assert_eq!(radio.set_channel(10), Err(Error::InvalidChannel(10)));
+    assert_eq!(radio.set_channel(27), Err(Error::InvalidChannel(27)));
+    assert_eq!(radio.channel(), 11);
+
+    let large = [0u8; MAX_FRAME_SIZE + 1];
+    assert_eq!(
+        radio.push(&large, RxMetadata::default()),
+        Err(Error::FrameTooLarge(MAX_FRAME_SIZE + 1))
+    );
+    radio.push(&ACK, RxMetadata::default()).unwrap();
+    assert_eq!(radio.push(&ACK, RxMetadata::default()), Err(Error::Full));
+    assert_eq!(radio.pending(), 1);
+
+    assert_eq!(
+        radio.transmit(&large),
+        Err(Error::FrameTooLarge(MAX_FRAME_SIZE + 1))
+    );
+    radio.transmit(&LEAVE).unwrap();
+    assert_eq!(radio.transmit(&ACK), Err(Error::Full));
+    assert_eq!(radio.transmitted().len(), 1);
+    assert_eq!(radio.transmitted()[0].as_slice(), &LEAVE);
+}
+
+fn capture() -> heapless::Vec<u8, 512> {
+    let mut writer = PcapWriter::new(heapless::Vec::new(), LinkType::Ieee802154Tap).unwrap();
+    let records: [(Option<u8>, u64, &[u8]); 3] = [
+        (Some(11), 1_000_000, &LEAVE),
+        (Some(15), 1_000_500, &ACK),
+        (None, 1_001_000, &ACK),
+    ];
+    for (channel, timestamp, frame) in records {
+        let metadata = RxMetadata {
+            rssi: Some(-50),
+            ..metadata(channel, timestamp)
+        };
+        writer.write_frame(&metadata, frame).unwrap();
+    }
+    writer.into_inner()
+}
+
+#[test]
+fn replay() {
+    let data = capture();
+
+    let mut radio = ReplayRadio::new(&data).unwrap();
+    assert_eq!(radio.now(), 1_000_000);
+    let received = radio.receive().unwrap().unwrap();
+    assert_eq!(received.data.as_slice(), &LEAVE);
+    assert_eq!(received.metadata.channel, Some(11));
+    assert_eq!(received.metadata.rssi, Some(-50));
+    assert_eq!(received.metadata.timestamp, 1_000_000);
+    // The frame on channel 15 is skipped
+    let received = radio.receive().unwrap().unwrap();
+    assert_eq!(received.data.as_slice(), &ACK);
+    assert_eq!(received.metadata.channel, Some(11));
+    assert_eq!(radio.now(), 1_001_000);
+    assert_eq!(radio.receive(), Ok(None));
+    assert_eq!(radio.now(), u64::MAX);
+
+    let mut radio = ReplayRadio::new(&data).unwrap();
+    radio.set_channel(15).unwrap();
+    let received = radio.receive().unwrap().unwrap();
+    assert_eq!(received.metadata.channel, Some(15));
+    assert_eq!(received.metadata.timestamp, 1_000_500);
+    assert_eq!(radio.set_channel(0), Err(Error::InvalidChannel(0)));
+}
+
+#[test]
+fn replay_errors() {
+    assert!(matches!(
+        ReplayRadio::new(&[0x01, 0x02]),
+        Err(Error::Capture(pcap::Error::Truncated))
+    ));
+    assert!(matches!(
+        ReplayRadio::new(&[0x01, 0x02, 0x03, 0x04]),
+        Err(Error::Capture(pcap::Error::UnknownFormat))
+    ));
+}
+
+#[test]
+fn raw_frame() {
+    // PSDU length, the frame, the signal strength and the link quality in place of the FCS
+    let mut raw = [0u8; MAX_FRAME_SIZE + 1];
+    raw[0] = (ACK.len() + 2) as u8;
+    raw[1..4].copy_from_slice(&ACK);
+    raw[4] = -42i8 as u8;
+    raw[5] = 0xd4;
+    let received = ReceivedFrame::from_raw(&raw, metadata(Some(20), 5)).unwrap();
+    assert_eq!(received.data.as_slice(), &ACK);
+    assert_eq!(received.metadata.rssi, Some(-42));
+    assert_eq!(received.metadata.lqi, Some(0xd4));
+    assert_eq!(received.metadata.channel, Some(20));
+    assert_eq!(received.metadata.timestamp, 5);
+
+    for size in [0, 2, 128, 255] {
+        raw[0] = size;
+        assert_eq!(
+            ReceivedFrame::from_raw(&raw, RxMetadata::default()),
+            Err(Error::InvalidLength(usize::from(size)))
+        );
+    }
+    // The link quality is missing
+    raw[0] = 5;
+    assert_eq!(
+        ReceivedFrame::from_raw(&raw[..5], RxMetadata::default()),
+        Err(Error::InvalidLength(5))
+    );
+    assert_eq!(
+        ReceivedFrame::from_raw(&[], RxMetadata::default()),
+        Err(Error::InvalidLength(0))
+    );
+}
@@ -0,0 +1,129 @@
+//! Key derivation known answers and install codes, run with `cargo test-host`.
+
+use esp32c6_psila::{
+    hash_key, install_code_key, mmo_hash, InstallCodeError, LinkKeyEntry, MmoHash, SecurityService,
+};
+use psila_data::{common::key::Key, ExtendedAddress};
+
+/// The default trust center link key, "ZigBeeAlliance09"
+const DEFAULT_LINK_KEY: [u8; 16] = *b"ZigBeeAlliance09";
+
+#[test]
+fn mmo_hash_vectors() {
+    // Zigbee specification C.5, a single byte and a full block
+    assert_eq!(
+        mmo_hash(&[0xc0]),
+        [
+            0xae, 0x3a, 0x10, 0x2a, 0x28, 0xd4, 0x3e, 0xe0, 0xd4, 0xa0, 0x9e, 0x22, 0x78, 0x8b,
+            0x20, 0x6c
+        ]
+    );
+    let message = (0xc0..=0xdf).collect::<Vec<u8>>();
+    assert_eq!(
+        mmo_hash(&message[..16]),
+        [
+            0xa7, 0x97, 0x7e, 0x88, 0xbc, 0x0b, 0x61, 0xe8, 0x21, 0x08, 0x27, 0x10, 0x9a, 0x22,
+            0x8f, 0x2d
+        ]
+    );
+    // The padding does not fit in the last block
+    assert_eq!(
+        mmo_hash(&message[..15]),
+        [
+            0x0e, 0xd9, 0xe3, 0x56, 0x68, 0xfe, 0x9e, 0x54, 0x6f, 0x25, 0x27, 0x1e, 0x36, 0xc6,
+            0xa5, 0xbc
+        ]
+    );
+    let expected = [
+        0xb9, 0xb3, 0xd7, 0x76, 0x30, 0x24, 0x13, 0x17, 0xb8, 0x1c, 0x0d, 0x82, 0x70, 0x7c, 0xc3,
+        0x07,
+    ];
+    assert_eq!(mmo_hash(&message), expected);
+
+    // Hashing in parts gives the same result
+    let mut hash = MmoHash::new();
+    for part in message.chunks(7) {
+        hash.update(part);
+    }
+    assert_eq!(hash.finalize(), expected);
+}
+
+#[test]
+fn hash_key_vectors() {
+    let key = Key::from(DEFAULT_LINK_KEY);
+    // Key-transport key
+    assert_eq!(
+        hash_key(&key, 0x00),
+        Key::from([
+            0x4b, 0xab, 0x0f, 0x17, 0x3e, 0x14, 0x34, 0xa2, 0xd5, 0x72, 0xe1, 0xc1, 0xef, 0x47,
+            0x87, 0x82
+        ])
+    );
+    // Key-load key
+    assert_eq!(
+        hash_key(&key, 0x02),
+        Key::from([
+            0xc5, 0xa4, 0x70, 0x35, 0xc3, 0x32, 0xcc, 0xbf, 0x25, 0x15, 0x71, 0xd8, 0xba, 0xde,
+            0xd1, 0x88
+        ])
+    );
+}
+
+/// Install code example from the Zigbee specification, including the CRC
+const INSTALL_CODE: [u8; 18] = [
+    0x83, 0xfe, 0xd3, 0x40, 0x7a, 0x93, 0x97, 0x23, 0xa5, 0xc6, 0x39, 0xb2, 0x69, 0x16, 0xd5, 0x05,
+    0xc3, 0xb5,
+];
+
+const INSTALL_CODE_KEY: [u8; 16] = [
+    0x66, 0xb6, 0x90, 0x09, 0x81, 0xe1, 0xee, 0x3c, 0xa4, 0x20, 0x6b, 0x6b, 0x86, 0x1c, 0x02, 0xbb,
+];
+
+#[test]
+fn install_code() {
+    assert_eq!(
+        install_code_key(&INSTALL_CODE),
+        Ok(Key::from(INSTALL_CODE_KEY))
+    );
+
+    let mut code = INSTALL_CODE;
+    code[17] ^= 0x01;
+    assert_eq!(install_code_key(&code), Err(InstallCodeError::InvalidCrc));
+    code[17] ^= 0x01;
+    code[0] ^= 0x01;
+    assert_eq!(install_code_key(&code), Err(InstallCodeError::InvalidCrc));
+
+    for size in [0, 2, 7, 9, 15, 17] {
+        assert_eq!(
+            install_code_key(&INSTALL_CODE[..size]),
+            Err(InstallCodeError::InvalidLength),
+            "size {}",
+            size
+        );
+    }
+    assert_eq!(
+        install_code_key(&[0u8; 19]),
+        Err(InstallCodeError::InvalidLength)
+    );
+}
+
+#[test]
+fn add_install_code() {
+    let address = ExtendedAddress::from(0x0011_2233_4455_6677u64);
+    let mut service = SecurityService::new();
+    assert_eq!(
+        service.add_install_code(address, &INSTALL_CODE),
+        Ok(Key::from(INSTALL_CODE_KEY))
+    );
+    assert!(service.link_keys.contains(&LinkKeyEntry {
+        address: Some(address),
+        key: Key::from(INSTALL_CODE_KEY),
+    }));
+
+    let count = service.link_keys.len();
+    assert_eq!(
+        service.add_install_code(address, &INSTALL_CODE[..16]),
+        Err(InstallCodeError::InvalidCrc)
+    );
+    assert_eq!(service.link_keys.len(), count);
+}
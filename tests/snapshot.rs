@@ -0,0 +1,117 @@
+//! Encode and restore the security state, run with `cargo test-host`.
+
+use esp32c6_psila::snapshot::{Error, Snapshot, MAX_SNAPSHOT_SIZE, SNAPSHOT_VERSION};
+use esp32c6_psila::{FrameCounterEntry, SecurityService};
+use psila_data::{common::key::Key, ExtendedAddress};
+
+const NETWORK_KEY: [u8; 16] = [
+    0x01, 0x03, 0x05, 0x07, 0x09, 0x0b, 0x0d, 0x0f, 0x00, 0x02, 0x04, 0x06, 0x08, 0x0a, 0x0c, 0x0d,
+];
+const NEXT_NETWORK_KEY: [u8; 16] = [
+    0xfe, 0xdc, 0xba, 0x98, 0x76, 0x54, 0x32, 0x10, 0xfe, 0xdc, 0xba, 0x98, 0x76, 0x54, 0x32, 0x10,
+];
+const LINK_KEY: [u8; 16] = [
+    0x00, 0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77, 0x88, 0x99, 0xaa, 0xbb, 0xcc, 0xdd, 0xee, 0xff,
+];
+const MAC_KEY: [u8; 16] = [
+    0x10, 0x32, 0x54, 0x76, 0x98, 0xba, 0xdc, 0xfe, 0x10, 0x32, 0x54, 0x76, 0x98, 0xba, 0xdc, 0xfe,
+];
+/// Install code example from the Zigbee specification, including the CRC
+const INSTALL_CODE: [u8; 18] = [
+    0x83, 0xfe, 0xd3, 0x40, 0x7a, 0x93, 0x97, 0x23, 0xa5, 0xc6, 0x39, 0xb2, 0x69, 0x16, 0xd5, 0x05,
+    0xc3, 0xb5,
+];
+
+const DEVICE: u64 = 0x0012_4b00_0102_0304;
+const INSTALLED_DEVICE: u64 = 0x0011_2233_4455_6677;
+
+fn service() -> SecurityService {
+    let mut service = SecurityService::new();
+    service.add_network_key(Key::from(NETWORK_KEY), Some(0));
+    service.add_network_key(Key::from(NEXT_NETWORK_KEY), Some(1));
+    service.add_link_key(Key::from(LINK_KEY), Some(ExtendedAddress::from(DEVICE)));
+    service
+        .add_install_code(ExtendedAddress::from(INSTALLED_DEVICE), &INSTALL_CODE)
+        .unwrap();
+    service.add_mac_key(Key::from(MAC_KEY), Some(1));
+    assert!(service.switch_network_key(0x1a62, 1));
+    assert!(!service.switch_network_key(0x2b73, 2));
+    for (source, sequence, counter) in [
+        (DEVICE, Some(1), 1234),
+        (DEVICE, None, 5),
+        (INSTALLED_DEVICE, Some(0), 0xffff_fffe),
+    ] {
+        service
+            .frame_counters
+            .entries
+            .push(FrameCounterEntry {
+                source: ExtendedAddress::from(source),
+                sequence,
+                counter,
+            })
+            .unwrap();
+    }
+    service
+}
+
+#[test]
+fn round_trip() {
+    let original = service();
+    let snapshot = original.snapshot();
+    let mut buffer = [0u8; MAX_SNAPSHOT_SIZE];
+    let encoded = snapshot.encode(&mut buffer).unwrap();
+    assert_eq!(encoded[0], SNAPSHOT_VERSION);
+    let decoded = Snapshot::decode(encoded).unwrap();
+    assert_eq!(decoded, snapshot);
+
+    let mut restored = SecurityService::new();
+    restored.restore(&decoded);
+    assert_eq!(restored.network_keys, original.network_keys);
+    assert_eq!(restored.link_keys, original.link_keys);
+    assert_eq!(restored.mac_keys, original.mac_keys);
+    assert_eq!(restored.active_network_keys, original.active_network_keys);
+    assert_eq!(
+        restored.frame_counters.entries,
+        original.frame_counters.entries
+    );
+    assert_eq!(restored.active_sequence(0x1a62), Some(1));
+    assert_eq!(
+        restored.active_network_key(0x1a62).map(|e| e.key),
+        Some(Key::from(NEXT_NETWORK_KEY))
+    );
+}
+
+#[test]
+fn trailing_bytes() {
+    let snapshot = service().snapshot();
+    // Erased flash
+    let mut buffer = [0xffu8; MAX_SNAPSHOT_SIZE];
+    let size = snapshot.encode(&mut buffer).unwrap().len();
+    assert!(size < buffer.len());
+    assert_eq!(Snapshot::decode(&buffer).unwrap(), snapshot);
+}
+
+#[test]
+fn unsupported_version() {
+    let snapshot = service().snapshot();
+    let mut buffer = [0u8; MAX_SNAPSHOT_SIZE];
+    let encoded = snapshot.encode(&mut buffer).unwrap();
+    encoded[0] = SNAPSHOT_VERSION + 1;
+    assert_eq!(
+        Snapshot::decode(encoded),
+        Err(Error::UnsupportedVersion(SNAPSHOT_VERSION + 1))
+    );
+}
+
+#[test]
+fn truncated() {
+    let snapshot = service().snapshot();
+    let mut buffer = [0u8; MAX_SNAPSHOT_SIZE];
+    let encoded = snapshot.encode(&mut buffer).unwrap();
+    assert_eq!(Snapshot::decode(&[]), Err(Error::Encoding));
+    assert_eq!(
+        Snapshot::decode(&encoded[..encoded.len() - 1]),
+        Err(Error::Encoding)
+    );
+    assert_eq!(Snapshot::decode(&encoded[..1]), Err(Error::Encoding));
+}
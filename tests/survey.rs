@@ -0,0 +1,129 @@
+//! Survey scripted traffic on two channels, run with `cargo test-host`.
+
+use esp32c6_psila::fcs::FcsStatus;
+use esp32c6_psila::radio::{MemoryRadio, Radio};
+use esp32c6_psila::survey::{Survey, SurveyConfig, CHANNEL_COUNT};
+use esp32c6_psila::RxMetadata;
+
+/// Zigbee PRO beacon from the coordinator of PAN 1a62
+const BEACON: [u8; 26] = [
+    0x00, 0x80, 0x4d, 0x62, 0x1a, 0x00, 0x00, 0xff, 0xcf, 0x00, 0x00, 0x00, 0x22, 0x84, 0x01, 0x02,
+    0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0xff, 0xff, 0xff, 0x00,
+];
+
+/// NWK Leave command from 1234 to the coordinator of PAN 1a62
+const LEAVE: [u8; 19] = [
+    0x41, 0x88, 0x11, 0x62, 0x1a, 0x00, 0x00, 0x34, 0x12, 0x09, 0x00, 0x00, 0x00, 0x34, 0x12, 0x01,
+    0x22, 0x04, 0x00,
+];
+
+/// Data frame cut short in the destination PAN identifier
+const TRUNCATED: [u8; 3] = [0x41, 0x88, 0x12];
+
+/// The beacon of another PAN
+fn beacon(pan: u16, extended_pan_identifier: u64) -> [u8; 26] {
+    let mut beacon = BEACON;
+    beacon[3..5].copy_from_slice(&pan.to_le_bytes());
+    beacon[14..22].copy_from_slice(&extended_pan_identifier.to_le_bytes());
+    beacon
+}
+
+fn metadata(channel: Option<u8>, timestamp: u64, rssi: i8) -> RxMetadata {
+    RxMetadata {
+        rssi: Some(rssi),
+        channel,
+        timestamp,
+        ..RxMetadata::default()
+    }
+}
+
+#[test]
+fn two_channels() {
+    let mut radio = MemoryRadio::<8>::new(100);
+    radio.push(&BEACON, metadata(Some(11), 200, -60)).unwrap();
+    radio.push(&LEAVE, metadata(Some(11), 500, -40)).unwrap();
+    // Sent while listening on channel 11, lost
+    radio.push(&LEAVE, metadata(Some(15), 600, -30)).unwrap();
+    radio
+        .push(
+            &beacon(0x2b73, 0x1122_3344_5566_7788),
+            metadata(Some(15), 1500, -70),
+        )
+        .unwrap();
+    // Second round
+    radio
+        .push(
+            &BEACON,
+            RxMetadata {
+                fcs: FcsStatus::Bad,
+                ..metadata(Some(11), 3500, -20)
+            },
+        )
+        .unwrap();
+    radio
+        .push(&TRUNCATED, metadata(Some(15), 4500, -75))
+        .unwrap();
+    // Frames without channel are received on any channel
+    radio.push(&LEAVE, metadata(None, 5000, -65)).unwrap();
+
+    let mut channel_dwell_time = [None; CHANNEL_COUNT];
+    channel_dwell_time[15 - 11] = Some(2000);
+    let config = SurveyConfig {
+        channels: (1 << 11) | (1 << 15),
+        dwell_time: 1000,
+        channel_dwell_time,
+        rounds: 2,
+    };
+    assert_eq!(config.dwell_time(11), 1000);
+    assert_eq!(config.dwell_time(15), 2000);
+
+    let mut survey = Survey::new(config);
+    let summary = survey.run(&mut radio).unwrap().clone();
+    assert_eq!(survey.channel(), None);
+    assert_eq!(radio.pending(), 0);
+    // Two rounds of 1 ms on channel 11 and 2 ms on channel 15
+    assert_eq!(radio.now(), 6000);
+    assert_eq!(summary.channels.len(), 2);
+    assert!(summary.channel(12).is_none());
+
+    let first = summary.channel(11).unwrap();
+    assert_eq!(first.listened, 2000);
+    assert_eq!(first.frames, 3);
+    assert_eq!(first.bytes, 26 + 19 + 26);
+    assert_eq!(first.bad_fcs, 1);
+    assert_eq!(first.malformed, 0);
+    // The frame with bad FCS is not inspected
+    assert_eq!(first.beacons, 1);
+    assert_eq!(first.strongest_rssi, Some(-40));
+    assert_eq!(first.pan_identifiers.as_slice(), &[0x1a62]);
+    assert_eq!(
+        first.extended_pan_identifiers.as_slice(),
+        &[0x0807_0605_0403_0201]
+    );
+
+    let second = summary.channel(15).unwrap();
+    assert_eq!(second.listened, 4000);
+    assert_eq!(second.frames, 3);
+    assert_eq!(second.bytes, 26 + 3 + 19);
+    assert_eq!(second.bad_fcs, 0);
+    assert_eq!(second.malformed, 1);
+    assert_eq!(second.beacons, 1);
+    assert_eq!(second.strongest_rssi, Some(-65));
+    assert_eq!(second.pan_identifiers.as_slice(), &[0x2b73, 0x1a62]);
+    assert_eq!(
+        second.extended_pan_identifiers.as_slice(),
+        &[0x1122_3344_5566_7788]
+    );
+}
+
+#[test]
+fn no_channels() {
+    let mut radio = MemoryRadio::<1>::new(100);
+    let config = SurveyConfig {
+        channels: 0,
+        ..SurveyConfig::default()
+    };
+    let mut survey = Survey::new(config);
+    assert!(survey.run(&mut radio).unwrap().channels.is_empty());
+    assert_eq!(radio.now(), 0);
+}
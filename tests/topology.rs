@@ -0,0 +1,205 @@
+//! Learn the mesh from routing traffic and export it, run with `cargo test-host`.
+
+use esp32c6_psila::topology::{Link, LinkSource, Node, Topology};
+use esp32c6_psila::{Parser, RxMetadata};
+
+/// Unsecured NWK frame in a MAC data frame, `frame_control` is the low byte of the NWK frame
+/// control, 0x08 for data and 0x09 for commands
+fn frame(
+    mac_destination: u16,
+    mac_source: u16,
+    frame_control: u8,
+    destination: u16,
+    source: u16,
+    payload: &[u8],
+) -> Vec<u8> {
+    let mut data = vec![0x41, 0x88, 0x01, 0x62, 0x1a];
+    data.extend_from_slice(&mac_destination.to_le_bytes());
+    data.extend_from_slice(&mac_source.to_le_bytes());
+    data.extend_from_slice(&[frame_control, 0x00]);
+    data.extend_from_slice(&destination.to_le_bytes());
+    data.extend_from_slice(&source.to_le_bytes());
+    data.extend_from_slice(&[0x1e, 0x01]);
+    data.extend_from_slice(payload);
+    data
+}
+
+fn update(topology: &mut Topology, data: &[u8]) {
+    let mut parser = Parser::new();
+    let decoded = parser.parse_frame(data, &RxMetadata::default()).unwrap();
+    assert!(decoded.error.is_none());
+    topology.update(&decoded);
+}
+
+fn link(topology: &Topology, from: u16, to: u16) -> Option<Link> {
+    topology
+        .links
+        .iter()
+        .find(|l| l.from == from && l.to == to)
+        .copied()
+}
+
+/// Link Status from 1234, coordinator with incoming cost 1 and outgoing cost 3, 5678 with
+/// incoming cost 5 and outgoing cost 7
+const LINK_STATUS: [u8; 8] = [0x08, 0x62, 0x00, 0x00, 0x31, 0x78, 0x56, 0x75];
+
+#[test]
+fn link_status() {
+    let mut topology = Topology::new();
+    update(
+        &mut topology,
+        &frame(0xffff, 0x1234, 0x09, 0xfffc, 0x1234, &LINK_STATUS),
+    );
+    assert_eq!(
+        topology.nodes.iter().map(|n| n.address).collect::<Vec<_>>(),
+        [0x1234, 0x0000, 0x5678]
+    );
+    // Outgoing costs are for links from the sender, incoming costs for links towards it
+    for (from, to, cost) in [
+        (0x1234, 0x0000, 3),
+        (0x0000, 0x1234, 1),
+        (0x1234, 0x5678, 7),
+        (0x5678, 0x1234, 5),
+    ] {
+        let link = link(&topology, from, to).unwrap();
+        assert_eq!(link.cost, Some(cost), "{:04x} -> {:04x}", from, to);
+        assert_eq!(link.source, LinkSource::LinkStatus);
+    }
+    assert_eq!(topology.links.len(), 4);
+}
+
+#[test]
+fn route_record() {
+    // Relayed by 5678, on the last hop to the concentrator
+    let mut topology = Topology::new();
+    let route_record = [0x05, 0x01, 0x78, 0x56];
+    update(
+        &mut topology,
+        &frame(0x0000, 0x5678, 0x09, 0x0000, 0x1234, &route_record),
+    );
+    for (from, to) in [(0x1234, 0x5678), (0x5678, 0x0000)] {
+        let link = link(&topology, from, to).unwrap();
+        assert_eq!(link.source, LinkSource::Route);
+        assert_eq!(link.cost, None);
+    }
+    assert_eq!(topology.links.len(), 2);
+
+    // Still on the way to the concentrator, the last hop is unknown
+    let mut topology = Topology::new();
+    update(
+        &mut topology,
+        &frame(0x9abc, 0x5678, 0x09, 0x0000, 0x1234, &route_record),
+    );
+    assert!(link(&topology, 0x1234, 0x5678).is_some());
+    assert!(link(&topology, 0x5678, 0x0000).is_none());
+    assert_eq!(topology.links.len(), 1);
+
+    // Route hops keep the costs learned from Link Status
+    let mut topology = Topology::new();
+    update(
+        &mut topology,
+        &frame(0xffff, 0x1234, 0x09, 0xfffc, 0x1234, &LINK_STATUS),
+    );
+    update(
+        &mut topology,
+        &frame(0x0000, 0x1234, 0x09, 0x0000, 0x1234, &[0x05, 0x00]),
+    );
+    let link = link(&topology, 0x1234, 0x0000).unwrap();
+    assert_eq!(link.cost, Some(3));
+    assert_eq!(link.source, LinkSource::LinkStatus);
+}
+
+#[test]
+fn neighbor_table() {
+    // Mgmt_Lqi_rsp from the coordinator listing 5678 as a router sibling
+    let mut payload = vec![0x00, 0x00, 0x31, 0x80, 0x00, 0x00, 0x00, 0x05];
+    payload.extend_from_slice(&[0x17, 0x00, 0x01, 0x00, 0x01]);
+    payload.extend_from_slice(&0x0807_0605_0403_0201u64.to_le_bytes());
+    payload.extend_from_slice(&0x0011_2233_4455_6677u64.to_le_bytes());
+    payload.extend_from_slice(&[0x78, 0x56, 0x25, 0x02, 0x01, 0xa5]);
+    let mut topology = Topology::new();
+    update(
+        &mut topology,
+        &frame(0x1234, 0x0000, 0x08, 0x1234, 0x0000, &payload),
+    );
+    assert_eq!(
+        topology.node(0x5678).unwrap().extended_address,
+        Some(0x0011_2233_4455_6677)
+    );
+    let link = link(&topology, 0x5678, 0x0000).unwrap();
+    assert_eq!(link.source, LinkSource::NeighborTable);
+    assert_eq!(link.link_quality, Some(0xa5));
+    assert_eq!(link.cost, None);
+    assert_eq!(topology.links.len(), 1);
+}
+
+fn example() -> Topology {
+    let mut topology = Topology::new();
+    for (address, extended_address, last_update) in
+        [(0x0000, Some(0x0011_2233_4455_6677), 1), (0x1234, None, 2)]
+    {
+        topology
+            .nodes
+            .push(Node {
+                address,
+                extended_address,
+                last_update,
+            })
+            .unwrap();
+    }
+    for (from, to, cost, link_quality, source) in [
+        (0x1234, 0x0000, Some(3), None, LinkSource::LinkStatus),
+        (0x0000, 0x1234, None, Some(200), LinkSource::NeighborTable),
+        (0x1234, 0x5678, None, None, LinkSource::Route),
+    ] {
+        topology
+            .links
+            .push(Link {
+                from,
+                to,
+                cost,
+                link_quality,
+                source,
+                last_update: 5,
+            })
+            .unwrap();
+    }
+    topology
+}
+
+#[test]
+fn dot() {
+    let mut output = String::new();
+    example().write_dot(&mut output).unwrap();
+    assert_eq!(
+        output,
+        concat!(
+            "digraph zigbee {\n",
+            "  \"0000\" [label=\"0000\\n0011223344556677\"];\n",
+            "  \"1234\" [label=\"1234\"];\n",
+            "  \"1234\" -> \"0000\" [label=\"3\"];\n",
+            "  \"0000\" -> \"1234\" [label=\"LQI 200\"];\n",
+            "  \"1234\" -> \"5678\" [style=dashed];\n",
+            "}\n",
+        )
+    );
+}
+
+#[test]
+fn json() {
+    let mut output = String::new();
+    example().write_json(&mut output).unwrap();
+    assert_eq!(
+        output,
+        concat!(
+            r#"{"nodes":["#,
+            r#"{"address":"0000","extended_address":"0011223344556677","last_update":1},"#,
+            r#"{"address":"1234","extended_address":null,"last_update":2}"#,
+            r#"],"links":["#,
+            r#"{"from":"1234","to":"0000","source":"link-status","cost":3,"link_quality":null,"last_update":5},"#,
+            r#"{"from":"0000","to":"1234","source":"neighbor-table","cost":null,"link_quality":200,"last_update":5},"#,
+            r#"{"from":"1234","to":"5678","source":"route","cost":null,"link_quality":null,"last_update":5}"#,
+            "]}\n",
+        )
+    );
+}
@@ -0,0 +1,113 @@
+//! Decode ZCL frames, run with `cargo test-host`.
+
+use esp32c6_psila::zcl::{
+    Attribute, AttributeStatus, AttributeValue, ClusterCommand, Command, Direction, Frame,
+    FrameType, Record, CLUSTER_BASIC, CLUSTER_LEVEL_CONTROL, CLUSTER_ON_OFF,
+    CLUSTER_TEMPERATURE_MEASUREMENT,
+};
+use psila_data::Error;
+
+const HOME_AUTOMATION: u16 = 0x0104;
+
+#[test]
+fn read_attributes_response() {
+    // Manufacturer name "IKEA" and an unsupported model identifier
+    let data = [
+        0x18, 0x42, 0x01, 0x04, 0x00, 0x00, 0x42, 0x04, b'I', b'K', b'E', b'A', 0x05, 0x00, 0x86,
+    ];
+    let frame = Frame::unpack(HOME_AUTOMATION, CLUSTER_BASIC, &data).unwrap();
+    assert_eq!(frame.header.frame_type, FrameType::Global);
+    assert_eq!(frame.header.direction, Direction::ServerToClient);
+    assert!(frame.header.disable_default_response);
+    assert_eq!(frame.header.manufacturer_code, None);
+    assert_eq!(frame.header.transaction_sequence, 0x42);
+    let Ok(Command::ReadAttributesResponse(records)) = frame.command() else {
+        panic!("No read attributes response");
+    };
+    let records = records.collect::<Result<Vec<_>, _>>().unwrap();
+    assert_eq!(
+        records,
+        [
+            Record::Status(AttributeStatus {
+                identifier: 0x0004,
+                status: 0x00,
+                attribute: Some(Attribute {
+                    identifier: 0x0004,
+                    data_type: 0x42,
+                    value: AttributeValue::CharacterString(b"IKEA"),
+                }),
+            }),
+            Record::Status(AttributeStatus {
+                identifier: 0x0005,
+                status: 0x86,
+                attribute: None,
+            }),
+        ]
+    );
+}
+
+#[test]
+fn report_attributes() {
+    // Measured value 21.5 C and an attribute cut short
+    let data = [
+        0x18, 0x07, 0x0a, 0x00, 0x00, 0x29, 0x66, 0x08, 0x01, 0x00, 0x29, 0x66,
+    ];
+    let frame = Frame::unpack(HOME_AUTOMATION, CLUSTER_TEMPERATURE_MEASUREMENT, &data).unwrap();
+    let Ok(Command::ReportAttributes(mut records)) = frame.command() else {
+        panic!("No report attributes");
+    };
+    assert_eq!(
+        records.next().unwrap().unwrap(),
+        Record::Attribute(Attribute {
+            identifier: 0x0000,
+            data_type: 0x29,
+            value: AttributeValue::Signed(2150),
+        })
+    );
+    assert!(matches!(
+        records.next(),
+        Some(Err(Error::WrongNumberOfBytes))
+    ));
+    assert!(records.next().is_none());
+}
+
+#[test]
+fn cluster_commands() {
+    let frame = Frame::unpack(HOME_AUTOMATION, CLUSTER_ON_OFF, &[0x11, 0x10, 0x02]).unwrap();
+    assert_eq!(frame.header.frame_type, FrameType::ClusterSpecific);
+    assert_eq!(frame.header.direction, Direction::ClientToServer);
+    assert!(matches!(
+        frame.command(),
+        Ok(Command::Cluster(ClusterCommand::Toggle))
+    ));
+
+    // Move to level with on/off, level 128 over 1 second
+    let data = [0x01, 0x11, 0x04, 0x80, 0x0a, 0x00];
+    let frame = Frame::unpack(HOME_AUTOMATION, CLUSTER_LEVEL_CONTROL, &data).unwrap();
+    assert!(matches!(
+        frame.command(),
+        Ok(Command::Cluster(ClusterCommand::MoveToLevel {
+            level: 0x80,
+            transition_time: 10,
+            with_on_off: true,
+        }))
+    ));
+
+    // Manufacturer specific commands are not decoded
+    let data = [0x05, 0x7c, 0x11, 0x12, 0x00, 0x01];
+    let frame = Frame::unpack(HOME_AUTOMATION, CLUSTER_ON_OFF, &data).unwrap();
+    assert_eq!(frame.header.manufacturer_code, Some(0x117c));
+    assert!(matches!(frame.command(), Ok(Command::Other(&[0x01]))));
+}
+
+#[test]
+fn errors() {
+    assert!(Frame::unpack(HOME_AUTOMATION, CLUSTER_ON_OFF, &[0x01, 0x10]).is_err());
+    assert!(matches!(
+        Frame::unpack(HOME_AUTOMATION, CLUSTER_ON_OFF, &[0x03, 0x10, 0x02]),
+        Err(Error::UnknownFrameType)
+    ));
+    // Default response without a status
+    let frame = Frame::unpack(HOME_AUTOMATION, CLUSTER_ON_OFF, &[0x18, 0x10, 0x0b, 0x02]).unwrap();
+    assert!(frame.command().is_err());
+}
@@ -0,0 +1,181 @@
+//! Decode ZDO messages, run with `cargo test-host`.
+
+use esp32c6_psila::zdo::{
+    Entry, Frame, LogicalType, Message, Neighbor, Relationship, RouteStatus, DEVICE_ANNOUNCE,
+    MANAGEMENT_LQI_RESPONSE, MANAGEMENT_NETWORK_UPDATE_REQUEST, MANAGEMENT_ROUTING_RESPONSE,
+    MATCH_DESCRIPTOR_REQUEST,
+};
+use psila_data::Error;
+
+const EXTENDED_PAN_IDENTIFIER: u64 = 0x0807_0605_0403_0201;
+
+/// Neighbor table entry as sent in a Mgmt_Lqi_rsp
+fn neighbor(extended_address: u64, network_address: u16, flags: u8, depth: u8, lqi: u8) -> Vec<u8> {
+    let mut data = EXTENDED_PAN_IDENTIFIER.to_le_bytes().to_vec();
+    data.extend_from_slice(&extended_address.to_le_bytes());
+    data.extend_from_slice(&network_address.to_le_bytes());
+    data.extend_from_slice(&[flags, 0x02, depth, lqi]);
+    data
+}
+
+#[test]
+fn management_lqi_response() {
+    // Transaction sequence, status, 3 entries in total, starting at 0 with 2 entries
+    let mut data = vec![0x17, 0x00, 0x03, 0x00, 0x02];
+    // Router sibling with the receiver on and an end device child with the receiver off
+    data.extend(neighbor(0x0011_2233_4455_6677, 0x1234, 0x25, 1, 0xa5));
+    data.extend(neighbor(0x8899_aabb_ccdd_eeff, 0x5678, 0x12, 2, 0x40));
+    let frame = Frame::unpack(MANAGEMENT_LQI_RESPONSE, &data).unwrap();
+    assert_eq!(frame.transaction_sequence, 0x17);
+    let Ok(Message::ManagementLqiResponse(table)) = frame.message() else {
+        panic!("No Mgmt_Lqi_rsp");
+    };
+    assert_eq!(table.status, 0);
+    assert_eq!(table.total, 3);
+    assert_eq!(table.start_index, 0);
+    assert_eq!(table.entries.len(), 2);
+    let entries = table.entries.collect::<Result<Vec<_>, _>>().unwrap();
+    assert_eq!(
+        entries,
+        [
+            Entry::Neighbor(Neighbor {
+                extended_pan_identifier: EXTENDED_PAN_IDENTIFIER,
+                extended_address: 0x0011_2233_4455_6677,
+                network_address: 0x1234,
+                device_type: LogicalType::Router,
+                receiver_on_when_idle: 1,
+                relationship: Relationship::Sibling,
+                permit_joining: 2,
+                depth: 1,
+                link_quality: 0xa5,
+            }),
+            Entry::Neighbor(Neighbor {
+                extended_pan_identifier: EXTENDED_PAN_IDENTIFIER,
+                extended_address: 0x8899_aabb_ccdd_eeff,
+                network_address: 0x5678,
+                device_type: LogicalType::EndDevice,
+                receiver_on_when_idle: 0,
+                relationship: Relationship::Child,
+                permit_joining: 2,
+                depth: 2,
+                link_quality: 0x40,
+            }),
+        ]
+    );
+
+    // The count claims an entry that is not there
+    data[4] = 3;
+    let frame = Frame::unpack(MANAGEMENT_LQI_RESPONSE, &data).unwrap();
+    let Ok(Message::ManagementLqiResponse(table)) = frame.message() else {
+        panic!("No Mgmt_Lqi_rsp");
+    };
+    let mut entries = table.entries;
+    assert!(entries.next().unwrap().is_ok());
+    assert!(entries.next().unwrap().is_ok());
+    assert!(matches!(
+        entries.next(),
+        Some(Err(Error::WrongNumberOfBytes))
+    ));
+    assert!(entries.next().is_none());
+
+    // A failed request carries no table
+    let frame = Frame::unpack(MANAGEMENT_LQI_RESPONSE, &[0x18, 0x84]).unwrap();
+    let Ok(Message::ManagementLqiResponse(table)) = frame.message() else {
+        panic!("No Mgmt_Lqi_rsp");
+    };
+    assert_eq!(table.status, 0x84);
+    assert!(table.entries.is_empty());
+}
+
+#[test]
+fn management_routing_response() {
+    // One many-to-one route to 0000 through 1234
+    let data = [0x20, 0x00, 0x01, 0x00, 0x01, 0x00, 0x00, 0x10, 0x34, 0x12];
+    let frame = Frame::unpack(MANAGEMENT_ROUTING_RESPONSE, &data).unwrap();
+    let Ok(Message::ManagementRoutingResponse(mut table)) = frame.message() else {
+        panic!("No Mgmt_Rtg_rsp");
+    };
+    let Some(Ok(Entry::Route(route))) = table.entries.next() else {
+        panic!("No route");
+    };
+    assert_eq!(route.destination, 0x0000);
+    assert_eq!(route.status, RouteStatus::Active);
+    assert!(route.many_to_one);
+    assert!(!route.memory_constrained);
+    assert_eq!(route.next_hop, 0x1234);
+    assert!(table.entries.next().is_none());
+}
+
+#[test]
+fn requests() {
+    let data = [
+        0x01, 0x34, 0x12, 0x77, 0x66, 0x55, 0x44, 0x33, 0x22, 0x11, 0x00, 0x8e,
+    ];
+    let frame = Frame::unpack(DEVICE_ANNOUNCE, &data).unwrap();
+    let Ok(Message::DeviceAnnounce {
+        network_address,
+        extended_address,
+        capability,
+    }) = frame.message()
+    else {
+        panic!("No device announce");
+    };
+    assert_eq!(network_address, 0x1234);
+    assert_eq!(extended_address, 0x0011_2233_4455_6677);
+    assert!(capability.full_function_device);
+    assert!(capability.mains_power);
+    assert!(capability.receiver_on_when_idle);
+    assert!(!capability.security);
+    assert!(capability.allocate_address);
+
+    // Any device with an On/Off server
+    let data = [0x02, 0xfd, 0xff, 0x04, 0x01, 0x01, 0x06, 0x00, 0x00];
+    let frame = Frame::unpack(MATCH_DESCRIPTOR_REQUEST, &data).unwrap();
+    let Ok(Message::MatchDescriptorRequest {
+        address,
+        profile,
+        input_clusters,
+        output_clusters,
+    }) = frame.message()
+    else {
+        panic!("No match descriptor request");
+    };
+    assert_eq!(address, 0xfffd);
+    assert_eq!(profile, 0x0104);
+    assert_eq!(input_clusters.iter().collect::<Vec<_>>(), [0x0006]);
+    assert!(output_clusters.is_empty());
+
+    // Change the network update identifier, no scan count
+    let data = [0x03, 0x00, 0x80, 0x00, 0x00, 0xfe, 0x05];
+    let frame = Frame::unpack(MANAGEMENT_NETWORK_UPDATE_REQUEST, &data).unwrap();
+    let Ok(Message::ManagementNetworkUpdateRequest {
+        channels,
+        scan_duration,
+        scan_count,
+        update_identifier,
+        manager_address,
+    }) = frame.message()
+    else {
+        panic!("No network update request");
+    };
+    assert_eq!(channels, 1 << 15);
+    assert_eq!(scan_duration, 0xfe);
+    assert_eq!(scan_count, None);
+    assert_eq!(update_identifier, Some(5));
+    assert_eq!(manager_address, None);
+}
+
+#[test]
+fn other() {
+    let frame = Frame::unpack(0x0040, &[0x04, 0x01, 0x02]).unwrap();
+    assert!(matches!(
+        frame.message(),
+        Ok(Message::Other {
+            cluster: 0x0040,
+            payload: &[0x01, 0x02],
+        })
+    ));
+    assert!(Frame::unpack(DEVICE_ANNOUNCE, &[]).is_err());
+    let frame = Frame::unpack(DEVICE_ANNOUNCE, &[0x05, 0x34, 0x12]).unwrap();
+    assert!(frame.message().is_err());
+}
@@ -0,0 +1,70 @@
+//! Encode ZEP datagrams, run with `cargo test-host`.
+
+use esp32c6_psila::fcs::{self, FcsStatus};
+use esp32c6_psila::zep::{Error, ZepEncoder, FCS_SIZE, HEADER_SIZE};
+use esp32c6_psila::RxMetadata;
+
+/// MAC acknowledgement
+const ACK: [u8; 3] = [0x02, 0x00, 0x11];
+
+#[test]
+fn encode() {
+    let mut encoder = ZepEncoder::new(0x1234);
+    let metadata = RxMetadata {
+        channel: Some(25),
+        lqi: Some(200),
+        // One and a half second past the UNIX epoch
+        timestamp: 1_500_000,
+        ..RxMetadata::default()
+    };
+    let mut output = [0u8; HEADER_SIZE + 8];
+    let size = encoder.encode(&metadata, &ACK, &mut output).unwrap();
+    assert_eq!(size, HEADER_SIZE + ACK.len() + FCS_SIZE);
+    assert_eq!(&output[0..4], b"EX\x02\x01");
+    assert_eq!(output[4], 25);
+    assert_eq!(&output[5..7], &[0x12, 0x34]);
+    assert_eq!(output[7], 1);
+    assert_eq!(output[8], 200);
+    assert_eq!(&output[9..13], &2_208_988_801u32.to_be_bytes());
+    assert_eq!(&output[13..17], &0x8000_0000u32.to_be_bytes());
+    assert_eq!(&output[17..21], &[0, 0, 0, 0]);
+    assert_eq!(output[31], 5);
+    assert_eq!(&output[HEADER_SIZE..HEADER_SIZE + 3], &ACK);
+    assert_eq!(&output[HEADER_SIZE + 3..size], &fcs::fcs(&ACK));
+
+    // The sequence number increases with each datagram
+    encoder.encode(&metadata, &ACK, &mut output).unwrap();
+    assert_eq!(&output[17..21], &[0, 0, 0, 1]);
+}
+
+#[test]
+fn bad_fcs() {
+    let mut encoder = ZepEncoder::new(0);
+    let metadata = RxMetadata {
+        fcs: FcsStatus::Bad,
+        ..RxMetadata::default()
+    };
+    let mut output = [0u8; HEADER_SIZE + 8];
+    let size = encoder.encode(&metadata, &ACK, &mut output).unwrap();
+    let check = fcs::check(
+        &output[HEADER_SIZE..size - FCS_SIZE],
+        &output[size - FCS_SIZE..size],
+    );
+    assert_eq!(check, FcsStatus::Bad);
+    assert_eq!(output[8], 255);
+}
+
+#[test]
+fn errors() {
+    let mut encoder = ZepEncoder::new(0);
+    let mut output = [0u8; HEADER_SIZE + 4];
+    assert_eq!(
+        encoder.encode(&RxMetadata::default(), &ACK, &mut output),
+        Err(Error::NotEnoughSpace)
+    );
+    let mut output = [0u8; HEADER_SIZE + 300];
+    assert_eq!(
+        encoder.encode(&RxMetadata::default(), &[0u8; 254], &mut output),
+        Err(Error::FrameTooLarge)
+    );
+}